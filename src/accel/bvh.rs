@@ -0,0 +1,435 @@
+use crate::geometry::{Bounds3, Point3, Ray, Vec3};
+use crate::interaction::SurfaceInteraction;
+use crate::object::Object;
+
+// Nodes with at most this many primitives are never split.
+const MAX_PRIMS_IN_NODE: usize = 4;
+
+// Number of buckets that the centroids are binned into when evaluating the surface area
+// heuristic.
+const NUM_BUCKETS: usize = 12;
+
+// Cost of traversing a node, relative to the cost of intersecting a primitive.
+const TRAVERSAL_COST: f32 = 0.125;
+
+// A part of an object, such as one triangle of a mesh. Meshes can have millions of these, so
+// they're kept small.
+#[derive(Clone, Copy)]
+struct Primitive {
+    obj: u32,
+    part: u32,
+}
+
+struct PrimInfo {
+    prim: Primitive,
+    bounds: Bounds3,
+    centroid: Point3,
+}
+
+enum NodeKind {
+    // Covers prims[first..first + count].
+    Leaf { first: usize, count: usize },
+    // The first child directly follows its parent in the node array.
+    Interior { second_child: usize, axis: usize },
+}
+
+struct Node {
+    bounds: Bounds3,
+    kind: NodeKind,
+}
+
+// A bounding volume hierarchy over the parts of a list of objects, built using the surface area
+// heuristic. The nodes are stored flattened in depth-first order. Objects with infinite bounds,
+// such as planes, are kept out of the hierarchy and tested against every ray.
+pub struct Bvh {
+    objs: Vec<Object>,
+    // The primitives in the hierarchy, followed by the unbounded ones from |unbounded_start| on.
+    prims: Vec<Primitive>,
+    unbounded_start: usize,
+    nodes: Vec<Node>,
+}
+
+impl Bvh {
+    pub fn new(objs: Vec<Object>) -> Bvh {
+        let (mut infos, unbounded): (Vec<_>, Vec<_>) = objs.iter()
+            .enumerate()
+            .flat_map(|(idx, obj)| {
+                (0..obj.shape.num_parts()).map(move |part| {
+                    let bounds = obj.shape.part_bounds(part);
+                    let prim = Primitive { obj: idx as u32, part };
+                    PrimInfo { prim, bounds, centroid: bounds.centroid() }
+                })
+            })
+            .partition(|info| info.bounds.is_finite());
+
+        // The primitives are ordered so that each leaf covers a contiguous range.
+        let mut prims = Vec::with_capacity(infos.len() + unbounded.len());
+        let mut nodes = Vec::new();
+
+        if !infos.is_empty() {
+            build(&mut infos, &mut prims, &mut nodes);
+        }
+
+        let unbounded_start = prims.len();
+        prims.extend(unbounded.iter().map(|info| info.prim));
+
+        Bvh { objs, prims, unbounded_start, nodes }
+    }
+
+    // Returns the bounds of all the primitives except the unbounded ones.
+    pub fn bounds(&self) -> Bounds3 {
+        self.nodes.first().map_or(Bounds3::empty(), |node| node.bounds)
+    }
+
+    pub fn objs(&self) -> &[Object] {
+        &self.objs
+    }
+
+    // Returns the closest intersection along |ray|, with the object that was hit filled in.
+    pub fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction<'_>> {
+        let mut closest = None;
+        let mut t_max = f32::MAX;
+
+        self.traverse(ray, |obj, part| {
+            if let Some(mut si) = obj.shape.intersect_part(part, ray) {
+                if si.t < t_max {
+                    t_max = si.t;
+                    si.obj = Some(obj);
+                    closest = Some(si);
+                }
+            }
+            (t_max, false)
+        });
+
+        closest
+    }
+
+    // Returns whether |ray| hits any object with t < |t_max|. Cheaper than intersect() since the
+    // traversal stops at the first hit.
+    pub fn intersect_p(&self, ray: &Ray, t_max: f32) -> bool {
+        let mut hit = false;
+
+        self.traverse(ray, |obj, part| {
+            if let Some(si) = obj.shape.intersect_part(part, ray) {
+                hit = si.t < t_max;
+            }
+            (t_max, hit)
+        });
+
+        hit
+    }
+
+    // Calls |f| with the object and part of every primitive whose node's bounds are hit by |ray|,
+    // visiting nearer children first. |f| returns the current maximum t, which is used to cull
+    // nodes, and whether the traversal should stop.
+    fn traverse<'a, F>(&'a self, ray: &Ray, mut f: F)
+        where F: FnMut(&'a Object, u32) -> (f32, bool) {
+
+        let mut t_max = f32::MAX;
+        for prim in &self.prims[self.unbounded_start..] {
+            let (new_t_max, stop) = f(&self.objs[prim.obj as usize], prim.part);
+            if stop {
+                return;
+            }
+            t_max = new_t_max;
+        }
+
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let inv_d = Vec3::new(1.0 / ray.d.x, 1.0 / ray.d.y, 1.0 / ray.d.z);
+        let dir_is_neg = [inv_d.x < 0.0, inv_d.y < 0.0, inv_d.z < 0.0];
+
+        let mut stack = Vec::with_capacity(64);
+        let mut idx = 0;
+
+        loop {
+            let node = &self.nodes[idx];
+
+            if node.bounds.intersect_p(ray, inv_d, t_max) {
+                match node.kind {
+                    NodeKind::Leaf { first, count } => {
+                        for prim in &self.prims[first..first + count] {
+                            let (new_t_max, stop) = f(&self.objs[prim.obj as usize], prim.part);
+                            if stop {
+                                return;
+                            }
+                            t_max = new_t_max;
+                        }
+                    },
+                    NodeKind::Interior { second_child, axis } => {
+                        if dir_is_neg[axis] {
+                            stack.push(idx + 1);
+                            idx = second_child;
+                        } else {
+                            stack.push(second_child);
+                            idx += 1;
+                        }
+                        continue;
+                    },
+                }
+            }
+
+            match stack.pop() {
+                Some(next) => idx = next,
+                None => break,
+            }
+        }
+    }
+}
+
+// Recursively builds the subtree over |infos|, appending its nodes to |nodes| and its primitives
+// to |prims|. Returns the index of the subtree's root node.
+fn build(infos: &mut [PrimInfo], prims: &mut Vec<Primitive>, nodes: &mut Vec<Node>) -> usize {
+    let bounds = infos.iter().fold(Bounds3::empty(), |b, info| Bounds3::union(b, info.bounds));
+
+    let node_idx = nodes.len();
+    nodes.push(Node { bounds, kind: NodeKind::Leaf { first: 0, count: 0 } });
+
+    let make_leaf = |infos: &[PrimInfo], prims: &mut Vec<Primitive>, nodes: &mut Vec<Node>| {
+        nodes[node_idx].kind = NodeKind::Leaf { first: prims.len(), count: infos.len() };
+        prims.extend(infos.iter().map(|info| info.prim));
+        node_idx
+    };
+
+    let n = infos.len();
+    if n == 1 {
+        return make_leaf(infos, prims, nodes);
+    }
+
+    let centroid_bounds = infos.iter()
+        .fold(Bounds3::empty(), |b, info| Bounds3::union_pt(b, info.centroid));
+    let axis = centroid_bounds.max_extent();
+
+    // All the centroids are at the same position, so there's no good way to split them.
+    if centroid_bounds.max[axis] == centroid_bounds.min[axis] {
+        return make_leaf(infos, prims, nodes);
+    }
+
+    let mid = if n <= 2 || bounds.surface_area() == 0.0 {
+        // Split into equal halves.
+        infos.select_nth_unstable_by(n / 2, |a, b| {
+            a.centroid[axis].partial_cmp(&b.centroid[axis]).unwrap()
+        });
+        n / 2
+    } else {
+        let bucket_of = |info: &PrimInfo| {
+            let b = (NUM_BUCKETS as f32 * centroid_bounds.offset(info.centroid)[axis]) as usize;
+            b.min(NUM_BUCKETS - 1)
+        };
+
+        let mut counts = [0usize; NUM_BUCKETS];
+        let mut bucket_bounds = [Bounds3::empty(); NUM_BUCKETS];
+        for info in infos.iter() {
+            let b = bucket_of(info);
+            counts[b] += 1;
+            bucket_bounds[b] = Bounds3::union(bucket_bounds[b], info.bounds);
+        }
+
+        // Evaluates the cost of splitting after each bucket except the last.
+        let mut min_cost = f32::MAX;
+        let mut min_split = 0;
+        for split in 0..NUM_BUCKETS - 1 {
+            let (mut b0, mut c0) = (Bounds3::empty(), 0);
+            let (mut b1, mut c1) = (Bounds3::empty(), 0);
+            for i in 0..=split {
+                b0 = Bounds3::union(b0, bucket_bounds[i]);
+                c0 += counts[i];
+            }
+            for i in split + 1..NUM_BUCKETS {
+                b1 = Bounds3::union(b1, bucket_bounds[i]);
+                c1 += counts[i];
+            }
+
+            let cost = TRAVERSAL_COST +
+                       (c0 as f32 * b0.surface_area() + c1 as f32 * b1.surface_area()) /
+                       bounds.surface_area();
+            if cost < min_cost {
+                min_cost = cost;
+                min_split = split;
+            }
+        }
+
+        let leaf_cost = n as f32;
+        if n <= MAX_PRIMS_IN_NODE && min_cost >= leaf_cost {
+            return make_leaf(infos, prims, nodes);
+        }
+
+        // Partition the primitives so that those in buckets up to |min_split| come first. Both
+        // sides are non-empty since the min and max centroids fall in the first and last buckets.
+        let mut mid = 0;
+        for i in 0..n {
+            if bucket_of(&infos[i]) <= min_split {
+                infos.swap(i, mid);
+                mid += 1;
+            }
+        }
+        mid
+    };
+
+    let (left, right) = infos.split_at_mut(mid);
+    build(left, prims, nodes);
+    let second_child = build(right, prims, nodes);
+
+    nodes[node_idx].kind = NodeKind::Interior { second_child, axis };
+
+    node_idx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::material::Material;
+    use crate::shapes::{Plane, Sphere, Triangle, TriangleMesh};
+
+    // A small deterministic generator so that the test scenes are reproducible.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> f32 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (self.0 >> 40) as f32 / (1u64 << 24) as f32
+        }
+
+        fn range(&mut self, lo: f32, hi: f32) -> f32 {
+            lo + (hi - lo) * self.next()
+        }
+
+        fn point(&mut self, lo: f32, hi: f32) -> Point3 {
+            Point3::new(self.range(lo, hi), self.range(lo, hi), self.range(lo, hi))
+        }
+    }
+
+    fn random_objs(rng: &mut Lcg) -> Vec<Object> {
+        let mut objs = Vec::new();
+        for i in 0..200 {
+            let shape: Arc<dyn crate::shapes::Shape> = if i % 2 == 0 {
+                Arc::new(Sphere { c: rng.point(-10.0, 10.0), r: rng.range(0.1, 1.0) })
+            } else {
+                let p0 = rng.point(-10.0, 10.0);
+                Arc::new(Triangle {
+                    p0,
+                    p1: p0 + (rng.point(-1.0, 1.0) - Point3::zeroes()),
+                    p2: p0 + (rng.point(-1.0, 1.0) - Point3::zeroes()),
+                })
+            };
+            objs.push(Object { shape, material: Material::default() });
+        }
+        objs
+    }
+
+    fn brute_force(objs: &[Object], ray: &Ray) -> Option<f32> {
+        objs.iter()
+            .filter_map(|obj| obj.shape.intersect(ray).map(|si| si.t))
+            .fold(None, |closest: Option<f32>, t| Some(closest.map_or(t, |c| c.min(t))))
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = Lcg(1);
+        let bvh = Bvh::new(random_objs(&mut rng));
+        assert_eq!(bvh.objs().len(), 200);
+
+        let mut num_hits = 0;
+        for i in 0..1000 {
+            // Aim half of the rays at an object so that there are plenty of hits.
+            let p = rng.point(-15.0, 15.0);
+            let d = if i % 2 == 0 {
+                let target = bvh.objs()[(rng.next() * 200.0) as usize % 200].shape.bounds();
+                target.centroid() - p
+            } else {
+                rng.point(-1.0, 1.0) - Point3::zeroes()
+            };
+            let ray = Ray { p, d };
+
+            let expected = brute_force(bvh.objs(), &ray);
+            let actual = bvh.intersect(&ray).map(|si| si.t);
+            assert_eq!(expected, actual);
+
+            assert_eq!(bvh.intersect_p(&ray, f32::MAX), expected.is_some());
+            if let Some(t) = expected {
+                assert!(!bvh.intersect_p(&ray, t * 0.999));
+                num_hits += 1;
+            }
+        }
+
+        // Make sure that the test actually exercises hits.
+        assert!(num_hits > 100);
+    }
+
+    #[test]
+    fn unbounded() {
+        let mut rng = Lcg(2);
+        let mut objs = random_objs(&mut rng);
+        objs.push(Object {
+            shape: Arc::new(Plane::new(Point3::new(0.0, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0))),
+            material: Material::default(),
+        });
+        let bvh = Bvh::new(objs);
+        assert_eq!(bvh.objs().len(), 201);
+        // The plane doesn't make the bounds infinite.
+        assert!(bvh.bounds().is_finite());
+
+        for _ in 0..200 {
+            let ray = Ray { p: rng.point(-15.0, 15.0), d: rng.point(-1.0, 1.0) - Point3::zeroes() };
+            let expected = brute_force(bvh.objs(), &ray);
+            assert_eq!(bvh.intersect(&ray).map(|si| si.t), expected);
+            assert_eq!(bvh.intersect_p(&ray, f32::MAX), expected.is_some());
+        }
+
+        let bvh = Bvh::new(vec![Object {
+            shape: Arc::new(Plane::new(Point3::zeroes(), Vec3::new(0.0, 1.0, 0.0))),
+            material: Material::default(),
+        }]);
+        let ray = Ray { p: Point3::new(0.0, 1.0, 0.0), d: Vec3::new(0.0, -1.0, 0.0) };
+        assert_eq!(bvh.intersect(&ray).map(|si| si.t), Some(1.0));
+        assert!(bvh.bounds().is_empty());
+    }
+
+    // The triangles of a mesh are primitives of a single object, and each one only costs the
+    // hierarchy a Primitive.
+    #[test]
+    fn mesh() {
+        assert_eq!(std::mem::size_of::<Primitive>(), 8);
+
+        let mut rng = Lcg(3);
+        let mut positions = Vec::new();
+        for _ in 0..100 {
+            let p0 = rng.point(-10.0, 10.0);
+            positions.push(p0);
+            positions.push(p0 + (rng.point(-1.0, 1.0) - Point3::zeroes()));
+            positions.push(p0 + (rng.point(-1.0, 1.0) - Point3::zeroes()));
+        }
+        let indices = (0..positions.len() as u32).collect();
+        let mesh = TriangleMesh::new(indices, positions, Vec::new(), Vec::new(), Vec::new());
+        let mut objs = random_objs(&mut rng);
+        objs.push(Object { shape: Arc::new(mesh), material: Material::default() });
+        let bvh = Bvh::new(objs);
+        assert_eq!((bvh.objs().len(), bvh.prims.len()), (201, 300));
+
+        let mut num_mesh_hits = 0;
+        for _ in 0..1000 {
+            // Aim at one of the mesh's triangles.
+            let p = rng.point(-15.0, 15.0);
+            let target = bvh.objs()[200].shape.part_bounds((rng.next() * 100.0) as u32 % 100);
+            let ray = Ray { p, d: target.centroid() - p };
+            let expected = brute_force(bvh.objs(), &ray);
+            let si = bvh.intersect(&ray);
+            assert_eq!(si.as_ref().map(|si| si.t), expected);
+            if si.is_some_and(|si| std::ptr::eq(si.obj.unwrap(), &bvh.objs()[200])) {
+                num_mesh_hits += 1;
+            }
+        }
+        assert!(num_mesh_hits > 500);
+    }
+
+    #[test]
+    fn empty() {
+        let bvh = Bvh::new(Vec::new());
+        let ray = Ray { p: Point3::zeroes(), d: Vec3::new(0.0, 0.0, 1.0) };
+        assert!(bvh.intersect(&ray).is_none());
+        assert!(!bvh.intersect_p(&ray, f32::MAX));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod bvh;
+
+pub use self::bvh::Bvh;
@@ -0,0 +1,65 @@
+use std::f32::consts::PI;
+
+use crate::bxdfs::{Bxdf, BxdfSample};
+use crate::bxdfs::bxdf::{abs_cos_theta, reflect, same_hemisphere};
+use crate::geometry::Vec3;
+use crate::rgb::Rgb;
+
+// Energy-normalized Blinn-Phong glossy reflection. Matches the highlight shape of the specular
+// term in the Whitted integrator.
+pub struct BlinnPhong {
+    pub r: Rgb,
+    pub exponent: f32,
+}
+
+impl Bxdf for BlinnPhong {
+    fn f(&self, wo: Vec3, wi: Vec3) -> Rgb {
+        if !same_hemisphere(wo, wi) {
+            return Rgb::new(0.0, 0.0, 0.0);
+        }
+
+        let wh = wo + wi;
+        if wh.len_sq() == 0.0 {
+            return Rgb::new(0.0, 0.0, 0.0);
+        }
+        let wh = Vec3::normalize(wh);
+
+        let norm = (self.exponent + 8.0) / (8.0 * PI);
+        norm * abs_cos_theta(wh).powf(self.exponent) * self.r
+    }
+
+    fn sample_f(&self, wo: Vec3, u: (f32, f32)) -> Option<BxdfSample> {
+        // Sample the half vector with density proportional to cos(theta_h)^exponent.
+        let cos_theta = u.0.powf(1.0 / (self.exponent + 1.0));
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * u.1;
+
+        let mut wh = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        if wo.z < 0.0 {
+            wh = -wh;
+        }
+
+        let wi = reflect(wo, wh);
+        let pdf = self.pdf(wo, wi);
+        if pdf == 0.0 {
+            return None;
+        }
+
+        Some(BxdfSample { wi, f: self.f(wo, wi), pdf, specular: false })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f32 {
+        if !same_hemisphere(wo, wi) {
+            return 0.0;
+        }
+
+        let wh = wo + wi;
+        if wh.len_sq() == 0.0 {
+            return 0.0;
+        }
+        let wh = Vec3::normalize(wh);
+
+        let pdf_wh = (self.exponent + 1.0) / (2.0 * PI) * abs_cos_theta(wh).powf(self.exponent);
+        pdf_wh / (4.0 * Vec3::dot(wo, wh).abs())
+    }
+}
@@ -0,0 +1,156 @@
+use crate::bxdfs::{Bxdf, BxdfSample};
+use crate::geometry::{Frame, Vec3};
+use crate::rgb::Rgb;
+
+// A collection of BxDFs at a point on a surface. Takes and returns world space directions.
+pub struct Bsdf {
+    frame: Frame,
+    bxdfs: Vec<Box<dyn Bxdf>>,
+}
+
+impl Bsdf {
+    // |frame| is the shading coordinate system, with the shading normal as its z axis.
+    pub fn new(frame: Frame) -> Self {
+        Bsdf {
+            frame,
+            bxdfs: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, bxdf: Box<dyn Bxdf>) {
+        self.bxdfs.push(bxdf);
+    }
+
+    pub fn n(&self) -> Vec3 {
+        self.frame.n
+    }
+
+    // Returns whether every component is specular, in which case f() and pdf() are always zero.
+    pub fn is_specular(&self) -> bool {
+        self.bxdfs.iter().all(|bxdf| bxdf.is_specular())
+    }
+
+    pub fn f(&self, wo: Vec3, wi: Vec3) -> Rgb {
+        let wo = self.frame.world_to_local(wo);
+        let wi = self.frame.world_to_local(wi);
+
+        let mut f = Rgb::new(0.0, 0.0, 0.0);
+        for bxdf in &self.bxdfs {
+            f += bxdf.f(wo, wi);
+        }
+        f
+    }
+
+    pub fn pdf(&self, wo: Vec3, wi: Vec3) -> f32 {
+        if self.bxdfs.is_empty() {
+            return 0.0;
+        }
+
+        let wo = self.frame.world_to_local(wo);
+        let wi = self.frame.world_to_local(wi);
+
+        let pdf: f32 = self.bxdfs.iter().map(|bxdf| bxdf.pdf(wo, wi)).sum();
+        pdf / self.bxdfs.len() as f32
+    }
+
+    // Samples one of the components uniformly using |u|.0, then samples a direction from it. For
+    // non-specular components, the returned value and pdf account for all the components.
+    pub fn sample_f(&self, wo_world: Vec3, u: (f32, f32)) -> Option<BxdfSample> {
+        let n = self.bxdfs.len();
+        if n == 0 {
+            return None;
+        }
+
+        let comp = ((u.0 * n as f32) as usize).min(n - 1);
+        // Remaps the sample so that it's uniform in [0, 1) again.
+        let u = ((u.0 * n as f32 - comp as f32).min(1.0 - f32::EPSILON), u.1);
+
+        let wo = self.frame.world_to_local(wo_world);
+        if wo.z == 0.0 {
+            return None;
+        }
+
+        let bxdf = &self.bxdfs[comp];
+        let mut sample = bxdf.sample_f(wo, u)?;
+        if sample.pdf == 0.0 {
+            return None;
+        }
+
+        if !sample.specular && n > 1 {
+            sample.f = Rgb::new(0.0, 0.0, 0.0);
+            sample.pdf = 0.0;
+            for bxdf in &self.bxdfs {
+                sample.f += bxdf.f(wo, sample.wi);
+                sample.pdf += bxdf.pdf(wo, sample.wi);
+            }
+        }
+        sample.pdf /= n as f32;
+
+        sample.wi = self.frame.local_to_world(sample.wi);
+
+        Some(sample)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bxdfs::{BlinnPhong, Lambertian, SpecularReflection};
+    use crate::sampling::Rng;
+
+    // Estimates the directional albedo of |bsdf| for |wo| by importance sampling it.
+    fn albedo(bsdf: &Bsdf, wo: Vec3, num_samples: usize) -> Rgb {
+        let mut rng = Rng::new(0);
+        let mut sum = Rgb::new(0.0, 0.0, 0.0);
+        for _ in 0..num_samples {
+            if let Some(s) = bsdf.sample_f(wo, rng.uniform_2d()) {
+                sum += Vec3::dot(s.wi, bsdf.n()).abs() / s.pdf * s.f;
+            }
+        }
+        sum / num_samples as f32
+    }
+
+    #[test]
+    fn lambertian_albedo() {
+        let mut bsdf = Bsdf::new(Frame::from_normal(Vec3::new(0.0, 1.0, 0.0)));
+        bsdf.add(Box::new(Lambertian { r: Rgb::new(0.5, 0.5, 0.5) }));
+
+        let a = albedo(&bsdf, Vec3::normalize(Vec3::new(0.3, 1.0, 0.2)), 1000);
+        // Importance sampling cancels out the cosine term exactly.
+        assert!((a.r - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn sample_matches_pdf() {
+        let mut bsdf = Bsdf::new(Frame::from_normal(Vec3::new(0.0, 0.0, 1.0)));
+        bsdf.add(Box::new(Lambertian { r: Rgb::new(0.5, 0.5, 0.5) }));
+        bsdf.add(Box::new(BlinnPhong { r: Rgb::new(0.5, 0.5, 0.5), exponent: 20.0 }));
+
+        let wo = Vec3::normalize(Vec3::new(0.5, 0.0, 1.0));
+        let mut rng = Rng::new(1);
+        for _ in 0..100 {
+            if let Some(s) = bsdf.sample_f(wo, rng.uniform_2d()) {
+                assert!((s.pdf - bsdf.pdf(wo, s.wi)).abs() <= 1e-3 * s.pdf);
+                let f = bsdf.f(wo, s.wi);
+                assert!((s.f.r - f.r).abs() <= 1e-3 * f.r);
+            }
+        }
+
+        // The estimator should integrate to roughly the sum of the two lobes' albedos.
+        let a = albedo(&bsdf, wo, 20000);
+        assert!(a.r > 0.5 && a.r < 1.1);
+    }
+
+    #[test]
+    fn mirror() {
+        let mut bsdf = Bsdf::new(Frame::from_normal(Vec3::new(0.0, 1.0, 0.0)));
+        bsdf.add(Box::new(SpecularReflection { r: Rgb::new(0.3, 0.3, 0.3) }));
+        assert!(bsdf.is_specular());
+
+        let wo = Vec3::normalize(Vec3::new(1.0, 1.0, 0.0));
+        let s = bsdf.sample_f(wo, (0.5, 0.5)).unwrap();
+        assert!(s.specular);
+        assert!((s.wi - Vec3::normalize(Vec3::new(-1.0, 1.0, 0.0))).len() < 1e-6);
+        assert!((s.f.r * Vec3::dot(s.wi, bsdf.n()) / s.pdf - 0.3).abs() < 1e-6);
+    }
+}
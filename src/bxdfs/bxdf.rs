@@ -0,0 +1,61 @@
+use crate::geometry::Vec3;
+use crate::rgb::Rgb;
+
+// BxDFs work in a local shading coordinate system where the surface normal is the z axis. All
+// directions point away from the surface.
+
+pub struct BxdfSample {
+    pub wi: Vec3,
+    pub f: Rgb,
+    pub pdf: f32,
+    // Whether |wi| was chosen deterministically, in which case |f| and |pdf| contain delta
+    // distributions that only make sense as a ratio.
+    pub specular: bool,
+}
+
+pub trait Bxdf: Send + Sync {
+    // Returns the value of the distribution for the pair of directions. Always zero for specular
+    // BxDFs.
+    fn f(&self, wo: Vec3, wi: Vec3) -> Rgb;
+
+    // Samples an incident direction for the outgoing direction |wo| using the uniform sample |u|.
+    fn sample_f(&self, wo: Vec3, u: (f32, f32)) -> Option<BxdfSample>;
+
+    // Returns the density with which sample_f() samples |wi| given |wo|.
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f32;
+
+    fn is_specular(&self) -> bool {
+        false
+    }
+}
+
+pub fn cos_theta(w: Vec3) -> f32 {
+    w.z
+}
+
+pub fn abs_cos_theta(w: Vec3) -> f32 {
+    w.z.abs()
+}
+
+pub fn same_hemisphere(w1: Vec3, w2: Vec3) -> bool {
+    w1.z * w2.z > 0.0
+}
+
+// Reflects |wo| about |n|.
+pub fn reflect(wo: Vec3, n: Vec3) -> Vec3 {
+    -wo + 2.0 * Vec3::dot(wo, n) * n
+}
+
+// Refracts |wi| through a boundary with normal |n| on the same side as |wi|, where |eta| is the
+// ratio of the index of refraction on the incident side to the one on the transmitted side.
+// Returns None for total internal reflection.
+pub fn refract(wi: Vec3, n: Vec3, eta: f32) -> Option<Vec3> {
+    let cos_theta_i = Vec3::dot(n, wi);
+    let sin2_theta_i = (1.0 - cos_theta_i * cos_theta_i).max(0.0);
+    let sin2_theta_t = eta * eta * sin2_theta_i;
+    if sin2_theta_t >= 1.0 {
+        return None;
+    }
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+    Some(eta * -wi + (eta * cos_theta_i - cos_theta_t) * n)
+}
@@ -0,0 +1,103 @@
+use std::mem;
+
+use crate::rgb::Rgb;
+
+// Returns the fraction of unpolarized light reflected at a smooth boundary between dielectrics
+// with indices of refraction |eta_i| on the incident side and |eta_t| on the other side.
+// |cos_theta_i| is measured against the normal on the incident side; a negative value means the
+// light arrives from the other side, and the two indices are swapped. Returns 1 for total internal
+// reflection.
+pub fn fr_dielectric(cos_theta_i: f32, eta_i: f32, eta_t: f32) -> f32 {
+    let mut cos_theta_i = cos_theta_i.clamp(-1.0, 1.0);
+    let (mut eta_i, mut eta_t) = (eta_i, eta_t);
+    if cos_theta_i < 0.0 {
+        mem::swap(&mut eta_i, &mut eta_t);
+        cos_theta_i = -cos_theta_i;
+    }
+
+    // Snell's law.
+    let sin_theta_i = (1.0 - cos_theta_i * cos_theta_i).max(0.0).sqrt();
+    let sin_theta_t = eta_i / eta_t * sin_theta_i;
+    if sin_theta_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_theta_t = (1.0 - sin_theta_t * sin_theta_t).max(0.0).sqrt();
+
+    let r_parl = (eta_t * cos_theta_i - eta_i * cos_theta_t) /
+                 (eta_t * cos_theta_i + eta_i * cos_theta_t);
+    let r_perp = (eta_i * cos_theta_i - eta_t * cos_theta_t) /
+                 (eta_i * cos_theta_i + eta_t * cos_theta_t);
+
+    0.5 * (r_parl * r_parl + r_perp * r_perp)
+}
+
+// Returns the fraction of unpolarized light reflected by a conductor with complex index of
+// refraction |eta| + i|k| relative to the surroundings, for each color channel.
+pub fn fr_conductor(cos_theta_i: f32, eta: Rgb, k: Rgb) -> Rgb {
+    let cos_theta_i = cos_theta_i.abs().min(1.0);
+    Rgb::new(fr_conductor_channel(cos_theta_i, eta.r, k.r),
+             fr_conductor_channel(cos_theta_i, eta.g, k.g),
+             fr_conductor_channel(cos_theta_i, eta.b, k.b))
+}
+
+fn fr_conductor_channel(cos_theta_i: f32, eta: f32, k: f32) -> f32 {
+    let cos2_theta_i = cos_theta_i * cos_theta_i;
+    let sin2_theta_i = 1.0 - cos2_theta_i;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+
+    let t0 = eta2 - k2 - sin2_theta_i;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2_theta_i;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_theta_i * a;
+    let r_perp = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2_theta_i * a2_plus_b2 + sin2_theta_i * sin2_theta_i;
+    let t4 = t2 * sin2_theta_i;
+    let r_parl = r_perp * (t3 - t4) / (t3 + t4);
+
+    0.5 * (r_parl + r_perp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dielectric() {
+        // At normal incidence, R = ((n1 - n2) / (n1 + n2))^2 = 0.04 for glass.
+        assert!((fr_dielectric(1.0, 1.0, 1.5) - 0.04).abs() < 1e-6);
+        assert!((fr_dielectric(-1.0, 1.0, 1.5) - 0.04).abs() < 1e-6);
+
+        // Grazing incidence reflects everything.
+        assert!((fr_dielectric(0.0, 1.0, 1.5) - 1.0).abs() < 1e-6);
+
+        // Brewster's angle: the parallel component vanishes, leaving half of r_perp^2.
+        let brewster = 1.5_f32.atan();
+        let r = fr_dielectric(brewster.cos(), 1.0, 1.5);
+        assert!((r - 0.0739).abs() < 1e-3);
+
+        // Inside glass past the critical angle of asin(1 / 1.5) ~ 41.8 degrees.
+        let cos_45 = 45.0_f32.to_radians().cos();
+        assert_eq!(fr_dielectric(-cos_45, 1.0, 1.5), 1.0);
+        assert!(fr_dielectric(-(35.0_f32.to_radians().cos()), 1.0, 1.5) < 1.0);
+    }
+
+    #[test]
+    fn conductor() {
+        // With no absorption, a conductor is a dielectric with the same index of refraction.
+        let white = Rgb::new(1.0, 1.0, 1.0);
+        for &cos in &[1.0, 0.7, 0.2] {
+            let r = fr_conductor(cos, 1.5 * white, Rgb::new(0.0, 0.0, 0.0));
+            assert!((r.g - fr_dielectric(cos, 1.0, 1.5)).abs() < 1e-5);
+        }
+
+        // At normal incidence, R = ((eta - 1)^2 + k^2) / ((eta + 1)^2 + k^2).
+        let r = fr_conductor(1.0, Rgb::new(0.2, 0.9, 1.1), Rgb::new(3.9, 2.5, 2.1));
+        assert!((r.r - (0.64 + 15.21) / (1.44 + 15.21)).abs() < 1e-5);
+        assert!(r.r > r.g && r.g > r.b);
+
+        assert!((fr_conductor(0.0, white, white).b - 1.0).abs() < 1e-6);
+    }
+}
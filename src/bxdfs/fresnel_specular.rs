@@ -0,0 +1,95 @@
+use crate::bxdfs::{Bxdf, BxdfSample};
+use crate::bxdfs::bxdf::{abs_cos_theta, cos_theta, refract};
+use crate::bxdfs::fresnel::fr_dielectric;
+use crate::geometry::Vec3;
+use crate::rgb::Rgb;
+
+// A smooth dielectric boundary that reflects and transmits light in proportions given by the
+// Fresnel equations. |eta_a| is the index of refraction on the side the normal points to and
+// |eta_b| the index on the other side.
+pub struct FresnelSpecular {
+    pub r: Rgb,
+    pub t: Rgb,
+    pub eta_a: f32,
+    pub eta_b: f32,
+}
+
+impl Bxdf for FresnelSpecular {
+    fn f(&self, _wo: Vec3, _wi: Vec3) -> Rgb {
+        Rgb::new(0.0, 0.0, 0.0)
+    }
+
+    fn sample_f(&self, wo: Vec3, u: (f32, f32)) -> Option<BxdfSample> {
+        let fr = fr_dielectric(cos_theta(wo), self.eta_a, self.eta_b);
+
+        // Choose between reflection and transmission in proportion to the Fresnel term.
+        if u.0 < fr {
+            let wi = Vec3::new(-wo.x, -wo.y, wo.z);
+            let cos = abs_cos_theta(wi);
+            if cos == 0.0 {
+                return None;
+            }
+            return Some(BxdfSample { wi, f: fr / cos * self.r, pdf: fr, specular: true });
+        }
+
+        let entering = cos_theta(wo) > 0.0;
+        let (eta_i, eta_t) = if entering {
+            (self.eta_a, self.eta_b)
+        } else {
+            (self.eta_b, self.eta_a)
+        };
+
+        let n = if entering { Vec3::new(0.0, 0.0, 1.0) } else { Vec3::new(0.0, 0.0, -1.0) };
+        let wi = refract(wo, n, eta_i / eta_t)?;
+        let cos = abs_cos_theta(wi);
+        if cos == 0.0 {
+            return None;
+        }
+
+        // Radiance is compressed into a smaller solid angle when it enters a denser medium.
+        let scale = (1.0 - fr) * (eta_i * eta_i) / (eta_t * eta_t);
+        Some(BxdfSample { wi, f: scale / cos * self.t, pdf: 1.0 - fr, specular: true })
+    }
+
+    fn pdf(&self, _wo: Vec3, _wi: Vec3) -> f32 {
+        0.0
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample() {
+        let glass = FresnelSpecular {
+            r: Rgb::new(1.0, 1.0, 1.0),
+            t: Rgb::new(1.0, 1.0, 1.0),
+            eta_a: 1.0,
+            eta_b: 1.5,
+        };
+
+        let wo = Vec3::normalize(Vec3::new(1.0, 0.0, 1.0));
+        let fr = fr_dielectric(cos_theta(wo), 1.0, 1.5);
+
+        let s = glass.sample_f(wo, (0.0, 0.0)).unwrap();
+        assert!((s.wi - Vec3::new(-wo.x, 0.0, wo.z)).len() < 1e-6);
+        assert!((s.f.r * abs_cos_theta(s.wi) / s.pdf - 1.0).abs() < 1e-5);
+
+        let s = glass.sample_f(wo, (fr + 0.01, 0.0)).unwrap();
+        // Snell's law: sin(theta_t) = sin(45 degrees) / 1.5.
+        assert!(s.wi.z < 0.0);
+        assert!((s.wi.x + 45.0_f32.to_radians().sin() / 1.5).abs() < 1e-5);
+        assert!((s.f.r * abs_cos_theta(s.wi) / s.pdf - 1.0 / 2.25).abs() < 1e-5);
+
+        // Leaving the glass past the critical angle always reflects.
+        let wo = Vec3::normalize(Vec3::new(1.0, 0.0, -1.0));
+        let s = glass.sample_f(wo, (0.99, 0.0)).unwrap();
+        assert!(s.wi.z < 0.0);
+        assert_eq!(s.pdf, 1.0);
+    }
+}
@@ -0,0 +1,44 @@
+use std::f32::consts::FRAC_1_PI;
+
+use crate::bxdfs::{Bxdf, BxdfSample};
+use crate::bxdfs::bxdf::{abs_cos_theta, same_hemisphere};
+use crate::geometry::Vec3;
+use crate::rgb::Rgb;
+use crate::sampling::warp;
+
+// Perfectly diffuse reflection.
+pub struct Lambertian {
+    pub r: Rgb,
+}
+
+impl Bxdf for Lambertian {
+    fn f(&self, wo: Vec3, wi: Vec3) -> Rgb {
+        if same_hemisphere(wo, wi) {
+            FRAC_1_PI * self.r
+        } else {
+            Rgb::new(0.0, 0.0, 0.0)
+        }
+    }
+
+    fn sample_f(&self, wo: Vec3, u: (f32, f32)) -> Option<BxdfSample> {
+        let mut wi = warp::cosine_sample_hemisphere(u);
+        if wo.z < 0.0 {
+            wi.z = -wi.z;
+        }
+
+        let pdf = self.pdf(wo, wi);
+        if pdf == 0.0 {
+            return None;
+        }
+
+        Some(BxdfSample { wi, f: self.f(wo, wi), pdf, specular: false })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f32 {
+        if same_hemisphere(wo, wi) {
+            warp::cosine_hemisphere_pdf(abs_cos_theta(wi))
+        } else {
+            0.0
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::f32::consts::PI;
+
+use crate::bxdfs::bxdf::abs_cos_theta;
+use crate::geometry::Vec3;
+use crate::sampling::warp;
+
+// Distributions with both alphas below this are treated as perfectly smooth, since evaluating
+// them becomes numerically unstable.
+const SMOOTH_ALPHA: f32 = 1e-3;
+
+// The Trowbridge-Reitz (GGX) distribution of microfacet normals, with the Smith model for masking
+// and shadowing. |alpha_x| and |alpha_y| are the roughnesses along the x and y axes of the
+// shading frame.
+#[derive(Debug, Copy, Clone)]
+pub struct TrowbridgeReitz {
+    pub alpha_x: f32,
+    pub alpha_y: f32,
+}
+
+impl TrowbridgeReitz {
+    // Maps the perceptual |roughness| in [0, 1] to alpha by squaring it, which makes the
+    // highlight change more evenly across the range. |anisotropy| in [0, 1] stretches the
+    // highlight along the x axis.
+    pub fn from_roughness(roughness: f32, anisotropy: f32) -> Self {
+        let alpha = roughness * roughness;
+        let aspect = (1.0 - 0.9 * anisotropy).sqrt();
+        TrowbridgeReitz { alpha_x: alpha / aspect, alpha_y: alpha * aspect }
+    }
+
+    pub fn effectively_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < SMOOTH_ALPHA
+    }
+
+    // Returns the density of microfacets with normal |wm|, per unit of projected area.
+    pub fn d(&self, wm: Vec3) -> f32 {
+        if wm.z <= 0.0 {
+            return 0.0;
+        }
+
+        let x = wm.x / self.alpha_x;
+        let y = wm.y / self.alpha_y;
+        let e = x * x + y * y + wm.z * wm.z;
+        1.0 / (PI * self.alpha_x * self.alpha_y * e * e)
+    }
+
+    // The Smith auxiliary function, which measures the microfacet area that's invisible from |w|
+    // per unit of visible area.
+    pub fn lambda(&self, w: Vec3) -> f32 {
+        let cos2_theta = w.z * w.z;
+        if cos2_theta == 0.0 {
+            return 0.0;
+        }
+
+        let alpha2_tan2_theta = (self.alpha_x * self.alpha_x * w.x * w.x +
+                                 self.alpha_y * self.alpha_y * w.y * w.y) / cos2_theta;
+        0.5 * ((1.0 + alpha2_tan2_theta).sqrt() - 1.0)
+    }
+
+    // Returns the fraction of microfacets that are visible from |w|.
+    pub fn g1(&self, w: Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    // Returns the fraction of microfacets that are visible from both |wo| and |wi|.
+    pub fn g(&self, wo: Vec3, wi: Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // Returns the density of the normals of the microfacets visible from |w|. This is the density
+    // that sample_wm() samples |wm| with.
+    pub fn pdf(&self, w: Vec3, wm: Vec3) -> f32 {
+        let cos_theta = abs_cos_theta(w);
+        if cos_theta == 0.0 {
+            return 0.0;
+        }
+        self.g1(w) / cos_theta * self.d(wm) * Vec3::dot(w, wm).abs()
+    }
+
+    // Samples the normal of a microfacet visible from |w| using |u|, following Heitz's 2018
+    // method. The normal is always in the upper hemisphere, even if |w| isn't.
+    pub fn sample_wm(&self, w: Vec3, u: (f32, f32)) -> Vec3 {
+        // Stretch |w| so that the distribution becomes the hemisphere of unit roughness.
+        let mut wh = Vec3::normalize(Vec3::new(self.alpha_x * w.x, self.alpha_y * w.y, w.z));
+        if wh.z < 0.0 {
+            wh = -wh;
+        }
+
+        let t1 = if wh.z < 0.99999 {
+            Vec3::normalize(Vec3::cross(Vec3::new(0.0, 0.0, 1.0), wh))
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = Vec3::cross(wh, t1);
+
+        // Sample the projection of the visible half of the hemisphere, which is a disk with one
+        // half squashed.
+        let (px, py) = warp::concentric_sample_disk(u);
+        let h = (1.0 - px * px).max(0.0).sqrt();
+        let s = 0.5 * (1.0 + wh.z);
+        let py = (1.0 - s) * h + s * py;
+        let pz = (1.0 - px * px - py * py).max(0.0).sqrt();
+
+        // Lift the point back to the hemisphere and unstretch it.
+        let nh = px * t1 + py * t2 + pz * wh;
+        Vec3::normalize(Vec3::new(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-6)))
+    }
+}
+
+// Flips |w| into the upper hemisphere, where the microfacet normals are.
+pub fn face_up(w: Vec3) -> Vec3 {
+    if w.z < 0.0 { -w } else { w }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::Rng;
+
+    // Integrates |f| over the sphere of directions with stratified uniform samples.
+    fn integrate_sphere<F: Fn(Vec3) -> f32>(f: F) -> f32 {
+        let n = 400;
+        let mut sum = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let z = 1.0 - 2.0 * (i as f32 + 0.5) / n as f32;
+                let phi = 2.0 * PI * (j as f32 + 0.5) / n as f32;
+                let r = (1.0 - z * z).sqrt();
+                sum += f(Vec3::new(r * phi.cos(), r * phi.sin(), z));
+            }
+        }
+        sum * 4.0 * PI / (n * n) as f32
+    }
+
+    #[test]
+    fn normalized() {
+        let distrib = TrowbridgeReitz { alpha_x: 0.5, alpha_y: 0.3 };
+
+        // The projected areas of the microfacets add up to the macrosurface area.
+        let area = integrate_sphere(|wm| distrib.d(wm) * wm.z.max(0.0));
+        assert!((area - 1.0).abs() < 0.01);
+
+        // The projected areas of the visible microfacets add up to the projected macrosurface
+        // area, so the visible normals' density integrates to 1.
+        let w = Vec3::normalize(Vec3::new(0.6, -0.3, 0.5));
+        let visible = integrate_sphere(|wm| {
+            if Vec3::dot(w, wm) > 0.0 { distrib.pdf(w, wm) } else { 0.0 }
+        });
+        assert!((visible - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn sample_wm() {
+        let distrib = TrowbridgeReitz::from_roughness(0.6, 0.5);
+        assert!(distrib.alpha_x > distrib.alpha_y);
+        assert!(!distrib.effectively_smooth());
+        assert!(TrowbridgeReitz::from_roughness(0.01, 0.0).effectively_smooth());
+
+        // Compare the fraction of samples that fall in a cone around the normal with the
+        // integral of the pdf over the cone.
+        let w = Vec3::normalize(Vec3::new(0.4, 0.2, 0.8));
+        let cos_cone = 0.9;
+        let mut rng = Rng::new(0);
+        let num_samples = 20000;
+        let mut in_cone = 0;
+        for _ in 0..num_samples {
+            let wm = distrib.sample_wm(w, rng.uniform_2d());
+            assert!(wm.z > 0.0);
+            assert!((wm.len() - 1.0).abs() < 1e-4);
+            if wm.z > cos_cone {
+                in_cone += 1;
+            }
+        }
+
+        let expected = integrate_sphere(|wm| {
+            if wm.z > cos_cone && Vec3::dot(w, wm) > 0.0 { distrib.pdf(w, wm) } else { 0.0 }
+        });
+        let actual = in_cone as f32 / num_samples as f32;
+        assert!((actual - expected).abs() < 0.02);
+    }
+}
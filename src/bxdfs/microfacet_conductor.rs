@@ -0,0 +1,133 @@
+use crate::bxdfs::{Bxdf, BxdfSample};
+use crate::bxdfs::bxdf::{abs_cos_theta, reflect, same_hemisphere};
+use crate::bxdfs::fresnel::fr_conductor;
+use crate::bxdfs::microfacet::{TrowbridgeReitz, face_up};
+use crate::geometry::Vec3;
+use crate::rgb::Rgb;
+
+// Reflection from a rough metal, modeled as a surface of perfectly specular microfacets. |eta|
+// and |k| are the real and imaginary parts of the metal's index of refraction relative to the
+// surroundings. Becomes a perfect mirror when the distribution is effectively smooth.
+pub struct MicrofacetConductor {
+    pub distrib: TrowbridgeReitz,
+    pub eta: Rgb,
+    pub k: Rgb,
+}
+
+impl Bxdf for MicrofacetConductor {
+    fn f(&self, wo: Vec3, wi: Vec3) -> Rgb {
+        if !same_hemisphere(wo, wi) || self.distrib.effectively_smooth() {
+            return Rgb::new(0.0, 0.0, 0.0);
+        }
+
+        let cos_theta_o = abs_cos_theta(wo);
+        let cos_theta_i = abs_cos_theta(wi);
+        let wm = wo + wi;
+        if cos_theta_o == 0.0 || cos_theta_i == 0.0 || wm.len_sq() == 0.0 {
+            return Rgb::new(0.0, 0.0, 0.0);
+        }
+        let wm = face_up(Vec3::normalize(wm));
+
+        let fr = fr_conductor(Vec3::dot(wo, wm), self.eta, self.k);
+        self.distrib.d(wm) * self.distrib.g(wo, wi) / (4.0 * cos_theta_o * cos_theta_i) * fr
+    }
+
+    fn sample_f(&self, wo: Vec3, u: (f32, f32)) -> Option<BxdfSample> {
+        if self.distrib.effectively_smooth() {
+            let wi = Vec3::new(-wo.x, -wo.y, wo.z);
+            let cos = abs_cos_theta(wi);
+            if cos == 0.0 {
+                return None;
+            }
+            let f = fr_conductor(cos, self.eta, self.k) / cos;
+            return Some(BxdfSample { wi, f, pdf: 1.0, specular: true });
+        }
+
+        if wo.z == 0.0 {
+            return None;
+        }
+        let wm = self.distrib.sample_wm(wo, u);
+        let wi = reflect(wo, wm);
+        if !same_hemisphere(wo, wi) {
+            return None;
+        }
+
+        let pdf = self.pdf(wo, wi);
+        if pdf == 0.0 {
+            return None;
+        }
+        Some(BxdfSample { wi, f: self.f(wo, wi), pdf, specular: false })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f32 {
+        if !same_hemisphere(wo, wi) || self.distrib.effectively_smooth() {
+            return 0.0;
+        }
+
+        let wm = wo + wi;
+        if wm.len_sq() == 0.0 {
+            return 0.0;
+        }
+        let wm = face_up(Vec3::normalize(wm));
+
+        // Account for the change of variables from the microfacet normal to the reflected
+        // direction.
+        self.distrib.pdf(wo, wm) / (4.0 * Vec3::dot(wo, wm).abs())
+    }
+
+    fn is_specular(&self) -> bool {
+        self.distrib.effectively_smooth()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::Rng;
+
+    #[test]
+    fn sample_matches_f_and_pdf() {
+        let gold = MicrofacetConductor {
+            distrib: TrowbridgeReitz::from_roughness(0.5, 0.3),
+            eta: Rgb::new(0.143, 0.374, 1.442),
+            k: Rgb::new(3.983, 2.385, 1.603),
+        };
+
+        let mut rng = Rng::new(0);
+        for &wo in &[Vec3::normalize(Vec3::new(0.3, 0.1, 0.9)),
+                     Vec3::normalize(Vec3::new(-0.8, 0.2, -0.3))] {
+            let num_samples = 20000;
+            let mut albedo = Rgb::new(0.0, 0.0, 0.0);
+            for _ in 0..num_samples {
+                if let Some(s) = gold.sample_f(wo, rng.uniform_2d()) {
+                    assert!(!s.specular);
+                    assert!(same_hemisphere(wo, s.wi));
+                    assert!((s.pdf - gold.pdf(wo, s.wi)).abs() <= 1e-4 * s.pdf);
+                    albedo += abs_cos_theta(s.wi) / s.pdf * s.f;
+                }
+            }
+
+            // Gold reflects red the most, and the microfacet model loses some energy to masking.
+            let albedo = albedo / num_samples as f32;
+            assert!(albedo.r < 1.0 && albedo.r > 0.75);
+            assert!(albedo.r > albedo.g && albedo.g > albedo.b);
+        }
+    }
+
+    #[test]
+    fn smooth() {
+        let mirror = MicrofacetConductor {
+            distrib: TrowbridgeReitz::from_roughness(0.0, 0.0),
+            eta: Rgb::new(1.5, 1.5, 1.5),
+            k: Rgb::new(0.0, 0.0, 0.0),
+        };
+        assert!(mirror.is_specular());
+
+        let wo = Vec3::new(0.0, 0.0, 1.0);
+        let s = mirror.sample_f(wo, (0.3, 0.7)).unwrap();
+        assert!(s.specular);
+        assert_eq!(s.wi, wo);
+        assert!((s.f.r / s.pdf - 0.04).abs() < 1e-6);
+        assert!(mirror.f(wo, wo).is_black());
+    }
+}
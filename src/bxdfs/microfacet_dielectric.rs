@@ -0,0 +1,184 @@
+use crate::bxdfs::{Bxdf, BxdfSample};
+use crate::bxdfs::bxdf::{cos_theta, reflect, refract, same_hemisphere};
+use crate::bxdfs::fresnel::fr_dielectric;
+use crate::bxdfs::microfacet::{TrowbridgeReitz, face_up};
+use crate::geometry::Vec3;
+use crate::rgb::Rgb;
+
+// Lower bound on the probability of sampling either reflection or transmission, so that lobes
+// that the macrosurface Fresnel term underestimates still get sampled.
+const MIN_LOBE_PROB: f32 = 0.05;
+
+// A rough boundary between dielectrics, modeled as a surface of perfectly smooth microfacets that
+// each reflect and refract according to the Fresnel equations. |eta| is the index of refraction
+// on the side opposite to the normal relative to the side the normal points to. Smooth boundaries
+// should use FresnelSpecular instead, since this doesn't handle effectively smooth distributions.
+pub struct MicrofacetDielectric {
+    pub distrib: TrowbridgeReitz,
+    pub eta: f32,
+}
+
+impl MicrofacetDielectric {
+    // Returns the relative index of refraction for light passing between the sides of |wo| and
+    // |wi|, or 1 if they're on the same side.
+    fn etap(&self, wo: Vec3, wi: Vec3) -> f32 {
+        if same_hemisphere(wo, wi) {
+            1.0
+        } else if cos_theta(wo) > 0.0 {
+            self.eta
+        } else {
+            1.0 / self.eta
+        }
+    }
+
+    // Returns the generalized half vector of |wo| and |wi| in the upper hemisphere, or None if
+    // it's undefined or the microfacet with that normal faces away from either direction.
+    fn half_vector(&self, wo: Vec3, wi: Vec3, etap: f32) -> Option<Vec3> {
+        let wm = etap * wi + wo;
+        if cos_theta(wo) == 0.0 || cos_theta(wi) == 0.0 || wm.len_sq() == 0.0 {
+            return None;
+        }
+        let wm = face_up(Vec3::normalize(wm));
+
+        if Vec3::dot(wm, wi) * cos_theta(wi) < 0.0 || Vec3::dot(wm, wo) * cos_theta(wo) < 0.0 {
+            return None;
+        }
+        Some(wm)
+    }
+
+    // Returns the probability with which sample_f() samples a reflection for |wo|. Only depends
+    // on |wo| so that pdf() can evaluate it, which is why it uses the macrosurface normal.
+    fn reflect_prob(&self, wo: Vec3) -> f32 {
+        fr_dielectric(cos_theta(wo), 1.0, self.eta).clamp(MIN_LOBE_PROB, 1.0 - MIN_LOBE_PROB)
+    }
+}
+
+impl Bxdf for MicrofacetDielectric {
+    fn f(&self, wo: Vec3, wi: Vec3) -> Rgb {
+        let etap = self.etap(wo, wi);
+        let wm = match self.half_vector(wo, wi, etap) {
+            Some(wm) => wm,
+            None => return Rgb::new(0.0, 0.0, 0.0),
+        };
+
+        let fr = fr_dielectric(Vec3::dot(wo, wm), 1.0, self.eta);
+        let d = self.distrib.d(wm);
+        let g = self.distrib.g(wo, wi);
+        let cos_theta_o = cos_theta(wo);
+        let cos_theta_i = cos_theta(wi);
+
+        let f = if etap == 1.0 {
+            d * g * fr / (4.0 * cos_theta_o * cos_theta_i).abs()
+        } else {
+            let denom = Vec3::dot(wi, wm) + Vec3::dot(wo, wm) / etap;
+            let denom = denom * denom * cos_theta_i * cos_theta_o;
+            let ft = d * (1.0 - fr) * g * (Vec3::dot(wi, wm) * Vec3::dot(wo, wm) / denom).abs();
+            // Radiance is compressed into a smaller solid angle when it enters a denser medium.
+            ft / (etap * etap)
+        };
+
+        Rgb::new(f, f, f)
+    }
+
+    fn sample_f(&self, wo: Vec3, u: (f32, f32)) -> Option<BxdfSample> {
+        if wo.z == 0.0 {
+            return None;
+        }
+
+        // Choose between reflection and transmission with |u|.0, then remap it to [0, 1).
+        let p_r = self.reflect_prob(wo);
+        let (reflection, u0) = if u.0 < p_r {
+            (true, u.0 / p_r)
+        } else {
+            (false, (u.0 - p_r) / (1.0 - p_r))
+        };
+        let wm = self.distrib.sample_wm(wo, (u0.min(1.0 - f32::EPSILON), u.1));
+
+        let wi = if reflection {
+            let wi = reflect(wo, wm);
+            if !same_hemisphere(wo, wi) {
+                return None;
+            }
+            wi
+        } else {
+            // refract() expects the normal on the side of |wo|.
+            let entering = cos_theta(wo) > 0.0;
+            let (n, eta) = if entering { (wm, 1.0 / self.eta) } else { (-wm, self.eta) };
+            if Vec3::dot(wo, n) <= 0.0 {
+                return None;
+            }
+            let wi = refract(wo, n, eta)?;
+            if same_hemisphere(wo, wi) || wi.z == 0.0 {
+                return None;
+            }
+            wi
+        };
+
+        let pdf = self.pdf(wo, wi);
+        if pdf == 0.0 {
+            return None;
+        }
+        Some(BxdfSample { wi, f: self.f(wo, wi), pdf, specular: false })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f32 {
+        let etap = self.etap(wo, wi);
+        let wm = match self.half_vector(wo, wi, etap) {
+            Some(wm) => wm,
+            None => return 0.0,
+        };
+
+        let p_r = self.reflect_prob(wo);
+        if etap == 1.0 {
+            p_r * self.distrib.pdf(wo, wm) / (4.0 * Vec3::dot(wo, wm).abs())
+        } else {
+            // Account for the change of variables from the microfacet normal to the refracted
+            // direction.
+            let denom = Vec3::dot(wi, wm) + Vec3::dot(wo, wm) / etap;
+            let dwm_dwi = Vec3::dot(wi, wm).abs() / (denom * denom);
+            (1.0 - p_r) * self.distrib.pdf(wo, wm) * dwm_dwi
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bxdfs::bxdf::abs_cos_theta;
+    use crate::sampling::Rng;
+
+    #[test]
+    fn sample_matches_f_and_pdf() {
+        let glass = MicrofacetDielectric {
+            distrib: TrowbridgeReitz::from_roughness(0.4, 0.0),
+            eta: 1.5,
+        };
+
+        let mut rng = Rng::new(0);
+        for &wo in &[Vec3::normalize(Vec3::new(0.3, 0.1, 0.9)),
+                     Vec3::normalize(Vec3::new(-0.2, 0.4, -0.8))] {
+            let num_samples = 20000;
+            let (mut reflected, mut transmitted) = (0.0, 0.0);
+            for _ in 0..num_samples {
+                if let Some(s) = glass.sample_f(wo, rng.uniform_2d()) {
+                    assert!((s.pdf - glass.pdf(wo, s.wi)).abs() <= 1e-3 * s.pdf);
+                    let weight = s.f.r * abs_cos_theta(s.wi) / s.pdf;
+                    if same_hemisphere(wo, s.wi) {
+                        reflected += weight;
+                    } else {
+                        // Undo the radiance scaling to count the transmitted energy.
+                        let etap = glass.etap(wo, s.wi);
+                        transmitted += weight * etap * etap;
+                    }
+                }
+            }
+
+            // A rough surface loses some energy to masking, but not much at this roughness. Most
+            // of the light is transmitted near normal incidence.
+            let reflected = reflected / num_samples as f32;
+            let transmitted = transmitted / num_samples as f32;
+            assert!(reflected > 0.02 && reflected < 0.2);
+            assert!(reflected + transmitted > 0.9 && reflected + transmitted < 1.01);
+        }
+    }
+}
@@ -0,0 +1,20 @@
+pub mod blinn_phong;
+pub mod bsdf;
+pub mod bxdf;
+pub mod fresnel;
+pub mod fresnel_specular;
+pub mod lambertian;
+pub mod microfacet;
+pub mod microfacet_conductor;
+pub mod microfacet_dielectric;
+pub mod specular_reflection;
+
+pub use self::blinn_phong::BlinnPhong;
+pub use self::bsdf::Bsdf;
+pub use self::bxdf::{Bxdf, BxdfSample};
+pub use self::fresnel_specular::FresnelSpecular;
+pub use self::lambertian::Lambertian;
+pub use self::microfacet::TrowbridgeReitz;
+pub use self::microfacet_conductor::MicrofacetConductor;
+pub use self::microfacet_dielectric::MicrofacetDielectric;
+pub use self::specular_reflection::SpecularReflection;
@@ -0,0 +1,33 @@
+use crate::bxdfs::{Bxdf, BxdfSample};
+use crate::bxdfs::bxdf::abs_cos_theta;
+use crate::geometry::Vec3;
+use crate::rgb::Rgb;
+
+// A perfect mirror that reflects a fixed fraction |r| of the incident light.
+pub struct SpecularReflection {
+    pub r: Rgb,
+}
+
+impl Bxdf for SpecularReflection {
+    fn f(&self, _wo: Vec3, _wi: Vec3) -> Rgb {
+        Rgb::new(0.0, 0.0, 0.0)
+    }
+
+    fn sample_f(&self, wo: Vec3, _u: (f32, f32)) -> Option<BxdfSample> {
+        let wi = Vec3::new(-wo.x, -wo.y, wo.z);
+        let cos = abs_cos_theta(wi);
+        if cos == 0.0 {
+            return None;
+        }
+
+        Some(BxdfSample { wi, f: self.r / cos, pdf: 1.0, specular: true })
+    }
+
+    fn pdf(&self, _wo: Vec3, _wi: Vec3) -> f32 {
+        0.0
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+}
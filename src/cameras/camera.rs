@@ -0,0 +1,15 @@
+use crate::geometry::Ray;
+
+// The sample values that determine a camera ray.
+pub struct CameraSample {
+    // Position on the film in continuous raster coordinates, where (0, 0) is the top left corner
+    // of the image and (width, height) is the bottom right corner.
+    pub film: (f32, f32),
+    // Uniform sample in [0, 1)^2 for cameras with a lens.
+    pub lens: (f32, f32),
+}
+
+pub trait Camera: Send + Sync {
+    // Returns the world space ray for |sample|. The direction isn't normalized.
+    fn generate_ray(&self, sample: &CameraSample) -> Ray;
+}
@@ -0,0 +1,5 @@
+pub mod camera;
+pub mod perspective;
+
+pub use self::camera::{Camera, CameraSample};
+pub use self::perspective::{Aperture, PerspectiveCamera, ThinLens};
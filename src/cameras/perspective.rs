@@ -0,0 +1,138 @@
+use crate::cameras::{Camera, CameraSample};
+use crate::geometry::{Point3, Ray, Transform, Vec3};
+use crate::sampling::warp;
+
+// The shape of the lens aperture, which determines the shape of out-of-focus highlights.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Aperture {
+    Circular,
+    // A regular polygon with the given number of blades (at least 3).
+    Polygonal(u32),
+}
+
+// A thin lens model of the camera's optics. Points at |focal_distance| along the viewing axis are
+// in focus, and everything else is blurred by an amount that grows with |radius|.
+#[derive(Debug, Copy, Clone)]
+pub struct ThinLens {
+    pub radius: f32,
+    pub focal_distance: f32,
+    pub aperture: Aperture,
+}
+
+// A perspective camera. Without a lens it's a pinhole camera and everything is in focus. In camera
+// space, the camera is at the origin looking down the +z axis with +y up.
+pub struct PerspectiveCamera {
+    camera_to_world: Transform,
+    lens: Option<ThinLens>,
+    width: f32,
+    height: f32,
+    // Half the width and height of the image plane at z = 1.
+    half_width: f32,
+    half_height: f32,
+}
+
+impl PerspectiveCamera {
+    // |fov| is the vertical field of view in degrees. The horizontal field of view follows from
+    // the aspect ratio of the |width| x |height| image.
+    pub fn new(camera_to_world: Transform, fov: f32, width: usize, height: usize,
+               lens: Option<ThinLens>) -> Self {
+        let half_height = (0.5 * fov).to_radians().tan();
+        let aspect = width as f32 / height as f32;
+
+        PerspectiveCamera {
+            camera_to_world,
+            lens,
+            width: width as f32,
+            height: height as f32,
+            half_width: half_height * aspect,
+            half_height,
+        }
+    }
+
+    // Returns the camera space direction through the raster position |film|.
+    pub fn raster_to_camera(&self, film: (f32, f32)) -> Vec3 {
+        let x = (2.0 * film.0 / self.width - 1.0) * self.half_width;
+        let y = (1.0 - 2.0 * film.1 / self.height) * self.half_height;
+        Vec3::new(x, y, 1.0)
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    fn generate_ray(&self, sample: &CameraSample) -> Ray {
+        let d = self.raster_to_camera(sample.film);
+
+        let (p, d) = match &self.lens {
+            None => (Point3::zeroes(), d),
+            Some(lens) => {
+                let (x, y) = match lens.aperture {
+                    Aperture::Circular => warp::concentric_sample_disk(sample.lens),
+                    Aperture::Polygonal(blades) => {
+                        warp::uniform_sample_polygon(sample.lens, blades)
+                    },
+                };
+                let p_lens = Point3::new(lens.radius * x, lens.radius * y, 0.0);
+
+                // All rays through the same film position converge on the plane of focus, since d
+                // has z = 1.
+                let p_focus = Point3::zeroes() + lens.focal_distance * d;
+                (p_lens, p_focus - p_lens)
+            },
+        };
+
+        Ray {
+            p: self.camera_to_world.apply_pt(&p),
+            d: self.camera_to_world.apply_vec(&d),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(x: f32, y: f32) -> CameraSample {
+        CameraSample { film: (x, y), lens: (0.5, 0.5) }
+    }
+
+    #[test]
+    fn generate_ray() {
+        let eye = Point3::new(1.0, 2.0, 3.0);
+        let camera_to_world = Transform::look_at(eye, Point3::new(1.0, 2.0, 4.0),
+                                                 Vec3::new(0.0, 1.0, 0.0));
+        let camera = PerspectiveCamera::new(camera_to_world, 90.0, 200, 100, None);
+
+        let center = camera.generate_ray(&sample(100.0, 50.0));
+        assert_eq!(center.p, eye);
+        assert!((center.d - Vec3::new(0.0, 0.0, 1.0)).len() < 1e-6);
+
+        // With a 90 degree vertical field of view, the top edge is at 45 degrees, and the 2:1
+        // aspect ratio makes the right edge twice as wide.
+        let top_left = camera.generate_ray(&sample(0.0, 0.0));
+        assert!((top_left.d - Vec3::new(-2.0, 1.0, 1.0)).len() < 1e-5);
+
+        let bottom_right = camera.generate_ray(&sample(200.0, 100.0));
+        assert!((bottom_right.d - Vec3::new(2.0, -1.0, 1.0)).len() < 1e-5);
+    }
+
+    #[test]
+    fn thin_lens() {
+        let lens = ThinLens { radius: 0.5, focal_distance: 4.0, aperture: Aperture::Polygonal(6) };
+        let camera = PerspectiveCamera::new(Transform::identity(), 90.0, 100, 100, Some(lens));
+
+        // Rays through the same film position start at different points on the lens but meet
+        // at the plane of focus.
+        let film = (30.0, 60.0);
+        let focus = {
+            let pinhole = PerspectiveCamera::new(Transform::identity(), 90.0, 100, 100, None);
+            let ray = pinhole.generate_ray(&CameraSample { film, lens: (0.5, 0.5) });
+            ray.p + 4.0 * ray.d
+        };
+
+        for &u in &[(0.1, 0.2), (0.9, 0.5), (0.5, 0.99)] {
+            let ray = camera.generate_ray(&CameraSample { film, lens: u });
+            assert_eq!(ray.p.z, 0.0);
+            assert!(ray.p.x.hypot(ray.p.y) <= 0.5 + 1e-6);
+            assert!((ray.p + ray.d - focus).len() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use crate::filters::Filter;
+use crate::rgb::Rgb;
+
+// The rendered image as linear radiance values, in row-major order starting at the top left.
+// Values aren't clamped, so they can be written out as HDR or encoded for display.
+pub struct Film {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgb>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize) -> Self {
+        Film { width, height, pixels: vec![Rgb::new(0.0, 0.0, 0.0); width * height] }
+    }
+
+    // |pixels| must contain |width| * |height| values in row-major order.
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Rgb>) -> Self {
+        assert_eq!(pixels.len(), width * height);
+        Film { width, height, pixels }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Rgb {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, val: Rgb) {
+        self.pixels[y * self.width + x] = val;
+    }
+}
+
+// The running mean and variance of the luminance of a pixel's samples, updated one sample at a
+// time with Welford's algorithm.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct PixelStats {
+    count: u32,
+    mean: f32,
+    // The sum of squared differences from the mean.
+    m2: f32,
+}
+
+impl PixelStats {
+    pub fn add(&mut self, v: f32) {
+        self.count += 1;
+        let delta = v - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (v - self.mean);
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn mean(&self) -> f32 {
+        self.mean
+    }
+
+    // The unbiased sample variance.
+    pub fn variance(&self) -> f32 {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / (self.count - 1) as f32
+        }
+    }
+
+    // Returns the standard error of the mean relative to the mean. Pixels darker than
+    // |min_mean| are compared against it instead, so that nearly black pixels aren't held to a
+    // precision that doesn't show.
+    pub fn relative_error(&self, min_mean: f32) -> f32 {
+        if self.count == 0 {
+            return f32::INFINITY;
+        }
+        (self.variance() / self.count as f32).sqrt() / self.mean.abs().max(min_mean)
+    }
+}
+
+// Accumulates the samples taken in a block of pixels, weighted by a reconstruction filter.
+// Samples near the edge of the block also contribute to the pixels around it, so the tile
+// covers the block grown by the filter's radius.
+pub struct FilmTile {
+    // The range of pixels that the tile covers, with |x1| and |y1| exclusive.
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+    // The filter-weighted sums of the samples and of their weights.
+    sums: Vec<Rgb>,
+    weights: Vec<f32>,
+    // The block of pixels that samples are taken in, and the statistics of each one's samples.
+    block: (usize, usize, usize, usize),
+    stats: Vec<PixelStats>,
+}
+
+impl FilmTile {
+    // Creates a tile for samples taken in the pixels from (|x0|, |y0|) up to but excluding
+    // (|x1|, |y1|) of a |film_width| x |film_height| film, with a filter of |radius|.
+    pub fn new(x0: usize, y0: usize, x1: usize, y1: usize, radius: (f32, f32),
+               film_width: usize, film_height: usize) -> Self {
+        let (tx0, tx1) = pixel_range(x0 as f32, x1 as f32, radius.0, film_width);
+        let (ty0, ty1) = pixel_range(y0 as f32, y1 as f32, radius.1, film_height);
+        let len = (tx1 - tx0) * (ty1 - ty0);
+        FilmTile {
+            x0: tx0,
+            y0: ty0,
+            x1: tx1,
+            y1: ty1,
+            sums: vec![Rgb::new(0.0, 0.0, 0.0); len],
+            weights: vec![0.0; len],
+            block: (x0, y0, x1, y1),
+            stats: vec![PixelStats::default(); (x1 - x0) * (y1 - y0)],
+        }
+    }
+
+    // Returns the statistics of the samples taken in the pixel at (|x|, |y|), which must be in
+    // the tile's block.
+    pub fn stats(&self, x: usize, y: usize) -> PixelStats {
+        self.stats[self.block_index(x, y)]
+    }
+
+    fn block_index(&self, x: usize, y: usize) -> usize {
+        let (bx0, by0, bx1, _) = self.block;
+        (y - by0) * (bx1 - bx0) + (x - bx0)
+    }
+
+    // Adds the radiance |l| arriving at the continuous film position |p|, where pixel (x, y)
+    // covers [x, x + 1) x [y, y + 1), to every pixel whose center is within the filter's radius.
+    pub fn add_sample(&mut self, p: (f32, f32), l: Rgb, filter: &dyn Filter) {
+        let (x, y) = (p.0.max(0.0) as usize, p.1.max(0.0) as usize);
+        let (bx0, by0, bx1, by1) = self.block;
+        if (bx0..bx1).contains(&x) && (by0..by1).contains(&y) {
+            let i = self.block_index(x, y);
+            self.stats[i].add(l.luminance());
+        }
+
+        let radius = filter.radius();
+        let (px0, px1) = pixel_range(p.0, p.0, radius.0, usize::MAX);
+        let (py0, py1) = pixel_range(p.1, p.1, radius.1, usize::MAX);
+
+        let width = self.x1 - self.x0;
+        for y in py0.max(self.y0)..py1.min(self.y1) {
+            for x in px0.max(self.x0)..px1.min(self.x1) {
+                let weight = filter.evaluate(x as f32 + 0.5 - p.0, y as f32 + 0.5 - p.1);
+                let i = (y - self.y0) * width + (x - self.x0);
+                self.sums[i] += weight * l;
+                self.weights[i] += weight;
+            }
+        }
+    }
+}
+
+// Returns the range of pixels, clamped to [0, |size|), whose centers c satisfy
+// |lo| - |radius| < c <= |hi| + |radius|. Pixels exactly at the radius on one side are left out
+// so that with a box filter of radius 1/2 a sample only lands in the pixel that it's in.
+fn pixel_range(lo: f32, hi: f32, radius: f32, size: usize) -> (usize, usize) {
+    let start = ((lo - 0.5 - radius).floor() + 1.0).max(0.0) as usize;
+    let end = ((hi - 0.5 + radius).floor() + 1.0).max(0.0) as usize;
+    (start.min(size), end.min(size))
+}
+
+impl Film {
+    // Combines the tiles of a |width| x |height| film into the final pixels by dividing each
+    // pixel's weighted sum of samples by its total weight. Filters with negative lobes can give
+    // negative values, which are clamped to zero.
+    pub fn from_tiles(width: usize, height: usize, tiles: &[FilmTile]) -> Self {
+        let mut sums = vec![Rgb::new(0.0, 0.0, 0.0); width * height];
+        let mut weights = vec![0.0; width * height];
+        for tile in tiles {
+            let tile_width = tile.x1 - tile.x0;
+            for y in tile.y0..tile.y1 {
+                for x in tile.x0..tile.x1 {
+                    let i = (y - tile.y0) * tile_width + (x - tile.x0);
+                    sums[y * width + x] += tile.sums[i];
+                    weights[y * width + x] += tile.weights[i];
+                }
+            }
+        }
+
+        let pixels = sums.iter().zip(&weights).map(|(&sum, &weight)| {
+            if weight == 0.0 {
+                Rgb::new(0.0, 0.0, 0.0)
+            } else {
+                let v = sum / weight;
+                Rgb::new(v.r.max(0.0), v.g.max(0.0), v.b.max(0.0))
+            }
+        }).collect();
+        Film::from_pixels(width, height, pixels)
+    }
+}
+
+// Returns the number of samples taken in each pixel of the |width| x |height| film that |tiles|
+// cover, in row-major order.
+pub fn sample_counts(width: usize, height: usize, tiles: &[FilmTile]) -> Vec<u32> {
+    let mut counts = vec![0; width * height];
+    for tile in tiles {
+        let (bx0, by0, bx1, by1) = tile.block;
+        for y in by0..by1 {
+            for x in bx0..bx1 {
+                counts[y * width + x] = tile.stats(x, y).count();
+            }
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::{BoxFilter, TriangleFilter};
+
+    #[test]
+    fn box_filter_averages_each_pixel() {
+        let filter = BoxFilter { radius: (0.5, 0.5) };
+        let mut tile = FilmTile::new(0, 0, 2, 1, filter.radius(), 2, 1);
+        tile.add_sample((0.0, 0.0), Rgb::new(1.0, 1.0, 1.0), &filter);
+        tile.add_sample((0.9, 0.5), Rgb::new(3.0, 3.0, 3.0), &filter);
+        tile.add_sample((1.0, 0.5), Rgb::new(5.0, 5.0, 5.0), &filter);
+
+        assert_eq!(sample_counts(2, 1, std::slice::from_ref(&tile)), vec![2, 1]);
+        let film = Film::from_tiles(2, 1, &[tile]);
+        assert_eq!(film.pixels, vec![Rgb::new(2.0, 2.0, 2.0), Rgb::new(5.0, 5.0, 5.0)]);
+    }
+
+    #[test]
+    fn pixel_stats() {
+        let mut stats = PixelStats::default();
+        assert_eq!(stats.relative_error(0.1), f32::INFINITY);
+        for &v in &[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            stats.add(v);
+        }
+        assert_eq!((stats.count(), stats.mean()), (8, 5.0));
+        assert!((stats.variance() - 32.0 / 7.0).abs() < 1e-5);
+        assert!((stats.relative_error(0.1) - (32.0f32 / 56.0).sqrt() / 5.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn samples_splat_across_tiles() {
+        let filter = TriangleFilter { radius: (1.5, 1.5) };
+        let mut left = FilmTile::new(0, 0, 2, 1, filter.radius(), 4, 1);
+        let mut right = FilmTile::new(2, 0, 4, 1, filter.radius(), 4, 1);
+        left.add_sample((1.9, 0.5), Rgb::new(1.0, 1.0, 1.0), &filter);
+        right.add_sample((2.1, 0.5), Rgb::new(3.0, 3.0, 3.0), &filter);
+
+        // Pixels 1 and 2 get both samples, weighted towards the nearer one, while pixel 0 is only
+        // within reach of the left sample and pixel 3 of the right one.
+        let film = Film::from_tiles(4, 1, &[left, right]);
+        assert_eq!(film.pixels[0].r, 1.0);
+        assert!(film.pixels[1].r > 1.0 && film.pixels[1].r < 2.0);
+        assert!(film.pixels[2].r > 2.0 && film.pixels[2].r < 3.0);
+        assert_eq!(film.pixels[3].r, 3.0);
+    }
+}
@@ -0,0 +1,17 @@
+use crate::filters::Filter;
+
+// Weights every sample within the radius equally. With a radius of half a pixel, each pixel is
+// the average of its own samples.
+pub struct BoxFilter {
+    pub radius: (f32, f32),
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> (f32, f32) {
+        self.radius
+    }
+
+    fn evaluate(&self, _x: f32, _y: f32) -> f32 {
+        1.0
+    }
+}
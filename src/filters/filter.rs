@@ -0,0 +1,11 @@
+// Reconstruction filters weight the samples that contribute to a pixel by their offset from the
+// pixel's center. Filters are shared between the render threads.
+pub trait Filter: Send + Sync {
+    // Returns how far the filter extends from the center along x and y, in pixels. Samples
+    // further away than this don't contribute.
+    fn radius(&self) -> (f32, f32);
+
+    // Returns the weight of a sample at offset (|x|, |y|) from the pixel's center. Can be
+    // negative.
+    fn evaluate(&self, x: f32, y: f32) -> f32;
+}
@@ -0,0 +1,48 @@
+use crate::filters::Filter;
+
+// A Gaussian e^(-alpha x^2), shifted down so that it reaches zero at the radius. Larger values of
+// |alpha| fall off faster and give sharper images.
+pub struct GaussianFilter {
+    radius: (f32, f32),
+    alpha: f32,
+    // The value of the unshifted Gaussian at the radius.
+    exp: (f32, f32),
+}
+
+impl GaussianFilter {
+    pub fn new(radius: (f32, f32), alpha: f32) -> Self {
+        GaussianFilter {
+            radius,
+            alpha,
+            exp: ((-alpha * radius.0 * radius.0).exp(), (-alpha * radius.1 * radius.1).exp()),
+        }
+    }
+
+    fn gaussian(&self, d: f32, exp: f32) -> f32 {
+        ((-self.alpha * d * d).exp() - exp).max(0.0)
+    }
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> (f32, f32) {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f32, y: f32) -> f32 {
+        self.gaussian(x, self.exp.0) * self.gaussian(y, self.exp.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_off_to_zero() {
+        let filter = GaussianFilter::new((1.5, 1.5), 2.0);
+        assert!((filter.evaluate(0.0, 0.0) - (1.0 - (-4.5f32).exp()).powi(2)).abs() < 1e-6);
+        assert!(filter.evaluate(0.5, 0.0) < filter.evaluate(0.2, 0.0));
+        assert_eq!(filter.evaluate(1.5, 0.0), 0.0);
+        assert_eq!(filter.evaluate(0.0, 2.0), 0.0);
+    }
+}
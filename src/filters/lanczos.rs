@@ -0,0 +1,54 @@
+use std::f32::consts::PI;
+
+use crate::filters::Filter;
+
+// A sinc, which is the ideal low-pass filter, windowed by a wider sinc stretched by |tau| so that
+// it reaches zero at the radius. Sharp, but rings around edges.
+pub struct LanczosSincFilter {
+    pub radius: (f32, f32),
+    pub tau: f32,
+}
+
+impl LanczosSincFilter {
+    fn windowed_sinc(&self, x: f32, radius: f32) -> f32 {
+        if x.abs() > radius {
+            0.0
+        } else {
+            sinc(x) * sinc(x / self.tau)
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    let x = x.abs();
+    if x < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+impl Filter for LanczosSincFilter {
+    fn radius(&self) -> (f32, f32) {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f32, y: f32) -> f32 {
+        self.windowed_sinc(x, self.radius.0) * self.windowed_sinc(y, self.radius.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windowed_sinc() {
+        let filter = LanczosSincFilter { radius: (4.0, 4.0), tau: 3.0 };
+        assert_eq!(filter.evaluate(0.0, 0.0), 1.0);
+        // Zero at every other integer and negative in between.
+        assert!(filter.evaluate(1.0, 0.0).abs() < 1e-6);
+        assert!(filter.evaluate(1.5, 0.0) < 0.0);
+        assert_eq!(filter.evaluate(0.0, 4.5), 0.0);
+    }
+}
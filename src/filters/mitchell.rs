@@ -0,0 +1,59 @@
+use crate::filters::Filter;
+
+// Mitchell and Netravali's family of cubic filters ("Reconstruction Filters in Computer
+// Graphics", 1988), stretched over the radius. |b| and |c| trade blurring against ringing;
+// they recommend b = c = 1/3.
+pub struct MitchellFilter {
+    pub radius: (f32, f32),
+    pub b: f32,
+    pub c: f32,
+}
+
+impl MitchellFilter {
+    // The cubic over [-2, 2].
+    fn mitchell_1d(&self, x: f32) -> f32 {
+        let (b, c) = (self.b, self.c);
+        let x = x.abs();
+        if x > 2.0 {
+            0.0
+        } else if x > 1.0 {
+            ((-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x +
+             (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+        } else {
+            ((12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x +
+             (6.0 - 2.0 * b)) / 6.0
+        }
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> (f32, f32) {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f32, y: f32) -> f32 {
+        self.mitchell_1d(2.0 * x / self.radius.0) * self.mitchell_1d(2.0 * y / self.radius.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cubic() {
+        let filter = MitchellFilter { radius: (2.0, 2.0), b: 1.0 / 3.0, c: 1.0 / 3.0 };
+
+        // The cubic is continuous, has negative lobes, and its integer translates sum to 1.
+        for &x in &[1.0, 2.0] {
+            assert!((filter.mitchell_1d(x - 1e-4) - filter.mitchell_1d(x + 1e-4)).abs() < 1e-3);
+        }
+        assert!(filter.mitchell_1d(1.5) < 0.0);
+        for &x in &[0.0, 0.3, 0.5] {
+            let sum = (-2..=2).map(|i| filter.mitchell_1d(x + i as f32)).sum::<f32>();
+            assert!((sum - 1.0).abs() < 1e-5);
+        }
+        assert!(filter.evaluate(2.0, 0.0).abs() < 1e-6);
+        assert_eq!(filter.evaluate(2.5, 0.0), 0.0);
+    }
+}
@@ -0,0 +1,13 @@
+pub mod box_filter;
+pub mod filter;
+pub mod gaussian;
+pub mod lanczos;
+pub mod mitchell;
+pub mod triangle;
+
+pub use self::box_filter::BoxFilter;
+pub use self::filter::Filter;
+pub use self::gaussian::GaussianFilter;
+pub use self::lanczos::LanczosSincFilter;
+pub use self::mitchell::MitchellFilter;
+pub use self::triangle::TriangleFilter;
@@ -0,0 +1,16 @@
+use crate::filters::Filter;
+
+// A tent that falls off linearly from the center to zero at the radius.
+pub struct TriangleFilter {
+    pub radius: (f32, f32),
+}
+
+impl Filter for TriangleFilter {
+    fn radius(&self) -> (f32, f32) {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f32, y: f32) -> f32 {
+        (self.radius.0 - x.abs()).max(0.0) * (self.radius.1 - y.abs()).max(0.0)
+    }
+}
@@ -0,0 +1,167 @@
+use crate::geometry::{Point3, Ray, Vec3};
+
+// An axis-aligned bounding box.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bounds3 {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Bounds3 {
+    pub fn new(p1: Point3, p2: Point3) -> Self {
+        Bounds3 {
+            min: Point3::new(p1.x.min(p2.x), p1.y.min(p2.y), p1.z.min(p2.z)),
+            max: Point3::new(p1.x.max(p2.x), p1.y.max(p2.y), p1.z.max(p2.z)),
+        }
+    }
+
+    // Returns a box that contains nothing. The union of the empty box with any other box is the
+    // other box.
+    pub fn empty() -> Self {
+        Bounds3 {
+            min: Point3::new(f32::MAX, f32::MAX, f32::MAX),
+            max: Point3::new(f32::MIN, f32::MIN, f32::MIN),
+        }
+    }
+
+    pub fn from_pt(p: Point3) -> Self {
+        Bounds3 { min: p, max: p }
+    }
+
+    pub fn union(b1: Self, b2: Self) -> Self {
+        Bounds3 {
+            min: Point3::new(b1.min.x.min(b2.min.x), b1.min.y.min(b2.min.y),
+                             b1.min.z.min(b2.min.z)),
+            max: Point3::new(b1.max.x.max(b2.max.x), b1.max.y.max(b2.max.y),
+                             b1.max.z.max(b2.max.z)),
+        }
+    }
+
+    pub fn union_pt(b: Self, p: Point3) -> Self {
+        Bounds3::union(b, Bounds3::from_pt(p))
+    }
+
+    // Returns a box that contains everything.
+    pub fn infinite() -> Self {
+        Bounds3 {
+            min: Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+            max: Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+        }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.min.x.is_finite() && self.min.y.is_finite() && self.min.z.is_finite() &&
+            self.max.x.is_finite() && self.max.y.is_finite() && self.max.z.is_finite()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn diagonal(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        Point3::new(0.5 * (self.min.x + self.max.x),
+                    0.5 * (self.min.y + self.max.y),
+                    0.5 * (self.min.z + self.max.z))
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let d = self.diagonal();
+        2.0 * (d.x * d.y + d.x * d.z + d.y * d.z)
+    }
+
+    // Returns the index of the axis along which the box is the longest.
+    pub fn max_extent(&self) -> usize {
+        let d = self.diagonal();
+        if d.x > d.y && d.x > d.z {
+            0
+        } else if d.y > d.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    // Returns the position of |p| relative to the corners of the box, where the min corner is at
+    // (0, 0, 0) and the max corner is at (1, 1, 1).
+    pub fn offset(&self, p: Point3) -> Vec3 {
+        let mut o = p - self.min;
+        for i in 0..3 {
+            if self.max[i] > self.min[i] {
+                o[i] /= self.max[i] - self.min[i];
+            }
+        }
+        o
+    }
+
+    // Returns whether |ray| passes through the box for some t in [0, t_max]. |inv_d| is the
+    // componentwise reciprocal of the ray direction, which callers precompute since the same ray
+    // is usually tested against many boxes.
+    pub fn intersect_p(&self, ray: &Ray, inv_d: Vec3, t_max: f32) -> bool {
+        let mut t0 = 0.0_f32;
+        let mut t1 = t_max;
+
+        for i in 0..3 {
+            let mut t_near = (self.min[i] - ray.p[i]) * inv_d[i];
+            let mut t_far = (self.max[i] - ray.p[i]) * inv_d[i];
+            if t_near > t_far {
+                std::mem::swap(&mut t_near, &mut t_far);
+            }
+
+            // f32::max and f32::min ignore NaNs, which appear when the ray lies in one of the
+            // slab planes.
+            t0 = t0.max(t_near);
+            t1 = t1.min(t_far);
+            if t0 > t1 {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn union() {
+        let a = Bounds3::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+        let b = Bounds3::new(Point3::new(2.0, -1.0, 0.5), Point3::new(3.0, 0.0, 0.5));
+        let b = Bounds3::union(a, b);
+        assert_eq!(b.min, Point3::new(0.0, -1.0, 0.0));
+        assert_eq!(b.max, Point3::new(3.0, 1.0, 1.0));
+        assert_eq!(b.max_extent(), 0);
+        assert_eq!(b.surface_area(), 2.0 * (6.0 + 3.0 + 2.0));
+
+        assert!(Bounds3::empty().is_empty());
+        assert_eq!(Bounds3::union(Bounds3::empty(), b), b);
+        assert_eq!(Bounds3::empty().surface_area(), 0.0);
+    }
+
+    #[test]
+    fn intersect_p() {
+        let b = Bounds3::new(Point3::new(-1.0, -1.0, 1.0), Point3::new(1.0, 1.0, 2.0));
+        let inv = |d: Vec3| Vec3::new(1.0 / d.x, 1.0 / d.y, 1.0 / d.z);
+
+        let ray = Ray { p: Point3::zeroes(), d: Vec3::new(0.0, 0.0, 1.0) };
+        assert!(b.intersect_p(&ray, inv(ray.d), f32::MAX));
+        assert!(!b.intersect_p(&ray, inv(ray.d), 0.5));
+
+        let ray = Ray { p: Point3::zeroes(), d: Vec3::new(0.0, 0.0, -1.0) };
+        assert!(!b.intersect_p(&ray, inv(ray.d), f32::MAX));
+
+        let ray = Ray { p: Point3::new(2.0, 0.0, 0.0), d: Vec3::new(0.0, 0.0, 1.0) };
+        assert!(!b.intersect_p(&ray, inv(ray.d), f32::MAX));
+
+        let ray = Ray { p: Point3::new(-2.0, 0.0, 1.5), d: Vec3::new(1.0, 0.0, 0.0) };
+        assert!(b.intersect_p(&ray, inv(ray.d), f32::MAX));
+    }
+}
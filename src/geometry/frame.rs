@@ -0,0 +1,84 @@
+use crate::geometry::Vec3;
+
+// An orthonormal basis with |n| as the z axis. Used to move directions to and from the local
+// shading coordinate system that BxDFs work in.
+#[derive(Debug, Copy, Clone)]
+pub struct Frame {
+    pub s: Vec3,
+    pub t: Vec3,
+    pub n: Vec3,
+}
+
+impl Frame {
+    // |n| must be normalized.
+    pub fn from_normal(n: Vec3) -> Self {
+        // Duff et al., "Building an Orthonormal Basis, Revisited".
+        let sign = 1.0_f32.copysign(n.z);
+        let a = -1.0 / (sign + n.z);
+        let b = n.x * n.y * a;
+
+        Frame {
+            s: Vec3::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x),
+            t: Vec3::new(b, sign + n.y * n.y * a, -n.y),
+            n,
+        }
+    }
+
+    // The frame with |n| as the z axis and the x axis as close to |s| as possible. Falls back to
+    // from_normal() when |s| is parallel to |n| or zero.
+    pub fn from_normal_tangent(n: Vec3, s: Vec3) -> Self {
+        let s = s - Vec3::dot(s, n) * n;
+        let len_sq = s.len_sq();
+        // Also catches NaNs from degenerate tangents.
+        if !(len_sq > 1e-12 && len_sq.is_finite()) {
+            return Frame::from_normal(n);
+        }
+        let s = s / len_sq.sqrt();
+        Frame { s, t: Vec3::cross(n, s), n }
+    }
+
+    pub fn world_to_local(&self, v: Vec3) -> Vec3 {
+        Vec3::new(Vec3::dot(v, self.s), Vec3::dot(v, self.t), Vec3::dot(v, self.n))
+    }
+
+    pub fn local_to_world(&self, v: Vec3) -> Vec3 {
+        v.x * self.s + v.y * self.t + v.z * self.n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orthonormal() {
+        let normals = [Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0),
+                       Vec3::normalize(Vec3::new(1.0, -2.0, 0.5))];
+
+        for &n in &normals {
+            let frame = Frame::from_normal(n);
+            assert!(Vec3::dot(frame.s, frame.t).abs() < 1e-6);
+            assert!(Vec3::dot(frame.s, frame.n).abs() < 1e-6);
+            assert!((frame.s.len() - 1.0).abs() < 1e-6);
+            assert!((frame.t.len() - 1.0).abs() < 1e-6);
+
+            let v = Vec3::new(0.3, -0.4, 0.5);
+            let round_trip = frame.local_to_world(frame.world_to_local(v));
+            assert!((round_trip - v).len() < 1e-6);
+            assert!((frame.world_to_local(n).z - 1.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn tangent() {
+        let n = Vec3::normalize(Vec3::new(1.0, -2.0, 0.5));
+        let frame = Frame::from_normal_tangent(n, Vec3::new(0.0, 3.0, 0.0));
+        assert!(Vec3::dot(frame.s, n).abs() < 1e-6 && (frame.s.len() - 1.0).abs() < 1e-6);
+        assert!(frame.s.y > 0.0);
+        let default = Frame::from_normal(n);
+        assert!((Vec3::cross(frame.s, frame.t) - Vec3::cross(default.s, default.t)).len() < 1e-6);
+
+        let frame = Frame::from_normal_tangent(n, 2.0 * n);
+        assert!((frame.s - default.s).len() < 1e-6);
+    }
+}
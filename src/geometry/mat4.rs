@@ -9,6 +9,7 @@ pub struct Mat4 {
 }
 
 impl Mat4 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(a00: f32, a01: f32, a02: f32, a03: f32,
                a10: f32, a11: f32, a12: f32, a13: f32,
                a20: f32, a21: f32, a22: f32, a23: f32,
@@ -62,7 +63,7 @@ impl Mat4 {
     }
 }
 
-impl<'a, 'b>  Mul<&'b Mat4> for &'a Mat4 {
+impl<'b> Mul<&'b Mat4> for &Mat4 {
     type Output = Mat4;
     
     fn mul(self, rhs: &'b Mat4) -> Self::Output {
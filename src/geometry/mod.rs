@@ -1,9 +1,13 @@
+pub mod bounds3;
+pub mod frame;
 pub mod mat4;
 pub mod point3;
 pub mod ray;
 pub mod transform;
 pub mod vec3;
 
+pub use self::bounds3::Bounds3;
+pub use self::frame::Frame;
 pub use self::mat4::Mat4;
 pub use self::point3::Point3;
 pub use self::ray::Ray;
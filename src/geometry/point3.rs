@@ -150,6 +150,18 @@ impl Add<Vec3> for Point3 {
     }
 }
 
+impl Sub<Vec3> for Point3 {
+    type Output = Self;
+
+    fn sub(self, v: Vec3) -> Self::Output {
+        Self {
+            x: self.x - v.x,
+            y: self.y - v.y,
+            z: self.z - v.z,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,4 +1,4 @@
-use crate::geometry::{Mat4, Point3, Vec3};
+use crate::geometry::{Bounds3, Mat4, Point3, Vec3};
 
 #[derive(Debug)]
 pub struct Transform {
@@ -64,6 +64,22 @@ impl Transform {
         }
     }
 
+    // Returns the camera-to-world transform for a camera at |eye| looking at |target|. In camera
+    // space the camera looks down the +z axis, with +y as close to |up| as possible and +x to the
+    // right. |up| must not be parallel to the viewing direction.
+    pub fn look_at(eye: Point3, target: Point3, up: Vec3) -> Transform {
+        let dir = Vec3::normalize(target - eye);
+        let right = Vec3::normalize(Vec3::cross(Vec3::normalize(up), dir));
+        let new_up = Vec3::cross(dir, right);
+
+        Transform {
+            mat: Mat4::new(right.x, new_up.x, dir.x, eye.x,
+                           right.y, new_up.y, dir.y, eye.y,
+                           right.z, new_up.z, dir.z, eye.z,
+                           0.0, 0.0, 0.0, 1.0),
+        }
+    }
+
     pub fn compose(&self, other: &Transform) -> Transform {
         Transform {
             mat: &self.mat * &other.mat,
@@ -85,6 +101,16 @@ impl Transform {
             z: self.mat.a20 * p.x + self.mat.a21 * p.y + self.mat.a22 * p.z + self.mat.a23,
         }
     }
+
+    // Returns the box around the transformed corners of |b|.
+    pub fn apply_bounds(&self, b: &Bounds3) -> Bounds3 {
+        (0..8).fold(Bounds3::empty(), |bounds, i| {
+            let corner = Point3::new(if i & 1 == 0 { b.min.x } else { b.max.x },
+                                     if i & 2 == 0 { b.min.y } else { b.max.y },
+                                     if i & 4 == 0 { b.min.z } else { b.max.z });
+            Bounds3::union_pt(bounds, self.apply_pt(&corner))
+        })
+    }
 }
 
 #[cfg(test)]
@@ -94,10 +120,9 @@ mod tests {
     // TODO: How to deal with numerical inaccuracies more generally.
     fn vec_equal(v1: &Vec3, v2: &Vec3) -> bool {
         let epsilon = 0.0001;
-        let is_equal = (v1.x - v2.x).abs() < epsilon &&     
-                       (v1.y - v2.y).abs() < epsilon &&  
-                       (v1.z - v2.z).abs() < epsilon;
-        is_equal
+        (v1.x - v2.x).abs() < epsilon &&
+        (v1.y - v2.y).abs() < epsilon &&
+        (v1.z - v2.z).abs() < epsilon
     }
 
     #[test]
@@ -114,4 +139,22 @@ mod tests {
         let v3 = Vec3::new(1.0, 0.0, 0.0);
         assert!(vec_equal(&transform3.apply_vec(&v3), &Vec3::new(0.0, 1.0, 0.0)));
     }
+
+    #[test]
+    fn look_at() {
+        let eye = Point3::new(0.0, 10.0, -10.0);
+        let transform = Transform::look_at(eye, Point3::new(0.0, 0.0, 0.0),
+                                           Vec3::new(0.0, 1.0, 0.0));
+
+        assert_eq!(transform.apply_pt(&Point3::zeroes()), eye);
+
+        let forward = transform.apply_vec(&Vec3::new(0.0, 0.0, 1.0));
+        assert!(vec_equal(&forward, &Vec3::normalize(Vec3::new(0.0, -1.0, 1.0))));
+
+        let right = transform.apply_vec(&Vec3::new(1.0, 0.0, 0.0));
+        assert!(vec_equal(&right, &Vec3::new(1.0, 0.0, 0.0)));
+
+        let up = transform.apply_vec(&Vec3::new(0.0, 1.0, 0.0));
+        assert!(vec_equal(&up, &Vec3::normalize(Vec3::new(0.0, 1.0, 1.0))));
+    }
 }
@@ -0,0 +1,524 @@
+use std::io::{self, Read, Write};
+
+use crate::film::Film;
+use crate::rgb::Rgb;
+
+const MAGIC: u32 = 20000630;
+// Version 2, single-part scanline file.
+const VERSION: u32 = 2;
+
+// Pixel type codes.
+const PIXEL_TYPE_UINT: i32 = 0;
+const PIXEL_TYPE_HALF: i32 = 1;
+const PIXEL_TYPE_FLOAT: i32 = 2;
+
+// Flags in the version field for the variants of the format that aren't supported.
+const VERSION_TILED: u32 = 0x200;
+const VERSION_DEEP: u32 = 0x800;
+const VERSION_MULTIPART: u32 = 0x1000;
+
+// Number of scanlines in each chunk with ZIP compression.
+const ZIP_LINES_PER_CHUNK: usize = 16;
+
+// zlib compression level used for ZIP compression.
+const ZIP_LEVEL: u8 = 6;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ExrCompression {
+    None,
+    // zlib compression of blocks of 16 scanlines.
+    Zip,
+}
+
+impl ExrCompression {
+    fn code(self) -> u8 {
+        match self {
+            ExrCompression::None => 0,
+            ExrCompression::Zip => 3,
+        }
+    }
+
+    fn lines_per_chunk(self) -> usize {
+        match self {
+            ExrCompression::None => 1,
+            ExrCompression::Zip => ZIP_LINES_PER_CHUNK,
+        }
+    }
+}
+
+// Writes |film| as a single-part scanline OpenEXR image with 32-bit float R, G and B channels.
+pub fn write_exr<W: Write>(w: &mut W, film: &Film, compression: ExrCompression) -> io::Result<()> {
+    let mut header = Vec::new();
+    header.extend_from_slice(&MAGIC.to_le_bytes());
+    header.extend_from_slice(&VERSION.to_le_bytes());
+
+    // The channels have to be listed in alphabetical order, which is also the order they're
+    // stored in within each scanline.
+    let mut channels = Vec::new();
+    for name in &["B", "G", "R"] {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        channels.extend_from_slice(&PIXEL_TYPE_FLOAT.to_le_bytes());
+        // pLinear and three reserved bytes.
+        channels.extend_from_slice(&[0, 0, 0, 0]);
+        // x and y sampling.
+        channels.extend_from_slice(&1i32.to_le_bytes());
+        channels.extend_from_slice(&1i32.to_le_bytes());
+    }
+    channels.push(0);
+
+    let mut window = Vec::new();
+    for &v in &[0, 0, film.width as i32 - 1, film.height as i32 - 1] {
+        window.extend_from_slice(&i32::to_le_bytes(v));
+    }
+
+    write_attribute(&mut header, "channels", "chlist", &channels);
+    write_attribute(&mut header, "compression", "compression", &[compression.code()]);
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+    // Increasing y.
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    write_attribute(&mut header, "pixelAspectRatio", "float", &1f32.to_le_bytes());
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(&mut header, "screenWindowWidth", "float", &1f32.to_le_bytes());
+    header.push(0);
+
+    let lines_per_chunk = compression.lines_per_chunk();
+    let mut chunks = Vec::new();
+    for y0 in (0..film.height).step_by(lines_per_chunk) {
+        let y1 = (y0 + lines_per_chunk).min(film.height);
+
+        let mut data = Vec::with_capacity((y1 - y0) * film.width * 3 * 4);
+        for y in y0..y1 {
+            for c in 0..3 {
+                for x in 0..film.width {
+                    let pixel = film.get(x, y);
+                    let v = [pixel.b, pixel.g, pixel.r][c];
+                    data.extend_from_slice(&v.to_le_bytes());
+                }
+            }
+        }
+
+        let data = match compression {
+            ExrCompression::None => data,
+            ExrCompression::Zip => {
+                // Readers treat chunks that don't shrink as uncompressed.
+                let compressed = zip_compress(&data);
+                if compressed.len() < data.len() { compressed } else { data }
+            },
+        };
+        chunks.push((y0, data));
+    }
+
+    // The offset table gives the position of each chunk from the start of the file.
+    let mut offset = (header.len() + 8 * chunks.len()) as u64;
+    let mut offsets = Vec::with_capacity(8 * chunks.len());
+    for (_, data) in &chunks {
+        offsets.extend_from_slice(&offset.to_le_bytes());
+        offset += 8 + data.len() as u64;
+    }
+
+    w.write_all(&header)?;
+    w.write_all(&offsets)?;
+    for (y, data) in &chunks {
+        w.write_all(&(*y as i32).to_le_bytes())?;
+        w.write_all(&(data.len() as i32).to_le_bytes())?;
+        w.write_all(data)?;
+    }
+
+    Ok(())
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, type_name: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(type_name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+// Compresses a chunk the way OpenEXR's ZIP compression does: the bytes are split into those at
+// even and odd positions, which groups the more predictable high bytes of the floats together,
+// and stored as differences from the previous byte before being passed to zlib.
+fn zip_compress(data: &[u8]) -> Vec<u8> {
+    let mut reordered = Vec::with_capacity(data.len());
+    reordered.extend(data.iter().step_by(2));
+    reordered.extend(data.iter().skip(1).step_by(2));
+
+    let mut prev = reordered.first().copied().unwrap_or(0);
+    for b in reordered.iter_mut().skip(1) {
+        let cur = *b;
+        *b = cur.wrapping_sub(prev).wrapping_add(128);
+        prev = cur;
+    }
+
+    miniz_oxide::deflate::compress_to_vec_zlib(&reordered, ZIP_LEVEL)
+}
+
+// Inverse of zip_compress().
+fn zip_decompress(compressed: &[u8]) -> io::Result<Vec<u8>> {
+    let mut reordered = miniz_oxide::inflate::decompress_to_vec_zlib(compressed)
+        .map_err(|_| invalid_data("corrupt ZIP compressed data"))?;
+    for i in 1..reordered.len() {
+        reordered[i] = reordered[i].wrapping_add(reordered[i - 1]).wrapping_sub(128);
+    }
+
+    let half = reordered.len().div_ceil(2);
+    let mut data = Vec::with_capacity(reordered.len());
+    for i in 0..half {
+        data.push(reordered[i]);
+        if half + i < reordered.len() {
+            data.push(reordered[half + i]);
+        }
+    }
+    Ok(data)
+}
+
+// Reads a single-part scanline OpenEXR image that's uncompressed or uses ZIP or ZIPS compression.
+// The R, G and B channels may be half, float or unsigned int; an image with only a Y channel is
+// read as grayscale. Other channels, such as alpha, are ignored.
+pub fn read_exr<R: Read>(r: &mut R) -> io::Result<Film> {
+    let mut bytes = Vec::new();
+    r.read_to_end(&mut bytes)?;
+    let mut cursor = ByteCursor { bytes: &bytes, pos: 0 };
+
+    if cursor.u32()? != MAGIC {
+        return Err(invalid_data("not an OpenEXR image"));
+    }
+    let version = cursor.u32()?;
+    if version & 0xff != VERSION {
+        return Err(invalid_data(format!("unsupported version {}", version & 0xff)));
+    }
+    if version & (VERSION_TILED | VERSION_DEEP | VERSION_MULTIPART) != 0 {
+        return Err(invalid_data("only single-part scanline images are supported"));
+    }
+
+    let mut channels = Vec::new();
+    let mut compression = None;
+    let mut window = None;
+    loop {
+        let name = cursor.string()?;
+        if name.is_empty() {
+            break;
+        }
+        let type_name = cursor.string()?;
+        let size = cursor.i32()?;
+        if size < 0 {
+            return Err(invalid_data("invalid attribute size"));
+        }
+        let mut value = ByteCursor { bytes: cursor.take(size as usize)?, pos: 0 };
+
+        match (name.as_str(), type_name.as_str()) {
+            ("channels", "chlist") => {
+                loop {
+                    let name = value.string()?;
+                    if name.is_empty() {
+                        break;
+                    }
+                    let pixel_type = value.i32()?;
+                    value.take(4)?;
+                    let (x_sampling, y_sampling) = (value.i32()?, value.i32()?);
+                    if x_sampling != 1 || y_sampling != 1 {
+                        return Err(invalid_data("subsampled channels aren't supported"));
+                    }
+                    channels.push((name, pixel_type));
+                }
+            },
+            ("compression", "compression") => compression = Some(value.take(1)?[0]),
+            ("dataWindow", "box2i") => {
+                window = Some((value.i32()?, value.i32()?, value.i32()?, value.i32()?));
+            },
+            _ => (),
+        }
+    }
+
+    let (x_min, y_min, x_max, y_max) = window.ok_or_else(|| invalid_data("missing dataWindow"))?;
+    if x_max < x_min || y_max < y_min {
+        return Err(invalid_data("invalid dataWindow"));
+    }
+    let width = (x_max as i64 - x_min as i64 + 1) as usize;
+    let height = (y_max as i64 - y_min as i64 + 1) as usize;
+
+    let lines_per_chunk = match compression {
+        // No compression and ZIPS, which compresses single scanlines.
+        Some(0) | Some(2) => 1,
+        Some(3) => ZIP_LINES_PER_CHUNK,
+        Some(c) => return Err(invalid_data(format!("unsupported compression type {}", c))),
+        None => return Err(invalid_data("missing compression")),
+    };
+
+    // Where each channel's values start within a scanline, relative to the start of the
+    // scanline, and how many bytes each value takes.
+    let mut offsets = Vec::with_capacity(channels.len());
+    let mut line_size = 0;
+    for (_, pixel_type) in &channels {
+        let size = match *pixel_type {
+            PIXEL_TYPE_HALF => 2,
+            PIXEL_TYPE_UINT | PIXEL_TYPE_FLOAT => 4,
+            t => return Err(invalid_data(format!("unknown pixel type {}", t))),
+        };
+        offsets.push(line_size);
+        line_size += size * width;
+    }
+
+    let find = |name: &str| channels.iter().position(|(n, _)| n == name);
+    let rgb = match (find("R"), find("G"), find("B"), find("Y")) {
+        (Some(r), Some(g), Some(b), _) => [r, g, b],
+        (_, _, _, Some(y)) => [y, y, y],
+        _ => return Err(invalid_data("the image has no RGB or Y channels")),
+    };
+
+    let num_chunks = height.div_ceil(lines_per_chunk);
+    let mut film = Film::new(width, height);
+    for _ in 0..num_chunks {
+        let offset = cursor.u64()? as usize;
+        let mut chunk = ByteCursor { bytes: &bytes, pos: offset };
+
+        let y0 = chunk.i32()? as i64 - y_min as i64;
+        if y0 < 0 || y0 as usize >= height {
+            return Err(invalid_data("chunk is outside the data window"));
+        }
+        let y0 = y0 as usize;
+        let lines = lines_per_chunk.min(height - y0);
+
+        let size = chunk.i32()?;
+        if size < 0 {
+            return Err(invalid_data("invalid chunk size"));
+        }
+        let data = chunk.take(size as usize)?;
+
+        // Chunks that don't shrink are stored uncompressed.
+        let raw_size = lines * line_size;
+        let data = if data.len() < raw_size {
+            zip_decompress(data)?
+        } else {
+            data.to_vec()
+        };
+        if data.len() != raw_size {
+            return Err(invalid_data("chunk has the wrong size"));
+        }
+
+        for line in 0..lines {
+            let line_data = &data[line * line_size..(line + 1) * line_size];
+            for x in 0..width {
+                let v = |c: usize| {
+                    let (_, pixel_type) = channels[c];
+                    read_value(line_data, offsets[c], x, pixel_type)
+                };
+                film.set(x, y0 + line, Rgb::new(v(rgb[0]), v(rgb[1]), v(rgb[2])));
+            }
+        }
+    }
+
+    Ok(film)
+}
+
+// Reads the |x|th value of a channel of type |pixel_type| whose values start at |offset|.
+fn read_value(data: &[u8], offset: usize, x: usize, pixel_type: i32) -> f32 {
+    match pixel_type {
+        PIXEL_TYPE_HALF => {
+            let pos = offset + 2 * x;
+            half_to_f32(u16::from_le_bytes([data[pos], data[pos + 1]]))
+        },
+        _ => {
+            let pos = offset + 4 * x;
+            let bytes = [data[pos], data[pos + 1], data[pos + 2], data[pos + 3]];
+            if pixel_type == PIXEL_TYPE_FLOAT {
+                f32::from_le_bytes(bytes)
+            } else {
+                u32::from_le_bytes(bytes) as f32
+            }
+        },
+    }
+}
+
+// Converts an IEEE 754 half precision float to an f32.
+fn half_to_f32(h: u16) -> f32 {
+    let sign = ((h >> 15) as u32) << 31;
+    let exponent = ((h >> 10) & 0x1f) as u32;
+    let mantissa = (h & 0x3ff) as u32;
+
+    let bits = match (exponent, mantissa) {
+        (0, 0) => sign,
+        // Subnormal halves are normal f32s.
+        (0, m) => {
+            let shift = m.leading_zeros() - 21;
+            let m = (m << shift) & 0x3ff;
+            sign | ((127 - 15 + 1 - shift) << 23) | (m << 13)
+        },
+        (0x1f, m) => sign | 0x7f80_0000 | (m << 13),
+        (e, m) => sign | ((e + 127 - 15) << 23) | (m << 13),
+    };
+    f32::from_bits(bits)
+}
+
+// Reads little-endian values from a byte slice.
+struct ByteCursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteCursor<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.pos.checked_add(n).is_none_or(|end| end > self.bytes.len()) {
+            return Err(invalid_data("unexpected end of file"));
+        }
+        let bytes = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(self.u32()? as i32)
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        let mut b = [0u8; 8];
+        b.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(b))
+    }
+
+    // Reads a null-terminated string.
+    fn string(&mut self) -> io::Result<String> {
+        let rest = &self.bytes[self.pos.min(self.bytes.len())..];
+        let len = rest.iter().position(|&b| b == 0)
+            .ok_or_else(|| invalid_data("unterminated string"))?;
+        let s = String::from_utf8_lossy(&rest[..len]).into_owned();
+        self.pos += len + 1;
+        Ok(s)
+    }
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_i32(bytes: &[u8], pos: usize) -> i32 {
+        i32::from_le_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]])
+    }
+
+    fn read_f32(bytes: &[u8], pos: usize) -> f32 {
+        f32::from_le_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]])
+    }
+
+    // Reads back the pixels of a file written by write_exr(), relying on the known header size.
+    fn read_pixels(bytes: &[u8], header_len: usize, width: usize, height: usize,
+                   compression: ExrCompression) -> Vec<Rgb> {
+        let lines_per_chunk = compression.lines_per_chunk();
+        let num_chunks = height.div_ceil(lines_per_chunk);
+
+        let mut pixels = vec![Rgb::new(0.0, 0.0, 0.0); width * height];
+        for chunk in 0..num_chunks {
+            let offset_pos = header_len + 8 * chunk;
+            let mut offset = [0u8; 8];
+            offset.copy_from_slice(&bytes[offset_pos..offset_pos + 8]);
+            let pos = u64::from_le_bytes(offset) as usize;
+
+            let y0 = read_i32(bytes, pos) as usize;
+            assert_eq!(y0, chunk * lines_per_chunk);
+            let size = read_i32(bytes, pos + 4) as usize;
+            let lines = lines_per_chunk.min(height - y0);
+            let raw_size = lines * width * 3 * 4;
+
+            let data = &bytes[pos + 8..pos + 8 + size];
+            let data = if size < raw_size { zip_decompress(data).unwrap() } else { data.to_vec() };
+            assert_eq!(data.len(), raw_size);
+
+            for line in 0..lines {
+                for x in 0..width {
+                    let base = line * width * 12 + 4 * x;
+                    pixels[(y0 + line) * width + x] = Rgb::new(read_f32(&data, base + 8 * width),
+                                                               read_f32(&data, base + 4 * width),
+                                                               read_f32(&data, base));
+                }
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn write() {
+        let (width, height) = (37, 21);
+        let mut film = Film::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                film.set(x, y, Rgb::new(x as f32 * 0.1, y as f32 * 10.0, -0.5));
+            }
+        }
+
+        for &compression in &[ExrCompression::None, ExrCompression::Zip] {
+            let mut bytes = Vec::new();
+            write_exr(&mut bytes, &film, compression).unwrap();
+
+            assert_eq!(&bytes[..8], &[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+
+            // The header ends after the last attribute's value, which is the 1.0 of
+            // screenWindowWidth.
+            let name = b"screenWindowWidth\0float\0";
+            let pos = bytes.windows(name.len()).position(|w| w == name).unwrap() + name.len();
+            assert_eq!(read_i32(&bytes, pos), 4);
+            assert_eq!(read_f32(&bytes, pos + 4), 1.0);
+            let header_len = pos + 8 + 1;
+            assert_eq!(bytes[header_len - 1], 0);
+
+            let pixels = read_pixels(&bytes, header_len, width, height, compression);
+            assert_eq!(pixels, film.pixels);
+
+            let read = read_exr(&mut &bytes[..]).unwrap();
+            assert_eq!((read.width, read.height), (width, height));
+            assert_eq!(read.pixels, film.pixels);
+        }
+    }
+
+    #[test]
+    fn half() {
+        assert_eq!(half_to_f32(0x0000), 0.0);
+        assert_eq!(half_to_f32(0x3c00), 1.0);
+        assert_eq!(half_to_f32(0xc000), -2.0);
+        assert_eq!(half_to_f32(0x3555), 0.333_251_95);
+        assert_eq!(half_to_f32(0x7bff), 65504.0);
+        // The smallest subnormal.
+        assert_eq!(half_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(half_to_f32(0x03ff), 1023.0 * 2f32.powi(-24));
+        assert_eq!(half_to_f32(0x7c00), f32::INFINITY);
+        assert!(half_to_f32(0x7e00).is_nan());
+    }
+
+    #[test]
+    fn read_errors() {
+        assert!(read_exr(&mut &b"PF\n"[..]).is_err());
+
+        let mut bytes = Vec::new();
+        write_exr(&mut bytes, &Film::new(4, 4), ExrCompression::Zip).unwrap();
+        // Tiled.
+        let mut tiled = bytes.clone();
+        tiled[5] |= 0x02;
+        assert!(read_exr(&mut &tiled[..]).is_err());
+        // Truncated.
+        assert!(read_exr(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn zip_shrinks_smooth_images() {
+        let mut film = Film::new(64, 64);
+        for pixel in film.pixels.iter_mut() {
+            *pixel = Rgb::new(0.5, 0.25, 1.0);
+        }
+
+        let mut uncompressed = Vec::new();
+        write_exr(&mut uncompressed, &film, ExrCompression::None).unwrap();
+        let mut zip = Vec::new();
+        write_exr(&mut zip, &film, ExrCompression::Zip).unwrap();
+        assert!(zip.len() * 10 < uncompressed.len());
+    }
+}
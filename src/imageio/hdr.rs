@@ -0,0 +1,324 @@
+use std::io::{self, BufRead, Write};
+
+use crate::film::Film;
+use crate::rgb::Rgb;
+
+// Scanlines outside this range of widths can't be run-length encoded.
+const MIN_RLE_WIDTH: usize = 8;
+const MAX_RLE_WIDTH: usize = 0x7fff;
+
+// Longest run that a single run-length encoded packet can hold.
+const MAX_RUN: usize = 127;
+// Shortest run of repeated bytes that's worth encoding as a run.
+const MIN_RUN: usize = 3;
+
+// Writes |film| as a Radiance RGBE image. Each pixel is stored as three 8-bit mantissas that
+// share an 8-bit exponent, with the scanlines run-length encoded. Negative values are clamped to
+// zero since the format can't represent them.
+pub fn write_hdr<W: Write>(w: &mut W, film: &Film) -> io::Result<()> {
+    write!(w, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", film.height, film.width)?;
+
+    let mut scanline = Vec::with_capacity(film.width);
+    for y in 0..film.height {
+        scanline.clear();
+        scanline.extend((0..film.width).map(|x| to_rgbe(film.get(x, y))));
+
+        if film.width < MIN_RLE_WIDTH || film.width > MAX_RLE_WIDTH {
+            for rgbe in &scanline {
+                w.write_all(rgbe)?;
+            }
+        } else {
+            write_rle_scanline(w, &scanline)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Reads a Radiance RGBE image with the standard -Y H +X W orientation, with scanlines that are
+// either flat or in the "new" run-length encoding.
+pub fn read_hdr<R: BufRead>(r: &mut R) -> io::Result<Film> {
+    let mut line = String::new();
+    r.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(invalid_data("not a Radiance image"));
+    }
+
+    // The header is a list of variables terminated by an empty line.
+    loop {
+        line.clear();
+        if r.read_line(&mut line)? == 0 {
+            return Err(invalid_data("unexpected end of header"));
+        }
+        let var = line.trim_end();
+        if var.is_empty() {
+            break;
+        }
+        if let Some(format) = var.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid_data(format!("unsupported format '{}'", format)));
+            }
+        }
+    }
+
+    line.clear();
+    r.read_line(&mut line)?;
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    let (height, width) = match words.as_slice() {
+        ["-Y", h, "+X", w] => match (h.parse::<usize>(), w.parse::<usize>()) {
+            (Ok(h), Ok(w)) => (h, w),
+            _ => return Err(invalid_data("invalid resolution")),
+        },
+        _ => return Err(invalid_data(format!("unsupported orientation '{}'", line.trim_end()))),
+    };
+
+    let mut film = Film::new(width, height);
+    let mut scanline = vec![[0u8; 4]; width];
+    for y in 0..height {
+        read_scanline(r, &mut scanline)?;
+        for (x, &rgbe) in scanline.iter().enumerate() {
+            film.set(x, y, from_rgbe(rgbe));
+        }
+    }
+
+    Ok(film)
+}
+
+fn read_scanline<R: BufRead>(r: &mut R, scanline: &mut [[u8; 4]]) -> io::Result<()> {
+    let width = scanline.len();
+    if width == 0 {
+        return Ok(());
+    }
+
+    let mut first = [0u8; 4];
+    r.read_exact(&mut first)?;
+    let is_rle = (MIN_RLE_WIDTH..=MAX_RLE_WIDTH).contains(&width) && first[0] == 2 &&
+                 first[1] == 2 && first[2] & 0x80 == 0;
+    if !is_rle {
+        scanline[0] = first;
+        for rgbe in &mut scanline[1..] {
+            r.read_exact(rgbe)?;
+        }
+        return Ok(());
+    }
+
+    if ((first[2] as usize) << 8 | first[3] as usize) != width {
+        return Err(invalid_data("scanline width doesn't match the image"));
+    }
+
+    for c in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut count = [0u8; 1];
+            r.read_exact(&mut count)?;
+            let count = count[0] as usize;
+
+            if count > 128 {
+                let run = count - 128;
+                if x + run > width {
+                    return Err(invalid_data("run extends past the end of the scanline"));
+                }
+                let mut value = [0u8; 1];
+                r.read_exact(&mut value)?;
+                for rgbe in &mut scanline[x..x + run] {
+                    rgbe[c] = value[0];
+                }
+                x += run;
+            } else {
+                if count == 0 || x + count > width {
+                    return Err(invalid_data("invalid run length"));
+                }
+                let mut values = [0u8; 128];
+                r.read_exact(&mut values[..count])?;
+                for (rgbe, &v) in scanline[x..x + count].iter_mut().zip(&values[..count]) {
+                    rgbe[c] = v;
+                }
+                x += count;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+// Converts |rgb| to the shared exponent representation.
+pub fn to_rgbe(rgb: Rgb) -> [u8; 4] {
+    let (r, g, b) = (rgb.r.max(0.0), rgb.g.max(0.0), rgb.b.max(0.0));
+    let v = r.max(g).max(b);
+    if v < 1e-32 || !v.is_finite() {
+        return [0, 0, 0, 0];
+    }
+
+    // Find e such that v = m * 2^e with m in [0.5, 1).
+    let mut e = v.log2().floor() as i32 + 1;
+    if v >= 2f32.powi(e) {
+        e += 1;
+    } else if v < 2f32.powi(e - 1) {
+        e -= 1;
+    }
+    let e = e.clamp(-128, 127);
+
+    let scale = 256.0 / 2f32.powi(e);
+    let to_byte = |c: f32| (c * scale).min(255.0) as u8;
+    [to_byte(r), to_byte(g), to_byte(b), (e + 128) as u8]
+}
+
+// Inverse of to_rgbe(), up to quantization.
+pub fn from_rgbe(rgbe: [u8; 4]) -> Rgb {
+    if rgbe[3] == 0 {
+        return Rgb::new(0.0, 0.0, 0.0);
+    }
+
+    // Decode to the middle of the quantization interval.
+    let scale = 2f32.powi(rgbe[3] as i32 - 128 - 8);
+    Rgb::new((rgbe[0] as f32 + 0.5) * scale,
+             (rgbe[1] as f32 + 0.5) * scale,
+             (rgbe[2] as f32 + 0.5) * scale)
+}
+
+// Writes a scanline in the "new" run-length encoding, which stores each of the four components
+// separately as a sequence of runs and literal spans.
+fn write_rle_scanline<W: Write>(w: &mut W, scanline: &[[u8; 4]]) -> io::Result<()> {
+    let width = scanline.len();
+    w.write_all(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8])?;
+
+    let mut component = Vec::with_capacity(width);
+    let mut packets = Vec::with_capacity(2 * width);
+    for c in 0..4 {
+        component.clear();
+        component.extend(scanline.iter().map(|rgbe| rgbe[c]));
+        packets.clear();
+
+        let mut i = 0;
+        while i < width {
+            let run = run_length(&component[i..]);
+            if run >= MIN_RUN {
+                packets.push(128 + run as u8);
+                packets.push(component[i]);
+                i += run;
+                continue;
+            }
+
+            // Gather bytes until the next run that's worth encoding.
+            let start = i;
+            while i < width && i - start < MAX_RUN && run_length(&component[i..]) < MIN_RUN {
+                i += 1;
+            }
+            packets.push((i - start) as u8);
+            packets.extend_from_slice(&component[start..i]);
+        }
+
+        w.write_all(&packets)?;
+    }
+
+    Ok(())
+}
+
+// Returns how many times the first byte of |bytes| repeats, up to MAX_RUN.
+fn run_length(bytes: &[u8]) -> usize {
+    bytes.iter().take(MAX_RUN).take_while(|&&b| b == bytes[0]).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Decodes the pixels written by write_hdr().
+    fn decode(bytes: &[u8], width: usize, height: usize) -> Vec<[u8; 4]> {
+        let header = format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", height, width);
+        assert!(bytes.starts_with(header.as_bytes()));
+
+        let mut data = &bytes[header.len()..];
+        let mut pixels = Vec::new();
+        for _ in 0..height {
+            if width < MIN_RLE_WIDTH {
+                for _ in 0..width {
+                    pixels.push([data[0], data[1], data[2], data[3]]);
+                    data = &data[4..];
+                }
+                continue;
+            }
+
+            assert_eq!(&data[..4], &[2, 2, (width >> 8) as u8, (width & 0xff) as u8]);
+            data = &data[4..];
+            let mut scanline = vec![[0u8; 4]; width];
+            for c in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let count = data[0] as usize;
+                    if count > 128 {
+                        for pixel in &mut scanline[x..x + count - 128] {
+                            pixel[c] = data[1];
+                        }
+                        x += count - 128;
+                        data = &data[2..];
+                    } else {
+                        assert!(count > 0);
+                        for (pixel, &b) in scanline[x..x + count].iter_mut().zip(&data[1..]) {
+                            pixel[c] = b;
+                        }
+                        x += count;
+                        data = &data[1 + count..];
+                    }
+                }
+                assert_eq!(x, width);
+            }
+            pixels.extend(scanline);
+        }
+
+        assert!(data.is_empty());
+        pixels
+    }
+
+    #[test]
+    fn rgbe() {
+        assert_eq!(to_rgbe(Rgb::new(0.0, 0.0, 0.0)), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe(Rgb::new(1.0, 0.5, 0.0)), [128, 64, 0, 129]);
+        assert_eq!(to_rgbe(Rgb::new(-1.0, 0.0, 0.0)), [0, 0, 0, 0]);
+
+        for &v in &[1e-3, 0.3, 0.999, 1.0, 7.5, 1000.0] {
+            let decoded = from_rgbe(to_rgbe(Rgb::new(v, 0.5 * v, 0.0)));
+            assert!((decoded.r - v).abs() <= v / 256.0);
+            assert!((decoded.g - 0.5 * v).abs() <= v / 256.0);
+        }
+    }
+
+    #[test]
+    fn write() {
+        for &width in &[5, 300] {
+            let mut film = Film::new(width, 3);
+            for y in 0..3 {
+                for x in 0..width {
+                    // Mix runs with varying values.
+                    let v = if x % 50 < 20 { 1.0 } else { (x * y) as f32 * 0.01 };
+                    film.set(x, y, Rgb::new(v, 2.0 * v, 0.1));
+                }
+            }
+
+            let mut bytes = Vec::new();
+            write_hdr(&mut bytes, &film).unwrap();
+
+            let pixels = decode(&bytes, width, 3);
+            let expected = film.pixels.iter().map(|&p| to_rgbe(p)).collect::<Vec<_>>();
+            assert_eq!(pixels, expected);
+
+            let read = read_hdr(&mut &bytes[..]).unwrap();
+            assert_eq!((read.width, read.height), (width, 3));
+            let decoded = expected.iter().map(|&rgbe| from_rgbe(rgbe)).collect::<Vec<_>>();
+            assert_eq!(read.pixels, decoded);
+        }
+    }
+
+    #[test]
+    fn read_errors() {
+        assert!(read_hdr(&mut &b"P6\n"[..]).is_err());
+        assert!(read_hdr(&mut &b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n"[..]).is_err());
+        assert!(read_hdr(&mut &b"#?RADIANCE\n\n+Y 1 +X 1\n\0\0\0\0"[..]).is_err());
+        // Truncated pixel data.
+        assert!(read_hdr(&mut &b"#?RADIANCE\n\n-Y 1 +X 2\n\0\0\0\0"[..]).is_err());
+    }
+}
@@ -0,0 +1,24 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+use crate::film::Film;
+use crate::imageio::exr::read_exr;
+use crate::imageio::hdr::read_hdr;
+
+// Reads the linear radiance values of the high dynamic range image at |path|, in the format given
+// by the path's extension: .exr or .hdr.
+pub fn read_image(path: &Path) -> io::Result<Film> {
+    let extension = path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    let mut r = BufReader::new(File::open(path)?);
+    match extension.as_str() {
+        "exr" => read_exr(&mut r),
+        "hdr" => read_hdr(&mut r),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                format!("unsupported image format '{}'", extension))),
+    }
+}
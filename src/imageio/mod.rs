@@ -0,0 +1,12 @@
+pub mod exr;
+pub mod hdr;
+pub mod input;
+pub mod output;
+pub mod pfm;
+pub mod srgb;
+pub mod tonemap;
+
+pub use self::exr::ExrCompression;
+pub use self::input::read_image;
+pub use self::output::{OutputOptions, write_image};
+pub use self::tonemap::ToneMap;
@@ -0,0 +1,93 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::film::Film;
+use crate::imageio::exr::{ExrCompression, write_exr};
+use crate::imageio::hdr::write_hdr;
+use crate::imageio::pfm::write_pfm;
+use crate::imageio::srgb::encode_srgb8;
+use crate::imageio::tonemap::{ToneMap, tone_map};
+
+pub struct OutputOptions {
+    // Exposure adjustment in stops and the tone mapping operator for 8-bit output.
+    pub exposure: f32,
+    pub tone_map: ToneMap,
+    // Whether to dither 8-bit output.
+    pub dither: bool,
+    pub exr_compression: ExrCompression,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        OutputOptions {
+            exposure: 0.0,
+            tone_map: ToneMap::Linear,
+            dither: false,
+            exr_compression: ExrCompression::Zip,
+        }
+    }
+}
+
+// Writes |film| to |path| in the format given by the path's extension. .exr, .hdr and .pfm files
+// keep the linear radiance values, while the formats supported by the image crate, such as .png,
+// get tone mapped 8-bit sRGB.
+pub fn write_image(path: &Path, film: &Film, options: &OutputOptions) -> io::Result<()> {
+    let extension = path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    let write_with = |f: &dyn Fn(&mut BufWriter<File>) -> io::Result<()>| {
+        let mut w = BufWriter::new(File::create(path)?);
+        f(&mut w)?;
+        w.flush()
+    };
+
+    match extension.as_str() {
+        "exr" => write_with(&|w| write_exr(w, film, options.exr_compression)),
+        "hdr" => write_with(&|w| write_hdr(w, film)),
+        "pfm" => write_with(&|w| write_pfm(w, film)),
+        _ => {
+            let format = image::ImageFormat::from_path(path).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput,
+                               format!("unsupported image format '{}'", extension))
+            })?;
+            let mapped = tone_map(film, options.exposure, options.tone_map);
+            let buffer = encode_srgb8(&mapped, options.dither);
+            image::save_buffer_with_format(path, &buffer, film.width as u32, film.height as u32,
+                                           image::ColorType::Rgb8, format)
+                .map_err(|e| match e {
+                    image::ImageError::IoError(e) => e,
+                    e => io::Error::other(e.to_string()),
+                })
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn format_from_extension() {
+        let dir = std::env::temp_dir().join("raytracer_write_image");
+        fs::create_dir_all(&dir).unwrap();
+        let film = Film::new(4, 3);
+        let options = OutputOptions::default();
+
+        write_image(&dir.join("a.PFM"), &film, &options).unwrap();
+        assert!(fs::read(dir.join("a.PFM")).unwrap().starts_with(b"PF\n4 3\n"));
+        write_image(&dir.join("a.hdr"), &film, &options).unwrap();
+        assert!(fs::read(dir.join("a.hdr")).unwrap().starts_with(b"#?RADIANCE\n"));
+        write_image(&dir.join("a.exr"), &film, &options).unwrap();
+        assert!(fs::read(dir.join("a.exr")).unwrap().starts_with(&[0x76, 0x2f, 0x31, 0x01]));
+        write_image(&dir.join("a.png"), &film, &options).unwrap();
+        assert!(fs::read(dir.join("a.png")).unwrap().starts_with(b"\x89PNG"));
+
+        let err = write_image(&dir.join("a.xyz"), &film, &options).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(write_image(&dir.join("missing/a.exr"), &film, &options).is_err());
+    }
+}
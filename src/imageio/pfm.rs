@@ -0,0 +1,49 @@
+use std::io::{self, Write};
+
+use crate::film::Film;
+
+// Writes |film| as a color Portable Float Map. The format stores 32-bit floats for each channel
+// with the rows ordered from bottom to top. A negative scale in the header marks the data as
+// little-endian.
+pub fn write_pfm<W: Write>(w: &mut W, film: &Film) -> io::Result<()> {
+    write!(w, "PF\n{} {}\n-1.0\n", film.width, film.height)?;
+
+    for y in (0..film.height).rev() {
+        for x in 0..film.width {
+            let pixel = film.get(x, y);
+            for &v in &[pixel.r, pixel.g, pixel.b] {
+                w.write_all(&v.to_le_bytes())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rgb::Rgb;
+
+    #[test]
+    fn write() {
+        let mut film = Film::new(2, 2);
+        film.set(1, 0, Rgb::new(1.5, -2.0, 1e6));
+        film.set(0, 1, Rgb::new(0.25, 0.0, 0.0));
+
+        let mut bytes = Vec::new();
+        write_pfm(&mut bytes, &film).unwrap();
+
+        let header = b"PF\n2 2\n-1.0\n";
+        assert_eq!(&bytes[..header.len()], header);
+        assert_eq!(bytes.len(), header.len() + 2 * 2 * 3 * 4);
+
+        let floats = bytes[header.len()..]
+            .chunks(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect::<Vec<_>>();
+        // The bottom row comes first.
+        assert_eq!(floats[0], 0.25);
+        assert_eq!(&floats[9..12], &[1.5, -2.0, 1e6]);
+    }
+}
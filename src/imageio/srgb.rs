@@ -0,0 +1,114 @@
+use crate::film::Film;
+
+// Applies the sRGB transfer function (the OETF) to a linear value in [0, 1].
+pub fn srgb_oetf(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        12.92 * v
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Inverse of srgb_oetf().
+pub fn srgb_eotf(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// Encodes the film as 8-bit sRGB, three bytes per pixel. Values outside [0, 1] are clamped, and
+// the encoded values are rounded to the nearest integer.
+//
+// With |dither|, triangular noise with an amplitude of one quantization step is added before
+// rounding, which trades banding in smooth gradients for fine noise. The noise only depends on
+// the pixel position so that the output is reproducible.
+pub fn encode_srgb8(film: &Film, dither: bool) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(film.width * film.height * 3);
+
+    for y in 0..film.height {
+        for x in 0..film.width {
+            let pixel = film.get(x, y);
+            for (c, &v) in [pixel.r, pixel.g, pixel.b].iter().enumerate() {
+                let v = v.clamp(0.0, 1.0);
+                let mut encoded = 255.0 * srgb_oetf(v);
+                // Leave black and white alone so that they stay exact.
+                if dither && v > 0.0 && v < 1.0 {
+                    let seed = ((y * film.width + x) * 3 + c) as u32;
+                    encoded += hash_to_unit(seed) + hash_to_unit(seed ^ 0x9e37_79b9) - 1.0;
+                }
+                bytes.push(encoded.round().clamp(0.0, 255.0) as u8);
+            }
+        }
+    }
+
+    bytes
+}
+
+// Maps |v| to a pseudorandom value in [0, 1).
+fn hash_to_unit(v: u32) -> f32 {
+    // The finalizer of MurmurHash3.
+    let mut h = v;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+    (h >> 8) as f32 / (1u32 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rgb::Rgb;
+
+    #[test]
+    fn transfer_function() {
+        assert_eq!(srgb_oetf(0.0), 0.0);
+        assert!((srgb_oetf(1.0) - 1.0).abs() < 1e-6);
+        // Middle gray.
+        assert!((srgb_oetf(0.18) - 0.4613).abs() < 1e-4);
+        // The two pieces meet at the threshold.
+        assert!((12.92 * 0.0031308 - (1.055 * 0.0031308_f32.powf(1.0 / 2.4) - 0.055)).abs() < 1e-5);
+
+        for &v in &[0.001, 0.02, 0.5, 0.9] {
+            assert!((srgb_eotf(srgb_oetf(v)) - v).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn encode() {
+        let mut film = Film::new(4, 1);
+        film.set(0, 0, Rgb::new(0.0, 1.0, 2.0));
+        film.set(1, 0, Rgb::new(-1.0, 0.5, 0.18));
+        // Encodes to 127.6 after the transfer function, so truncating would give 127.
+        film.set(2, 0, Rgb::new(srgb_eotf(127.6 / 255.0), 0.0, 0.0));
+
+        let bytes = encode_srgb8(&film, false);
+        assert_eq!(&bytes[0..3], &[0, 255, 255]);
+        assert_eq!(&bytes[3..6], &[0, 188, 118]);
+        assert_eq!(bytes[6], 128);
+    }
+
+    #[test]
+    fn dither() {
+        // A flat area halfway between two codes should dither to a mix of nearby codes whose
+        // average is the original value.
+        let v = srgb_eotf(100.5 / 255.0);
+        let mut film = Film::new(64, 64);
+        for pixel in film.pixels.iter_mut() {
+            *pixel = Rgb::new(v, v, v);
+        }
+
+        let bytes = encode_srgb8(&film, true);
+        assert!(bytes.iter().all(|&b| (99..=102).contains(&b)));
+        assert!(bytes.iter().any(|&b| b != bytes[0]));
+        let mean = bytes.iter().map(|&b| b as f32).sum::<f32>() / bytes.len() as f32;
+        assert!((mean - 100.5).abs() < 0.05);
+
+        // Black and white aren't dithered.
+        let film = Film::from_pixels(2, 1, vec![Rgb::new(0.0, 0.0, 0.0), Rgb::new(1.0, 1.0, 1.0)]);
+        assert_eq!(encode_srgb8(&film, true), vec![0, 0, 0, 255, 255, 255]);
+    }
+}
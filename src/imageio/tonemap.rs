@@ -0,0 +1,155 @@
+use crate::film::Film;
+use crate::rgb::Rgb;
+
+// Operators that compress linear radiance into the [0, 1] range of a display.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ToneMap {
+    // Clips everything above 1.
+    Linear,
+    // Reinhard et al.'s L / (1 + L) curve, applied to the luminance so that hues are kept.
+    // Approaches but never reaches white.
+    Reinhard,
+    // Reinhard's curve extended so that luminance |white| maps to 1 and anything brighter burns
+    // out. Uses the brightest pixel if |white| isn't given.
+    ReinhardExtended { white: Option<f32> },
+    // John Hable's filmic curve from Uncharted 2, applied to each channel.
+    Hable,
+    // Stephen Hill's fit of the ACES reference rendering and output transforms.
+    Aces,
+}
+
+// Returns |film| scaled by 2^|exposure| and then tone mapped by |op|. The result is still in
+// linear light, ready to be encoded for display.
+pub fn tone_map(film: &Film, exposure: f32, op: ToneMap) -> Film {
+    let scale = 2f32.powf(exposure);
+    let exposed = film.pixels.iter().map(|&pixel| scale * pixel);
+
+    let pixels = match op {
+        ToneMap::Linear => exposed.map(Rgb::clamp_to_unit).collect(),
+        ToneMap::Reinhard => exposed.map(|pixel| reinhard(pixel, f32::INFINITY)).collect(),
+        ToneMap::ReinhardExtended { white } => {
+            let white = white.unwrap_or_else(|| {
+                film.pixels.iter().map(|pixel| scale * pixel.luminance()).fold(0.0, f32::max)
+            });
+            exposed.map(|pixel| reinhard(pixel, white)).collect()
+        },
+        ToneMap::Hable => {
+            let white_scale = 1.0 / hable_partial(HABLE_WHITE);
+            exposed.map(|pixel| {
+                let f = |v: f32| hable_partial(HABLE_EXPOSURE_BIAS * v) * white_scale;
+                Rgb::new(f(pixel.r), f(pixel.g), f(pixel.b)).clamp_to_unit()
+            }).collect()
+        },
+        ToneMap::Aces => exposed.map(aces_fitted).collect(),
+    };
+
+    Film::from_pixels(film.width, film.height, pixels)
+}
+
+fn reinhard(pixel: Rgb, white: f32) -> Rgb {
+    let l = pixel.luminance();
+    if l <= 0.0 {
+        return Rgb::new(0.0, 0.0, 0.0);
+    }
+
+    let l_mapped = l * (1.0 + l / (white * white)) / (1.0 + l);
+    (l_mapped / l * pixel).clamp_to_unit()
+}
+
+// Linear values are doubled before applying Hable's curve, and |HABLE_WHITE| is the value that
+// maps to white.
+const HABLE_EXPOSURE_BIAS: f32 = 2.0;
+const HABLE_WHITE: f32 = 11.2;
+
+fn hable_partial(x: f32) -> f32 {
+    const A: f32 = 0.15; // Shoulder strength.
+    const B: f32 = 0.50; // Linear strength.
+    const C: f32 = 0.10; // Linear angle.
+    const D: f32 = 0.20; // Toe strength.
+    const E: f32 = 0.02; // Toe numerator.
+    const F: f32 = 0.30; // Toe denominator.
+    ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+}
+
+// Applies the ACES transforms in the space of the ACES reference rendering transform, which the
+// two matrices convert to and from.
+fn aces_fitted(pixel: Rgb) -> Rgb {
+    const INPUT: [[f32; 3]; 3] = [[0.59719, 0.35458, 0.04823],
+                                  [0.07600, 0.90834, 0.01566],
+                                  [0.02840, 0.13383, 0.83777]];
+    const OUTPUT: [[f32; 3]; 3] = [[1.60475, -0.53108, -0.07367],
+                                   [-0.10208, 1.10813, -0.00605],
+                                   [-0.00327, -0.07276, 1.07602]];
+
+    let mul = |m: &[[f32; 3]; 3], c: Rgb| {
+        Rgb::new(m[0][0] * c.r + m[0][1] * c.g + m[0][2] * c.b,
+                 m[1][0] * c.r + m[1][1] * c.g + m[1][2] * c.b,
+                 m[2][0] * c.r + m[2][1] * c.g + m[2][2] * c.b)
+    };
+    let rrt_and_odt_fit = |v: f32| {
+        (v * (v + 0.0245786) - 0.000090537) / (v * (0.983729 * v + 0.432951) + 0.238081)
+    };
+
+    let c = mul(&INPUT, pixel);
+    let c = Rgb::new(rrt_and_odt_fit(c.r), rrt_and_odt_fit(c.g), rrt_and_odt_fit(c.b));
+    mul(&OUTPUT, c).clamp_to_unit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(v: f32) -> Rgb {
+        Rgb::new(v, v, v)
+    }
+
+    fn map(v: Rgb, exposure: f32, op: ToneMap) -> Rgb {
+        tone_map(&Film::from_pixels(1, 1, vec![v]), exposure, op).pixels[0]
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(map(Rgb::new(0.5, 2.0, -1.0), 0.0, ToneMap::Linear), Rgb::new(0.5, 1.0, 0.0));
+        assert_eq!(map(gray(0.25), 1.0, ToneMap::Linear), gray(0.5));
+
+        // L / (1 + L) on the luminance, with the hue preserved.
+        let r = map(Rgb::new(2.0, 1.0, 0.0), 0.0, ToneMap::Reinhard);
+        let l = Rgb::new(2.0, 1.0, 0.0).luminance();
+        assert!((r.luminance() - l / (1.0 + l)).abs() < 1e-5);
+        assert!((r.r - 2.0 * r.g).abs() < 1e-5);
+
+        let white = ToneMap::ReinhardExtended { white: Some(4.0) };
+        assert!((map(gray(4.0), 0.0, white).r - 1.0).abs() < 1e-5);
+        assert!(map(gray(2.0), 0.0, white).r > map(gray(2.0), 0.0, ToneMap::Reinhard).r);
+
+        for &op in &[ToneMap::Hable, ToneMap::Aces] {
+            assert!(map(gray(0.0), 0.0, op).r < 1e-4);
+            assert!(map(gray(100.0), 0.0, op).r > 0.99);
+            assert!(map(gray(0.18), 0.0, op).r > 0.05 && map(gray(0.18), 0.0, op).r < 0.3);
+        }
+        let white = map(gray(HABLE_WHITE / HABLE_EXPOSURE_BIAS), 0.0, ToneMap::Hable);
+        assert!((white.r - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn monotonic() {
+        let ops = [ToneMap::Reinhard, ToneMap::ReinhardExtended { white: Some(8.0) },
+                   ToneMap::Hable, ToneMap::Aces];
+        for &op in &ops {
+            let mut prev = -1.0;
+            for i in 0..100 {
+                let v = map(gray(i as f32 * 0.1), 0.0, op).g;
+                assert!(v >= prev);
+                prev = v;
+            }
+        }
+    }
+
+    #[test]
+    fn white_point_defaults_to_brightest_pixel() {
+        let film = Film::from_pixels(2, 1, vec![gray(3.0), gray(1.0)]);
+        let mapped = tone_map(&film, 0.0, ToneMap::ReinhardExtended { white: None });
+        assert!((mapped.pixels[0].r - 1.0).abs() < 1e-5);
+        assert!(mapped.pixels[1].r < 1.0);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::bxdfs::Bsdf;
+use crate::geometry::{Point3, Ray, Vec3};
+use crate::lights::{Light, LightSample};
+use crate::rgb::Rgb;
+use crate::sampling::Sampler;
+use crate::world::World;
+
+// Offset applied to the origin of rays leaving a surface, to keep them from hitting the surface
+// they start on.
+pub const RAY_EPSILON: f32 = 0.001;
+
+// Shadow rays towards a point on a light stop this far short of it, in units of the ray's
+// length, so that the light's own surface doesn't count as an occluder.
+const SHADOW_EPSILON: f32 = 0.001;
+
+pub trait Integrator: Send + Sync {
+    // Returns the radiance arriving at the origin of |ray| from its direction. Integrators that
+    // use random sampling draw from |sampler|.
+    fn li(&self, ray: &Ray, world: &World, sampler: &mut dyn Sampler) -> Rgb;
+}
+
+// Returns the origin for a ray leaving |p| in direction |d|, offset to the side of the surface
+// with normal |n| that |d| points to.
+pub fn offset_origin(p: Point3, n: Vec3, d: Vec3) -> Point3 {
+    if Vec3::dot(d, n) > 0.0 {
+        p + RAY_EPSILON * n
+    } else {
+        p - RAY_EPSILON * n
+    }
+}
+
+// Returns whether nothing blocks the light of |s| from reaching |p|, on a surface with normal
+// |n|. Light from infinitely far away is blocked by anything along its direction, however far
+// it is from the rest of the scene.
+pub fn unoccluded(world: &World, p: Point3, n: Vec3, s: &LightSample) -> bool {
+    match s.p {
+        Some(p_light) => {
+            let origin = offset_origin(p, n, p_light - p);
+            let shadow_ray = Ray { p: origin, d: p_light - origin };
+            !world.bvh.intersect_p(&shadow_ray, 1.0 - SHADOW_EPSILON)
+        },
+        None => {
+            let shadow_ray = Ray { p: offset_origin(p, n, s.wi), d: s.wi };
+            !world.bvh.intersect_p(&shadow_ray, f32::MAX)
+        },
+    }
+}
+
+// Estimates the light from |light| that |bsdf| at |p| reflects towards |wo|, averaging
+// |num_samples| samples of the light. Delta lights only need one. If |mis| is set, the samples of
+// area lights are weighted against BSDF sampling with the power heuristic, and the caller is
+// expected to add the other half when its BSDF samples hit an emitter.
+#[allow(clippy::too_many_arguments)]
+pub fn estimate_direct(world: &World, light: &dyn Light, bsdf: &Bsdf, p: Point3, wo: Vec3,
+                       num_samples: u32, mis: bool, sampler: &mut dyn Sampler) -> Rgb {
+    let num_samples = if light.is_delta() { 1 } else { num_samples };
+    let n = bsdf.n();
+
+    let mut l = Rgb::new(0.0, 0.0, 0.0);
+    for _ in 0..num_samples {
+        let s = match light.sample_li(p, sampler.get_2d()) {
+            Some(s) => s,
+            None => continue,
+        };
+
+        let f = bsdf.f(wo, s.wi);
+        if f.is_black() || s.li.is_black() || !unoccluded(world, p, n, &s) {
+            continue;
+        }
+
+        let weight = if mis && !light.is_delta() {
+            power_heuristic(num_samples, s.pdf, 1, bsdf.pdf(wo, s.wi))
+        } else {
+            1.0
+        };
+        l += weight * Vec3::dot(s.wi, n).abs() / s.pdf * (f * s.li);
+    }
+
+    l / num_samples as f32
+}
+
+// Veach's power heuristic with an exponent of 2, for combining |nf| samples with density |f_pdf|
+// and |ng| samples with density |g_pdf|. Returns the weight of the first kind.
+pub fn power_heuristic(nf: u32, f_pdf: f32, ng: u32, g_pdf: f32) -> f32 {
+    let f = nf as f32 * f_pdf;
+    let g = ng as f32 * g_pdf;
+    if f.is_infinite() {
+        return 1.0;
+    }
+    if f == 0.0 && g == 0.0 {
+        return 0.0;
+    }
+    (f * f) / (f * f + g * g)
+}
@@ -0,0 +1,7 @@
+pub mod integrator;
+pub mod path;
+pub mod whitted;
+
+pub use self::integrator::Integrator;
+pub use self::path::PathIntegrator;
+pub use self::whitted::WhittedIntegrator;
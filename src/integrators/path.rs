@@ -0,0 +1,235 @@
+use crate::geometry::{Ray, Vec3};
+use crate::integrators::Integrator;
+use crate::integrators::integrator::{estimate_direct, offset_origin, power_heuristic};
+use crate::rgb::Rgb;
+use crate::sampling::Sampler;
+use crate::world::World;
+
+// Number of bounces before Russian roulette starts terminating paths.
+const MIN_BOUNCES: u32 = 3;
+
+// A unidirectional path tracer. At each vertex, the direct lighting is estimated by sampling the
+// lights (next event estimation), and the path is extended by importance sampling the BSDF. Paths
+// are terminated after |max_depth| bounces, or earlier by Russian roulette.
+//
+// Each area light is sampled |light_samples| times per vertex. Since emitters can also be hit by
+// the BSDF samples, the two strategies are combined with multiple importance sampling.
+pub struct PathIntegrator {
+    pub max_depth: u32,
+    pub light_samples: u32,
+}
+
+impl Integrator for PathIntegrator {
+    fn li(&self, ray: &Ray, world: &World, sampler: &mut dyn Sampler) -> Rgb {
+        let mut l = Rgb::new(0.0, 0.0, 0.0);
+        // The path throughput: the product of the BSDF values and cosines divided by the pdfs.
+        let mut beta = Rgb::new(1.0, 1.0, 1.0);
+        let mut ray = Ray { p: ray.p, d: ray.d };
+        // The pdf of the BSDF sample that the current ray was generated from, or None if the
+        // light it finds can't be sampled any other way: for camera rays and after specular
+        // bounces.
+        let mut bsdf_pdf = None;
+
+        for bounces in 0.. {
+            let si = match world.bvh.intersect(&ray) {
+                Some(si) => si,
+                None => {
+                    // Light from the environment, weighted like the emitters below.
+                    for light in &world.lights {
+                        let le = light.le(&ray);
+                        if le.is_black() {
+                            continue;
+                        }
+                        let weight = match bsdf_pdf {
+                            Some(bsdf_pdf) => {
+                                let light_pdf = light.pdf_li(ray.p, ray.d);
+                                power_heuristic(1, bsdf_pdf, self.light_samples, light_pdf)
+                            },
+                            None => 1.0,
+                        };
+                        l += weight * (beta * le);
+                    }
+                    break;
+                },
+            };
+
+            let obj = si.obj.unwrap();
+            let le = obj.material.emission;
+            if !le.is_black() {
+                let weight = match bsdf_pdf {
+                    Some(bsdf_pdf) => {
+                        let light_pdf = obj.shape.pdf_from(ray.p, ray.d);
+                        power_heuristic(1, bsdf_pdf, self.light_samples, light_pdf)
+                    },
+                    None => 1.0,
+                };
+                l += weight * (beta * le);
+            }
+
+            if bounces >= self.max_depth {
+                break;
+            }
+
+            let (p, wo) = (si.p, si.wo);
+
+            // The BSDFs handle directions on both sides of the surface, so the normal is left as
+            // the shape returns it.
+            let n = si.shading.n;
+
+            let bsdf = obj.material.bsdf(si.shading_frame());
+
+            // Next event estimation.
+            if !bsdf.is_specular() {
+                for light in &world.lights {
+                    l += beta * estimate_direct(world, light.as_ref(), &bsdf, p, wo,
+                                                self.light_samples, true, sampler);
+                }
+            }
+
+            let sample = match bsdf.sample_f(wo, sampler.get_2d()) {
+                Some(s) => s,
+                None => break,
+            };
+            if sample.f.is_black() {
+                break;
+            }
+
+            beta *= Vec3::dot(sample.wi, n).abs() / sample.pdf * sample.f;
+            // The true normal tells which side of the surface the new ray really leaves from.
+            ray = Ray { p: offset_origin(p, si.n, sample.wi), d: sample.wi };
+            bsdf_pdf = if sample.specular { None } else { Some(sample.pdf) };
+
+            // Randomly terminate paths that carry little energy, boosting the survivors to keep
+            // the estimate unbiased.
+            if bounces >= MIN_BOUNCES {
+                let q = (1.0 - beta.max_component()).max(0.05);
+                if sampler.get_1d() < q {
+                    break;
+                }
+                beta = beta / (1.0 - q);
+            }
+        }
+
+        l
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+    use std::sync::Arc;
+    use crate::geometry::Point3;
+    use crate::integrators::WhittedIntegrator;
+    use crate::lights::{DistantLight, Light, PointLight, SunLight};
+    use crate::material::Material;
+    use crate::object::Object;
+    use crate::sampling::IndependentSampler;
+    use crate::shapes::{Plane, Sphere, Triangle};
+
+    // A diffuse sphere lit by a single point light directly in front of it. The path tracer
+    // should converge to the same value as the Whitted integrator, since there's nothing else in
+    // the scene for light to bounce off. The light's intensity cancels out the falloff over the
+    // distance of 4 and the 1 / pi of the Lambertian BRDF, so the result is the albedo.
+    #[test]
+    fn matches_whitted_direct_lighting() {
+        let material = Material { diffuse: Rgb::new(0.5, 0.5, 0.5), ..Material::default() };
+        let objs = vec![Object {
+            shape: Arc::new(Sphere { c: Point3::new(0.0, 0.0, 5.0), r: 1.0 }),
+            material,
+        }];
+        let intensity = 16.0 * PI;
+        let light = PointLight {
+            p: Point3::zeroes(),
+            intensity: Rgb::new(intensity, intensity, intensity),
+        };
+        let world = World::new(objs, vec![Box::new(light)]);
+
+        let ray = Ray { p: Point3::zeroes(), d: Vec3::new(0.0, 0.0, 1.0) };
+        let integrator = PathIntegrator { max_depth: 5, light_samples: 1 };
+
+        let mut sampler = IndependentSampler::new(1, 0);
+        let l = integrator.li(&ray, &world, &mut sampler);
+        assert!((l.r - 0.5).abs() < 1e-4);
+        let whitted = WhittedIntegrator { max_depth: 1, light_samples: 1 };
+        assert!((whitted.li(&ray, &world, &mut sampler).r - 0.5).abs() < 1e-4);
+
+        let ray = Ray { p: Point3::zeroes(), d: Vec3::new(0.0, 1.0, 0.0) };
+        assert!(integrator.li(&ray, &world, &mut sampler).is_black());
+    }
+
+    // A diffuse triangle lit by a spherical area light whose center is along its normal. The
+    // irradiance is pi L sin^2(theta), where theta is the half-angle of the cone that the sphere
+    // subtends, so the reflected radiance is the albedo times L sin^2(theta).
+    #[test]
+    fn area_light_matches_analytic() {
+        let objs = vec![
+            Object {
+                shape: Arc::new(Triangle {
+                    p0: Point3::new(-10.0, -10.0, 0.0),
+                    p1: Point3::new(10.0, -10.0, 0.0),
+                    p2: Point3::new(0.0, 10.0, 0.0),
+                }),
+                material: Material { diffuse: Rgb::new(0.5, 0.5, 0.5), ..Material::default() },
+            },
+            Object {
+                shape: Arc::new(Sphere { c: Point3::new(0.0, 0.0, -5.0), r: 2.0 }),
+                material: Material { emission: Rgb::new(1.0, 1.0, 1.0), ..Material::default() },
+            },
+        ];
+        let world = World::new(objs, Vec::new());
+        assert_eq!(world.lights.len(), 1);
+
+        // Looking at the light gives its radiance.
+        let mut sampler = IndependentSampler::new(1, 0);
+        let ray = Ray { p: Point3::new(0.0, 0.0, -10.0), d: Vec3::new(0.0, 0.0, 1.0) };
+        let path = PathIntegrator { max_depth: 1, light_samples: 1 };
+        assert_eq!(path.li(&ray, &world, &mut sampler), Rgb::new(1.0, 1.0, 1.0));
+
+        let expected = 0.5 * (2.0 * 2.0) / (5.0 * 5.0);
+        let ray = Ray { p: Point3::new(0.0, 0.0, -1.0), d: Vec3::new(0.0, 0.0, 1.0) };
+        let whitted = WhittedIntegrator { max_depth: 1, light_samples: 16 };
+        for integrator in &[&path as &dyn Integrator, &whitted] {
+            let n = 4000;
+            let mut sum = 0.0;
+            for _ in 0..n {
+                sum += integrator.li(&ray, &world, &mut sampler).g;
+            }
+            assert!((sum / n as f32 - expected).abs() < 0.03 * expected);
+        }
+    }
+
+    // Planes are left out of the scene's bounds, so points on them can be far away from
+    // everything else. A sphere high above the origin must still shadow a point on the ground
+    // far to one side from light arriving past it, while a point on the other side stays lit.
+    #[test]
+    fn infinite_light_shadows_beyond_bounds() {
+        let wi = Vec3::normalize(Vec3::new(-100.0, 20.0, 0.0));
+        let lights: Vec<Box<dyn Light>> = vec![
+            Box::new(DistantLight::new(-wi, Rgb::new(1.0, 1.0, 1.0))),
+            Box::new(SunLight::new(wi, Rgb::new(1e4, 1e4, 1e4), 0.004)),
+        ];
+        for light in lights {
+            let objs = vec![
+                Object {
+                    shape: Arc::new(Plane::new(Point3::zeroes(), Vec3::new(0.0, 1.0, 0.0))),
+                    material: Material {
+                        diffuse: Rgb::new(0.5, 0.5, 0.5),
+                        ..Material::default()
+                    },
+                },
+                Object {
+                    shape: Arc::new(Sphere { c: Point3::new(0.0, 20.0, 0.0), r: 1.0 }),
+                    material: Material::default(),
+                },
+            ];
+            let world = World::new(objs, vec![light]);
+
+            let integrator = PathIntegrator { max_depth: 1, light_samples: 4 };
+            let mut sampler = IndependentSampler::new(1, 0);
+            let down = |x: f32| Ray { p: Point3::new(x, 1.0, 0.0), d: Vec3::new(0.0, -1.0, 0.0) };
+            assert!(integrator.li(&down(100.0), &world, &mut sampler).is_black());
+            assert!(!integrator.li(&down(-100.0), &world, &mut sampler).is_black());
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::bxdfs::bxdf::{reflect, refract};
+use crate::bxdfs::fresnel::fr_dielectric;
+use crate::geometry::{Frame, Ray, Vec3};
+use crate::integrators::Integrator;
+use crate::integrators::integrator::{estimate_direct, offset_origin};
+use crate::interaction::SurfaceInteraction;
+use crate::material::{Material, MaterialKind, REFLECTANCE};
+use crate::rgb::Rgb;
+use crate::sampling::Sampler;
+use crate::world::World;
+
+// A Whitted-style ray tracer: an ambient term, direct lighting with shadows, and mirror
+// reflections and refractions up to |max_depth| bounces. Surfaces are shaded with the BSDF of
+// their material, so rough reflections only show up as highlights from the lights. Area lights
+// cast soft shadows, which are estimated with |light_samples| random samples per light; that's
+// the only randomness.
+pub struct WhittedIntegrator {
+    pub max_depth: u32,
+    pub light_samples: u32,
+}
+
+impl WhittedIntegrator {
+    fn li_depth(&self, ray: &Ray, world: &World, depth: u32, sampler: &mut dyn Sampler) -> Rgb {
+        let si = match world.bvh.intersect(ray) {
+            Some(si) => si,
+            None => {
+                let mut l = Rgb::new(0.0, 0.0, 0.0);
+                for light in &world.lights {
+                    l += light.le(ray);
+                }
+                return l;
+            },
+        };
+
+        // Emitters are visible from both sides.
+        let obj = si.obj.unwrap();
+        let le = obj.material.emission;
+
+        match obj.material.kind {
+            MaterialKind::Dielectric { ior, .. } => {
+                le + self.li_dielectric(world, depth, &si, ior, sampler)
+            },
+            _ => le + self.li_bsdf(world, depth, &si, &obj.material, sampler),
+        }
+    }
+
+    // Shades the point |si| using the BSDF of |material|, lit by every light in the world, and
+    // traces a mirror reflection if the material has one. Phong materials also get their
+    // ambient term.
+    fn li_bsdf(&self, world: &World, depth: u32, si: &SurfaceInteraction, material: &Material,
+               sampler: &mut dyn Sampler) -> Rgb {
+        let (p, wo) = (si.p, si.wo);
+        let n = if Vec3::dot(wo, si.shading.n) < 0.0 { -si.shading.n } else { si.shading.n };
+        let bsdf = material.bsdf(Frame::from_normal_tangent(n, si.shading.dpdu));
+        let mut l = Rgb::new(0.0, 0.0, 0.0);
+
+        if material.kind == MaterialKind::Phong {
+            l += material.ambient;
+        }
+
+        if !bsdf.is_specular() {
+            for light in &world.lights {
+                l += estimate_direct(world, light.as_ref(), &bsdf, p, wo, self.light_samples,
+                                     false, sampler);
+            }
+        }
+
+        if depth >= self.max_depth {
+            return l;
+        }
+
+        if material.kind == MaterialKind::Phong {
+            if material.reflect {
+                let d = reflect(wo, n);
+                let reflect_ray = Ray { p: offset_origin(p, si.n, d), d };
+                l += REFLECTANCE * self.li_depth(&reflect_ray, world, depth + 1, sampler);
+            }
+        } else if bsdf.is_specular() {
+            if let Some(s) = bsdf.sample_f(wo, (0.5, 0.5)) {
+                let reflect_ray = Ray { p: offset_origin(p, si.n, s.wi), d: s.wi };
+                let weight = Vec3::dot(s.wi, n).abs() / s.pdf * s.f;
+                l += weight * self.li_depth(&reflect_ray, world, depth + 1, sampler);
+            }
+        }
+
+        l
+    }
+
+    // Traces the reflected and refracted rays at a point |si| on a dielectric with index of
+    // refraction |ior|, weighted by the Fresnel reflectance. Dielectrics don't get any Phong
+    // shading; all their light comes through the two rays.
+    fn li_dielectric(&self, world: &World, depth: u32, si: &SurfaceInteraction, ior: f32,
+                     sampler: &mut dyn Sampler) -> Rgb {
+        if depth >= self.max_depth {
+            return Rgb::new(0.0, 0.0, 0.0);
+        }
+
+        // Work with the normal on the side that the ray arrives from. The ray is entering the
+        // material if that's the front of the surface.
+        let (p, wo) = (si.p, si.wo);
+        let n = if Vec3::dot(wo, si.shading.n) < 0.0 { -si.shading.n } else { si.shading.n };
+        let (eta_i, eta_t) = if si.front_face { (1.0, ior) } else { (ior, 1.0) };
+
+        let fr = fr_dielectric(Vec3::dot(wo, n), eta_i, eta_t);
+        let mut l = Rgb::new(0.0, 0.0, 0.0);
+
+        let d = reflect(wo, n);
+        let reflect_ray = Ray { p: offset_origin(p, si.n, d), d };
+        l += fr * self.li_depth(&reflect_ray, world, depth + 1, sampler);
+
+        if let Some(d) = refract(wo, n, eta_i / eta_t) {
+            let refract_ray = Ray { p: offset_origin(p, si.n, d), d };
+            l += (1.0 - fr) * self.li_depth(&refract_ray, world, depth + 1, sampler);
+        }
+
+        l
+    }
+}
+
+impl Integrator for WhittedIntegrator {
+    fn li(&self, ray: &Ray, world: &World, sampler: &mut dyn Sampler) -> Rgb {
+        self.li_depth(ray, world, 0, sampler)
+    }
+}
@@ -0,0 +1,190 @@
+use crate::geometry::{Frame, Point3, Ray, Vec3};
+use crate::object::Object;
+
+// The local geometry used for shading, which can differ from the true geometry of the surface,
+// for example where a mesh interpolates its vertex normals.
+#[derive(Debug, Copy, Clone)]
+pub struct Shading {
+    pub n: Vec3,
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    pub dndu: Vec3,
+    pub dndv: Vec3,
+}
+
+// The local geometry at the point where a ray hits a shape.
+#[derive(Copy, Clone)]
+pub struct SurfaceInteraction<'a> {
+    // The distance along the ray in units of its direction's length.
+    pub t: f32,
+    pub p: Point3,
+    // The normalized normal of the surface itself. Closed shapes point it outwards, and flat
+    // two-sided ones towards the ray.
+    pub n: Vec3,
+    // Whether the ray hit the front of the surface. That's the outside of closed shapes, and
+    // for flat ones the side that their own normal points to, such as the side that a
+    // triangle's vertices wind counterclockwise around, unless their shading normals point to
+    // the other one. Dielectrics rely on it to tell entering and exiting apart.
+    pub front_face: bool,
+    pub uv: (f32, f32),
+    // The partial derivatives of the position and the normal with respect to u and v.
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    pub dndu: Vec3,
+    pub dndv: Vec3,
+    // The normalized direction back along the ray.
+    pub wo: Vec3,
+    pub shading: Shading,
+    // The object that was hit, which the Bvh fills in. Shapes leave it as None.
+    pub obj: Option<&'a Object>,
+}
+
+impl<'a> SurfaceInteraction<'a> {
+    // The hit at |t| along |ray|, with the shading geometry the same as the true geometry. |n|
+    // points to the front of the surface.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(ray: &Ray, t: f32, n: Vec3, uv: (f32, f32), dpdu: Vec3, dpdv: Vec3, dndu: Vec3,
+               dndv: Vec3) -> Self {
+        SurfaceInteraction {
+            t,
+            p: ray.p + t * ray.d,
+            n,
+            front_face: Vec3::dot(ray.d, n) < 0.0,
+            uv,
+            dpdu,
+            dpdv,
+            dndu,
+            dndv,
+            wo: -Vec3::normalize(ray.d),
+            shading: Shading { n, dpdu, dpdv, dndu, dndv },
+            obj: None,
+        }
+    }
+
+    // Turns the normal towards the ray, for flat shapes that are two-sided. |front_face| still
+    // tells which side was hit.
+    pub fn face_ray(&mut self) {
+        if !self.front_face {
+            self.n = -self.n;
+            self.shading.n = -self.shading.n;
+        }
+    }
+
+    // Replaces the shading geometry. The orientation of |n| is taken to be the one the surface
+    // was meant to have, so the true normal is flipped to the same side, which becomes the
+    // front.
+    pub fn set_shading_geometry(&mut self, n: Vec3, dpdu: Vec3, dpdv: Vec3, dndu: Vec3,
+                                dndv: Vec3) {
+        self.shading = Shading { n, dpdu, dpdv, dndu, dndv };
+        if Vec3::dot(self.n, n) < 0.0 {
+            self.n = -self.n;
+        }
+        self.front_face = Vec3::dot(self.n, self.wo) > 0.0;
+    }
+
+    // The shading coordinate system, with the tangent along dpdu where there is one so that
+    // anisotropic BSDFs line up with the surface's parameterization. The normal points to the
+    // front of the surface, so BSDFs can tell which side |wo| is on.
+    pub fn shading_frame(&self) -> Frame {
+        // The normal faces the ray on flat shapes, which is the back if the ray hit that side.
+        let front = (Vec3::dot(self.n, self.wo) > 0.0) == self.front_face;
+        let n = if front { self.shading.n } else { -self.shading.n };
+        Frame::from_normal_tangent(n, self.shading.dpdu)
+    }
+}
+
+// Returns the derivatives of the unit normal |n| with respect to u and v from the derivatives
+// of the position, using the Weingarten equations. |n| can point to either side of the surface.
+pub fn normal_derivatives(n: Vec3, dpdu: Vec3, dpdv: Vec3, d2pduu: Vec3, d2pduv: Vec3,
+                          d2pdvv: Vec3) -> (Vec3, Vec3) {
+    // The coefficients of the first and second fundamental forms.
+    let (e1, f1, g1) = (Vec3::dot(dpdu, dpdu), Vec3::dot(dpdu, dpdv), Vec3::dot(dpdv, dpdv));
+    let (e2, f2, g2) = (Vec3::dot(n, d2pduu), Vec3::dot(n, d2pduv), Vec3::dot(n, d2pdvv));
+
+    let denom = e1 * g1 - f1 * f1;
+    if denom == 0.0 {
+        return (Vec3::zeroes(), Vec3::zeroes());
+    }
+    let inv = 1.0 / denom;
+    let dndu = ((f2 * f1 - e2 * g1) * inv) * dpdu + ((e2 * f1 - f2 * e1) * inv) * dpdv;
+    let dndv = ((g2 * f1 - f2 * g1) * inv) * dpdu + ((f2 * f1 - g2 * e1) * inv) * dpdv;
+    (dndu, dndv)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::shapes::Shape;
+
+    // Checks the derivatives that |shape| returns for the hit of |ray| against nearby hits.
+    // Moving the hit point by a small step along dpdu should land on the surface, change u by
+    // the same step, and move the shading normal by the step along dndu, and likewise for v.
+    pub fn check_derivatives(shape: &dyn Shape, ray: &Ray) {
+        let si = shape.intersect(ray).unwrap();
+        let eps = 1e-3;
+
+        let steps = [(si.dpdu, si.shading.dndu, (eps, 0.0)),
+                     (si.dpdv, si.shading.dndv, (0.0, eps))];
+        for &(dp, dn, duv) in &steps {
+            let target = si.p + eps * dp;
+            let next = shape.intersect(&Ray { p: ray.p, d: target - ray.p }).unwrap();
+            let scale = dp.len().max(1.0);
+            assert!((next.p - target).len() < 1e-4 * scale, "{:?} {:?}", next.p, target);
+            assert!((next.uv.0 - si.uv.0 - duv.0).abs() < 1e-4, "{:?} {:?}", next.uv, si.uv);
+            assert!((next.uv.1 - si.uv.1 - duv.1).abs() < 1e-4, "{:?} {:?}", next.uv, si.uv);
+            let n = si.shading.n + eps * dn;
+            assert!((next.shading.n - n).len() < 1e-4 * scale, "{:?} {:?}", next.shading.n, n);
+        }
+    }
+
+    #[test]
+    fn weingarten() {
+        // A cylinder of radius 2 around z, parameterized by the angle and the height.
+        let (phi, r) = (0.3f32, 2.0);
+        let n = Vec3::new(phi.cos(), phi.sin(), 0.0);
+        let dpdu = Vec3::new(-r * phi.sin(), r * phi.cos(), 0.0);
+        let dpdv = Vec3::new(0.0, 0.0, 1.0);
+        let d2pduu = Vec3::new(-r * phi.cos(), -r * phi.sin(), 0.0);
+        let (dndu, dndv) = normal_derivatives(n, dpdu, dpdv, d2pduu, Vec3::zeroes(),
+                                              Vec3::zeroes());
+        assert!((dndu - dpdu / r).len() < 1e-6);
+        assert!(dndv.len() < 1e-6);
+
+        // Flipping the normal flips its derivatives.
+        let (dndu, _) = normal_derivatives(-n, dpdu, dpdv, d2pduu, Vec3::zeroes(),
+                                           Vec3::zeroes());
+        assert!((dndu + dpdu / r).len() < 1e-6);
+    }
+
+    #[test]
+    fn shading_geometry() {
+        let ray = Ray { p: Point3::new(0.0, 0.0, 1.0), d: Vec3::new(0.0, 0.0, -2.0) };
+        let (x, y) = (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let mut si = SurfaceInteraction::new(&ray, 0.5, Vec3::new(0.0, 0.0, 1.0), (0.0, 0.0), y,
+                                             -x, Vec3::zeroes(), Vec3::zeroes());
+        assert_eq!(si.p, Point3::zeroes());
+        assert_eq!(si.wo, Vec3::new(0.0, 0.0, 1.0));
+        assert!(si.front_face);
+
+        // The frame's tangent follows dpdu.
+        let frame = si.shading_frame();
+        assert!((frame.s - y).len() < 1e-6 && (frame.t + x).len() < 1e-6);
+
+        // Flat shapes turn the normal towards the ray, and the frame back to the front.
+        let mut back = SurfaceInteraction::new(&ray, 0.5, Vec3::new(0.0, 0.0, -1.0), (0.0, 0.0),
+                                               y, -x, Vec3::zeroes(), Vec3::zeroes());
+        assert!(!back.front_face);
+        back.face_ray();
+        assert_eq!((back.n, back.shading.n), (si.n, si.n));
+        assert_eq!(back.shading_frame().n, Vec3::new(0.0, 0.0, -1.0));
+
+        // A shading normal on the other side flips the true normal, and the ray now hits the
+        // back.
+        let n = Vec3::normalize(Vec3::new(0.1, 0.0, -1.0));
+        si.set_shading_geometry(n, x, y, Vec3::zeroes(), Vec3::zeroes());
+        assert_eq!(si.n, Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(si.shading.n, n);
+        assert!(!si.front_face);
+        assert_eq!(si.shading_frame().n, n);
+    }
+}
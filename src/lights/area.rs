@@ -0,0 +1,90 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::geometry::{Point3, Vec3};
+use crate::lights::{Light, LightSample};
+use crate::rgb::Rgb;
+use crate::shapes::Shape;
+
+// A shape that emits |radiance| uniformly in every direction from both sides of its surface.
+pub struct AreaLight {
+    pub shape: Arc<dyn Shape>,
+    pub radiance: Rgb,
+}
+
+impl Light for AreaLight {
+    fn sample_li(&self, p: Point3, u: (f32, f32)) -> Option<LightSample> {
+        let s = self.shape.sample_from(p, u)?;
+        if s.pdf <= 0.0 || !s.pdf.is_finite() {
+            return None;
+        }
+
+        Some(LightSample { p: Some(s.p), wi: Vec3::normalize(s.p - p), li: self.radiance,
+                           pdf: s.pdf })
+    }
+
+    // Both sides emit into a hemisphere.
+    fn power(&self) -> Rgb {
+        2.0 * PI * self.shape.area() * self.radiance
+    }
+
+    fn is_delta(&self) -> bool {
+        false
+    }
+
+    fn pdf_li(&self, p: Point3, wi: Vec3) -> f32 {
+        self.shape.pdf_from(p, wi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::Rng;
+    use crate::shapes::{Sphere, Triangle};
+
+    // Estimates the solid angle that |light| subtends from |p| by averaging 1 / pdf, which is
+    // only correct if every sample is visible from |p|.
+    fn solid_angle(light: &AreaLight, p: Point3) -> f32 {
+        let mut rng = Rng::new(0);
+        let n = 100_000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            if let Some(s) = light.sample_li(p, rng.uniform_2d()) {
+                sum += 1.0 / s.pdf;
+
+                // The pdf of the sampled direction must match the one that's queried.
+                assert!((light.pdf_li(p, s.wi) - s.pdf).abs() <= 1e-2 * s.pdf);
+            }
+        }
+        sum / n as f32
+    }
+
+    #[test]
+    fn sphere_solid_angle() {
+        let light = AreaLight {
+            shape: Arc::new(Sphere { c: Point3::new(0.0, 0.0, 4.0), r: 2.0 }),
+            radiance: Rgb::new(1.0, 1.0, 1.0),
+        };
+
+        // The cone of directions subtended by a sphere at distance 4 with radius 2.
+        let expected = 2.0 * PI * (1.0 - (1.0f32 - 0.25).sqrt());
+        assert!((solid_angle(&light, Point3::zeroes()) - expected).abs() < 1e-3);
+    }
+
+    #[test]
+    fn triangle_solid_angle() {
+        // One face of the cube [-1, 1]^3 split in two subtends 1/6 of the sphere from its center.
+        let triangle = AreaLight {
+            shape: Arc::new(Triangle {
+                p0: Point3::new(-1.0, -1.0, 1.0),
+                p1: Point3::new(1.0, -1.0, 1.0),
+                p2: Point3::new(1.0, 1.0, 1.0),
+            }),
+            radiance: Rgb::new(1.0, 1.0, 1.0),
+        };
+
+        let expected = 4.0 * PI / 12.0;
+        assert!((solid_angle(&triangle, Point3::zeroes()) - expected).abs() < 0.02 * expected);
+    }
+}
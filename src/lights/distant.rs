@@ -0,0 +1,38 @@
+use std::f32::consts::PI;
+
+use crate::geometry::{Bounds3, Point3, Vec3};
+use crate::lights::{Light, LightSample};
+use crate::rgb::Rgb;
+
+// A light infinitely far away, such as the sun, whose light arrives everywhere from the same
+// direction with the same |irradiance|. |dir| is the normalized direction the light travels in.
+pub struct DistantLight {
+    pub dir: Vec3,
+    pub irradiance: Rgb,
+    // The radius of the scene's bounding sphere, set by preprocess().
+    scene_radius: f32,
+}
+
+impl DistantLight {
+    pub fn new(dir: Vec3, irradiance: Rgb) -> Self {
+        DistantLight { dir, irradiance, scene_radius: 0.0 }
+    }
+}
+
+impl Light for DistantLight {
+    fn sample_li(&self, _p: Point3, _u: (f32, f32)) -> Option<LightSample> {
+        Some(LightSample { p: None, wi: -self.dir, li: self.irradiance, pdf: 1.0 })
+    }
+
+    // The power that falls on the disk that the scene's bounding sphere projects to.
+    fn power(&self) -> Rgb {
+        PI * self.scene_radius * self.scene_radius * self.irradiance
+    }
+
+    fn preprocess(&mut self, scene_bounds: Bounds3) {
+        if scene_bounds.is_empty() {
+            return;
+        }
+        self.scene_radius = 0.5 * scene_bounds.diagonal().len();
+    }
+}
@@ -0,0 +1,207 @@
+use std::f32::consts::PI;
+
+use crate::film::Film;
+use crate::geometry::{Bounds3, Point3, Ray, Transform, Vec3};
+use crate::lights::{Light, LightSample};
+use crate::rgb::Rgb;
+use crate::sampling::distribution::Distribution2D;
+
+// Light arriving from infinitely far away in every direction, given by an environment map in the
+// equirectangular (latitude-longitude) layout. In the light's own space, the top row of the map
+// is straight up along +y, and the horizontal position is the angle around y, starting from +x
+// and turning towards +z.
+//
+// Directions are importance sampled in proportion to the brightness of the map.
+pub struct EnvironmentLight {
+    map: Film,
+    // Multiplies the radiance of the map.
+    scale: Rgb,
+    light_to_world: Transform,
+    world_to_light: Transform,
+    distribution: Distribution2D,
+    // The radius of the scene's bounding sphere, set by preprocess().
+    scene_radius: f32,
+}
+
+impl EnvironmentLight {
+    // |light_to_world| must be a rotation, with |world_to_light| its inverse.
+    pub fn new(map: Film, scale: Rgb, light_to_world: Transform, world_to_light: Transform)
+               -> Self {
+        // Weight each pixel by sin(theta) to account for the rows near the poles covering less
+        // of the sphere.
+        let mut func = Vec::with_capacity(map.width * map.height);
+        for y in 0..map.height {
+            let sin_theta = (PI * (y as f32 + 0.5) / map.height as f32).sin();
+            for x in 0..map.width {
+                func.push(map.get(x, y).luminance().max(0.0) * sin_theta);
+            }
+        }
+        let distribution = Distribution2D::new(&func, map.width, map.height);
+
+        EnvironmentLight {
+            map,
+            scale,
+            light_to_world,
+            world_to_light,
+            distribution,
+            scene_radius: 0.0,
+        }
+    }
+
+    // Returns the radiance arriving from direction |w| in light space, which is normalized.
+    fn lookup(&self, w: Vec3) -> Rgb {
+        let (u, v) = direction_to_uv(w);
+        let x = ((u * self.map.width as f32) as usize).min(self.map.width - 1);
+        let y = ((v * self.map.height as f32) as usize).min(self.map.height - 1);
+        self.scale * self.map.get(x, y)
+    }
+}
+
+// Maps the normalized direction |w| to coordinates in [0, 1]^2 on the map.
+fn direction_to_uv(w: Vec3) -> (f32, f32) {
+    let theta = w.y.clamp(-1.0, 1.0).acos();
+    let mut phi = w.z.atan2(w.x);
+    if phi < 0.0 {
+        phi += 2.0 * PI;
+    }
+    (phi / (2.0 * PI), theta / PI)
+}
+
+// Inverse of direction_to_uv(). Also returns sin(theta).
+fn uv_to_direction(u: f32, v: f32) -> (Vec3, f32) {
+    let (theta, phi) = (v * PI, u * 2.0 * PI);
+    let sin_theta = theta.sin();
+    (Vec3::new(sin_theta * phi.cos(), theta.cos(), sin_theta * phi.sin()), sin_theta)
+}
+
+impl Light for EnvironmentLight {
+    fn sample_li(&self, _p: Point3, u: (f32, f32)) -> Option<LightSample> {
+        let ((u, v), map_pdf) = self.distribution.sample_continuous(u);
+        if map_pdf == 0.0 {
+            return None;
+        }
+
+        let (w, sin_theta) = uv_to_direction(u, v);
+        if sin_theta == 0.0 {
+            return None;
+        }
+
+        // The map covers 2pi x pi radians, and a solid angle dw is sin(theta) dtheta dphi.
+        let pdf = map_pdf / (2.0 * PI * PI * sin_theta);
+        let wi = Vec3::normalize(self.light_to_world.apply_vec(&w));
+        Some(LightSample { p: None, wi, li: self.lookup(w), pdf })
+    }
+
+    // The power that falls on the disk that the scene's bounding sphere projects to, using the
+    // average radiance of the map over the sphere of directions. Like the sampling distribution,
+    // each row is weighted by sin(theta).
+    fn power(&self) -> Rgb {
+        let mut sum = Rgb::new(0.0, 0.0, 0.0);
+        let mut total_weight = 0.0;
+        for y in 0..self.map.height {
+            let sin_theta = (PI * (y as f32 + 0.5) / self.map.height as f32).sin();
+            for x in 0..self.map.width {
+                sum += sin_theta * self.map.get(x, y);
+            }
+            total_weight += sin_theta * self.map.width as f32;
+        }
+        let average = sum / total_weight;
+        PI * self.scene_radius * self.scene_radius * (self.scale * average)
+    }
+
+    fn is_delta(&self) -> bool {
+        false
+    }
+
+    fn pdf_li(&self, _p: Point3, wi: Vec3) -> f32 {
+        let w = Vec3::normalize(self.world_to_light.apply_vec(&wi));
+        let (u, v) = direction_to_uv(w);
+        let sin_theta = (v * PI).sin();
+        if sin_theta == 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf((u, v)) / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn le(&self, ray: &Ray) -> Rgb {
+        self.lookup(Vec3::normalize(self.world_to_light.apply_vec(&ray.d)))
+    }
+
+    fn preprocess(&mut self, scene_bounds: Bounds3) {
+        if !scene_bounds.is_empty() {
+            self.scene_radius = 0.5 * scene_bounds.diagonal().len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::Rng;
+
+    // A map that's black except for a bright band, rotated by 90 degrees around x.
+    fn light() -> EnvironmentLight {
+        let mut map = Film::new(16, 8);
+        for x in 0..16 {
+            map.set(x, 2, Rgb::new(10.0, 10.0, 10.0));
+            map.set(x, 6, Rgb::new(0.5, 0.5, 0.5));
+        }
+        let axis = Vec3::new(1.0, 0.0, 0.0);
+        EnvironmentLight::new(map, Rgb::new(2.0, 1.0, 1.0), Transform::rotate(90.0, axis),
+                              Transform::rotate(-90.0, axis))
+    }
+
+    #[test]
+    fn uv_mapping() {
+        for &(u, v) in &[(0.1, 0.2), (0.5, 0.5), (0.9, 0.7)] {
+            let (w, _) = uv_to_direction(u, v);
+            let (u2, v2) = direction_to_uv(w);
+            assert!((u - u2).abs() < 1e-5 && (v - v2).abs() < 1e-5);
+        }
+        assert_eq!(direction_to_uv(Vec3::new(0.0, 1.0, 0.0)).1, 0.0);
+    }
+
+    #[test]
+    fn sampling() {
+        let light = light();
+        let mut rng = Rng::new(0);
+
+        let n = 20000;
+        let mut estimate = Rgb::new(0.0, 0.0, 0.0);
+        for _ in 0..n {
+            let s = match light.sample_li(Point3::zeroes(), rng.uniform_2d()) {
+                Some(s) => s,
+                None => continue,
+            };
+            // Samples only come from the bright rows, and agree with le() and pdf_li().
+            assert!(!s.li.is_black());
+            assert_eq!(s.li, light.le(&Ray { p: Point3::zeroes(), d: s.wi }));
+            assert!((light.pdf_li(Point3::zeroes(), s.wi) - s.pdf).abs() < 1e-3 * s.pdf);
+            estimate += s.li / s.pdf;
+        }
+        estimate = estimate / n as f32;
+
+        // The integral of the radiance over the sphere: each row covers 2pi times the
+        // difference in cos(theta) over its extent.
+        let row = |y: f32| {
+            2.0 * PI * ((PI * y / 8.0).cos() - (PI * (y + 1.0) / 8.0).cos())
+        };
+        let expected = 20.0 * row(2.0) + 1.0 * row(6.0);
+        assert!((estimate.r - expected).abs() < 0.01 * expected);
+    }
+
+    #[test]
+    fn power() {
+        let mut light = light();
+        light.preprocess(Bounds3::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0)));
+
+        // The average radiance over the sphere, on the disk of the bounding sphere's radius
+        // sqrt(3). Each row counts in proportion to the fraction of the sphere it covers, which
+        // is more than a plain average of the pixels gives the bright row near the equator.
+        let row = |y: f32| ((PI * y / 8.0).cos() - (PI * (y + 1.0) / 8.0).cos()) / 2.0;
+        let expected = 3.0 * PI * (20.0 * row(2.0) + 1.0 * row(6.0));
+        let power = light.power();
+        assert!((power.r - expected).abs() < 0.02 * expected, "{} {}", power.r, expected);
+        assert!((power.g - 0.5 * expected).abs() < 0.02 * expected);
+    }
+}
@@ -0,0 +1,44 @@
+use crate::geometry::{Bounds3, Point3, Ray, Vec3};
+use crate::rgb::Rgb;
+
+// Light arriving at a point from a sampled point on a light.
+pub struct LightSample {
+    // The point on the light, which shadow rays are traced towards, or None for lights infinitely
+    // far away. Shadow rays towards those go on along |wi| without end.
+    pub p: Option<Point3>,
+    // The normalized direction from the receiving point towards |p|.
+    pub wi: Vec3,
+    pub li: Rgb,
+    // The density of |wi| with respect to solid angle. Lights described by a delta distribution
+    // return 1.
+    pub pdf: f32,
+}
+
+// Lights are shared between the render threads.
+pub trait Light: Send + Sync {
+    // Samples the light arriving at |p| using |u|. Doesn't check whether it's occluded.
+    fn sample_li(&self, p: Point3, u: (f32, f32)) -> Option<LightSample>;
+
+    // Returns the total power that the light emits.
+    fn power(&self) -> Rgb;
+
+    // Whether the light only emits from a single point or in a single direction. These can't be
+    // hit by rays, and a single sample of them is exact.
+    fn is_delta(&self) -> bool {
+        true
+    }
+
+    // Returns the density with which sample_li() samples direction |wi| from |p|, which is
+    // always 0 for delta lights.
+    fn pdf_li(&self, _p: Point3, _wi: Vec3) -> f32 {
+        0.0
+    }
+
+    // Returns the radiance that the light contributes to |ray| if it escapes the scene.
+    fn le(&self, _ray: &Ray) -> Rgb {
+        Rgb::new(0.0, 0.0, 0.0)
+    }
+
+    // Called with the bounds of the scene once they're known.
+    fn preprocess(&mut self, _scene_bounds: Bounds3) {}
+}
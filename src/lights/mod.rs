@@ -0,0 +1,17 @@
+pub mod area;
+pub mod distant;
+pub mod environment;
+pub mod light;
+pub mod point;
+pub mod sky;
+pub mod spot;
+pub mod sun;
+
+pub use self::area::AreaLight;
+pub use self::distant::DistantLight;
+pub use self::environment::EnvironmentLight;
+pub use self::light::{Light, LightSample};
+pub use self::point::PointLight;
+pub use self::sky::Sky;
+pub use self::spot::SpotLight;
+pub use self::sun::SunLight;
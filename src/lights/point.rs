@@ -0,0 +1,46 @@
+use std::f32::consts::PI;
+
+use crate::geometry::{Point3, Vec3};
+use crate::lights::{Light, LightSample};
+use crate::rgb::Rgb;
+
+// A light that emits |intensity| equally in all directions from the point |p|.
+pub struct PointLight {
+    pub p: Point3,
+    pub intensity: Rgb,
+}
+
+impl Light for PointLight {
+    fn sample_li(&self, p: Point3, _u: (f32, f32)) -> Option<LightSample> {
+        let d = self.p - p;
+        let dist_sq = d.len_sq();
+        if dist_sq == 0.0 {
+            return None;
+        }
+
+        Some(LightSample { p: Some(self.p), wi: Vec3::normalize(d), li: self.intensity / dist_sq,
+                           pdf: 1.0 })
+    }
+
+    fn power(&self) -> Rgb {
+        4.0 * PI * self.intensity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inverse_square_falloff() {
+        let light = PointLight {
+            p: Point3::new(0.0, 2.0, 0.0),
+            intensity: Rgb::new(8.0, 4.0, 0.0),
+        };
+        let s = light.sample_li(Point3::zeroes(), (0.5, 0.5)).unwrap();
+        assert_eq!(s.wi, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(s.li, Rgb::new(2.0, 1.0, 0.0));
+        assert_eq!((s.p, s.pdf), (Some(light.p), 1.0));
+        assert!(light.sample_li(light.p, (0.5, 0.5)).is_none());
+    }
+}
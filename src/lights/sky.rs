@@ -0,0 +1,208 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use crate::film::Film;
+use crate::geometry::Vec3;
+use crate::rgb::Rgb;
+
+// Preetham et al.'s analytic model of a clear daylight sky ("A Practical Analytic Model for
+// Daylight", 1999), given the direction of the sun and the turbidity of the atmosphere: 2 is a
+// very clear sky and 10 a hazy one. The model is only valid for turbidities in between and with
+// the sun above the horizon.
+//
+// Radiance is in kcd/m^2. +y is up, and the sky below the horizon is black.
+pub struct Sky {
+    // The normalized direction towards the sun.
+    sun_dir: Vec3,
+    turbidity: f32,
+    // The Perez distribution coefficients A to E for the luminance Y and the chromaticities x
+    // and y.
+    perez_y: [f32; 5],
+    perez_cx: [f32; 5],
+    perez_cy: [f32; 5],
+    // The luminance and chromaticities at the zenith.
+    zenith: (f32, f32, f32),
+}
+
+impl Sky {
+    pub fn new(sun_dir: Vec3, turbidity: f32) -> Self {
+        let sun_dir = Vec3::normalize(sun_dir);
+        let t = turbidity;
+        let theta_s = sun_dir.y.clamp(0.0, 1.0).acos();
+
+        let perez_y = [0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251,
+                       0.1206 * t - 2.5771, -0.0670 * t + 0.3703];
+        let perez_cx = [-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125,
+                        -0.0641 * t - 0.8989, -0.0033 * t + 0.0452];
+        let perez_cy = [-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102,
+                        -0.0441 * t - 1.6537, -0.0109 * t + 0.0529];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_y = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let (th, th2, th3) = (theta_s, theta_s * theta_s, theta_s * theta_s * theta_s);
+        let t2 = t * t;
+        let zenith_x =
+            t2 * (0.00166 * th3 - 0.00375 * th2 + 0.00209 * th) +
+            t * (-0.02903 * th3 + 0.06377 * th2 - 0.03202 * th + 0.00394) +
+            (0.11693 * th3 - 0.21196 * th2 + 0.06052 * th + 0.25886);
+        let zenith_cy =
+            t2 * (0.00275 * th3 - 0.00610 * th2 + 0.00317 * th) +
+            t * (-0.04214 * th3 + 0.08970 * th2 - 0.04153 * th + 0.00516) +
+            (0.15346 * th3 - 0.26756 * th2 + 0.06670 * th + 0.26688);
+
+        Sky {
+            sun_dir,
+            turbidity,
+            perez_y,
+            perez_cx,
+            perez_cy,
+            zenith: (zenith_y, zenith_x, zenith_cy),
+        }
+    }
+
+    pub fn sun_dir(&self) -> Vec3 {
+        self.sun_dir
+    }
+
+    // Returns the radiance of the sky arriving from the normalized direction |w|.
+    pub fn radiance(&self, w: Vec3) -> Rgb {
+        if w.y <= 0.0 {
+            return Rgb::new(0.0, 0.0, 0.0);
+        }
+
+        let cos_theta = w.y;
+        let cos_gamma = Vec3::dot(w, self.sun_dir).clamp(-1.0, 1.0);
+        let theta_s = self.sun_dir.y.clamp(0.0, 1.0).acos();
+
+        // The Perez formula, relative to its value at the zenith.
+        let perez = |c: &[f32; 5], zenith: f32| {
+            let f = |cos_theta: f32, gamma: f32, cos_gamma: f32| {
+                (1.0 + c[0] * (c[1] / cos_theta.max(1e-3)).exp()) *
+                (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * cos_gamma * cos_gamma)
+            };
+            zenith * f(cos_theta, cos_gamma.acos(), cos_gamma) /
+                f(1.0, theta_s, self.sun_dir.y.clamp(0.0, 1.0))
+        };
+
+        let lum = perez(&self.perez_y, self.zenith.0);
+        let x = perez(&self.perez_cx, self.zenith.1);
+        let y = perez(&self.perez_cy, self.zenith.2);
+        xyy_to_rgb(x, y, lum)
+    }
+
+    // Returns the radiance of the sun's disk, which is the light outside the atmosphere
+    // attenuated by scattering off molecules (Rayleigh) and aerosols (Mie), as in the appendix
+    // of Preetham et al. Absorption by ozone and water vapour is ignored.
+    pub fn sun_radiance(&self) -> Rgb {
+        let theta_s = self.sun_dir.y.clamp(0.0, 1.0).acos();
+
+        // The relative optical mass of the air that the sunlight passes through.
+        let m = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+        let beta = 0.04608 * self.turbidity - 0.04586;
+
+        // Roughly the wavelengths of the red, green and blue primaries, in micrometers.
+        let transmittance = |lambda: f32| {
+            let rayleigh = (-0.008735 * lambda.powf(-4.08) * m).exp();
+            let aerosol = (-beta * lambda.powf(-1.3) * m).exp();
+            rayleigh * aerosol
+        };
+
+        SUN_RADIANCE * Rgb::new(transmittance(0.65), transmittance(0.55), transmittance(0.45))
+    }
+
+    // Tabulates the sky as an equirectangular environment map in the layout of
+    // EnvironmentLight.
+    pub fn to_environment_map(&self, width: usize, height: usize) -> Film {
+        let mut map = Film::new(width, height);
+        for y in 0..height {
+            let theta = PI * (y as f32 + 0.5) / height as f32;
+            for x in 0..width {
+                let phi = 2.0 * PI * (x as f32 + 0.5) / width as f32;
+                let w = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+                map.set(x, y, self.radiance(w));
+            }
+        }
+        map
+    }
+}
+
+// The luminance of the sun outside the atmosphere, in kcd/m^2.
+const SUN_RADIANCE: f32 = 2.0e6;
+
+// The angular radius of the sun's disk, in radians.
+pub const SUN_ANGULAR_RADIUS: f32 = 0.00465;
+
+// Converts CIE xyY to linear sRGB.
+fn xyy_to_rgb(x: f32, y: f32, lum: f32) -> Rgb {
+    if y <= 0.0 {
+        return Rgb::new(0.0, 0.0, 0.0);
+    }
+    let cx = x / y * lum;
+    let cz = (1.0 - x - y) / y * lum;
+    Rgb::new(3.2406 * cx - 1.5372 * lum - 0.4986 * cz,
+             -0.9689 * cx + 1.8758 * lum + 0.0415 * cz,
+             0.0557 * cx - 0.2040 * lum + 1.0570 * cz)
+}
+
+// Returns the direction towards the sun as seen from |latitude| and |longitude| in degrees (north
+// and east are positive), on |day| of the year (1 to 365) at |time| hours of local standard
+// time, in the time zone |timezone| hours ahead of UTC. Uses the formulas from the appendix of
+// Preetham et al. +x points east, +y up and +z north.
+pub fn sun_direction(latitude: f32, longitude: f32, timezone: f32, day: f32, time: f32) -> Vec3 {
+    let lat = latitude.to_radians();
+    let standard_meridian = (15.0 * timezone).to_radians();
+
+    let solar_time = time + 0.170 * (4.0 * PI * (day - 80.0) / 373.0).sin() -
+                     0.129 * (2.0 * PI * (day - 8.0) / 355.0).sin() +
+                     12.0 * (longitude.to_radians() - standard_meridian) / PI;
+    let declination = 0.4093 * (2.0 * PI * (day - 81.0) / 368.0).sin();
+    let hour_angle = PI * solar_time / 12.0;
+
+    let theta = FRAC_PI_2 - (lat.sin() * declination.sin() -
+                             lat.cos() * declination.cos() * hour_angle.cos()).asin();
+    // The azimuth is measured from south towards west.
+    let phi = (-declination.cos() * hour_angle.sin())
+        .atan2(lat.cos() * declination.sin() - lat.sin() * declination.cos() * hour_angle.cos());
+
+    Vec3::new(-theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sky() {
+        let sky = Sky::new(Vec3::new(0.0, 1.0, 1.0), 3.0);
+
+        // A clear sky is blue, brighter towards the sun, and black below the horizon.
+        let zenith = sky.radiance(Vec3::new(0.0, 1.0, 0.0));
+        assert!(zenith.b > zenith.r && zenith.luminance() > 1.0);
+        let near_sun = sky.radiance(Vec3::normalize(Vec3::new(0.0, 1.0, 1.1)));
+        let away = sky.radiance(Vec3::normalize(Vec3::new(0.0, 1.0, -1.1)));
+        assert!(near_sun.luminance() > away.luminance());
+        assert!(sky.radiance(Vec3::new(0.0, -0.1, 1.0)).is_black());
+
+        // The sun gets redder and dimmer as it sets.
+        let noon = Sky::new(Vec3::new(0.0, 1.0, 0.0), 3.0).sun_radiance();
+        let sunset = Sky::new(Vec3::new(0.0, 0.05, 1.0), 3.0).sun_radiance();
+        assert!(sunset.g < noon.g);
+        assert!(sunset.r / sunset.b > noon.r / noon.b);
+    }
+
+    #[test]
+    fn sun_position() {
+        // At noon on the June solstice, on the Tropic of Cancer on the standard meridian, the sun
+        // is almost straight up.
+        let d = sun_direction(23.44, 0.0, 0.0, 172.0, 12.0);
+        assert!(d.y > 0.99);
+
+        // In the northern hemisphere, it rises in the east and is in the south at noon.
+        let morning = sun_direction(45.0, 0.0, 0.0, 80.0, 8.0);
+        assert!(morning.x > 0.5 && morning.y > 0.0);
+        let noon = sun_direction(45.0, 0.0, 0.0, 80.0, 12.0);
+        assert!(noon.z < -0.5 && noon.x.abs() < 0.1);
+        assert!((noon.y - 45f32.to_radians().cos()).abs() < 0.03);
+        assert!(sun_direction(45.0, 0.0, 0.0, 80.0, 0.0).y < 0.0);
+    }
+}
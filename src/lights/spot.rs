@@ -0,0 +1,101 @@
+use std::f32::consts::PI;
+
+use crate::geometry::{Point3, Vec3};
+use crate::lights::{Light, LightSample};
+use crate::rgb::Rgb;
+
+// A point light at |p| that only emits in a cone around the normalized direction |dir|. The
+// intensity is |intensity| up to the angle with cosine |cos_falloff_start|, then falls off
+// smoothly to 0 at the edge of the cone, where the cosine is |cos_total_width|.
+pub struct SpotLight {
+    pub p: Point3,
+    pub dir: Vec3,
+    pub intensity: Rgb,
+    pub cos_total_width: f32,
+    pub cos_falloff_start: f32,
+}
+
+impl SpotLight {
+    // Returns the fraction of the intensity that's emitted in direction |w| from the light.
+    fn falloff(&self, w: Vec3) -> f32 {
+        let cos_theta = Vec3::dot(w, self.dir);
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+        if cos_theta <= self.cos_total_width {
+            return 0.0;
+        }
+
+        let t = (cos_theta - self.cos_total_width) /
+                (self.cos_falloff_start - self.cos_total_width);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample_li(&self, p: Point3, _u: (f32, f32)) -> Option<LightSample> {
+        let d = self.p - p;
+        let dist_sq = d.len_sq();
+        if dist_sq == 0.0 {
+            return None;
+        }
+
+        let wi = Vec3::normalize(d);
+        let falloff = self.falloff(-wi);
+        if falloff == 0.0 {
+            return None;
+        }
+        Some(LightSample { p: Some(self.p), wi, li: falloff / dist_sq * self.intensity,
+                           pdf: 1.0 })
+    }
+
+    fn power(&self) -> Rgb {
+        // The full intensity inside the inner cone, and on average half of it in the falloff
+        // region.
+        let inner = 1.0 - self.cos_falloff_start;
+        let falloff = 0.5 * (self.cos_falloff_start - self.cos_total_width);
+        2.0 * PI * (inner + falloff) * self.intensity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cone() {
+        let light = SpotLight {
+            p: Point3::new(0.0, 1.0, 0.0),
+            dir: Vec3::new(0.0, -1.0, 0.0),
+            intensity: Rgb::new(1.0, 1.0, 1.0),
+            cos_total_width: 30f32.to_radians().cos(),
+            cos_falloff_start: 20f32.to_radians().cos(),
+        };
+
+        let li = |x: f32| light.sample_li(Point3::new(x, 0.0, 0.0), (0.5, 0.5)).map(|s| s.li.r);
+        assert_eq!(li(0.0), Some(1.0));
+        // Inside the falloff region, at about 25 degrees.
+        let v = li(0.466).unwrap();
+        assert!(v > 0.0 && v < 1.0 / (1.0 + 0.466 * 0.466));
+        // Outside the cone.
+        assert!(li(1.0).is_none());
+    }
+
+    #[test]
+    fn power() {
+        // A spot light that covers the whole sphere emits as much as a point light.
+        let light = SpotLight {
+            p: Point3::zeroes(),
+            dir: Vec3::new(0.0, 0.0, 1.0),
+            intensity: Rgb::new(1.0, 1.0, 1.0),
+            cos_total_width: -1.0,
+            cos_falloff_start: -1.0,
+        };
+        assert!((light.power().r - 4.0 * PI).abs() < 1e-5);
+
+        // With the falloff region covering the whole cone, on average half the intensity is
+        // emitted.
+        let light = SpotLight { cos_falloff_start: 1.0, ..light };
+        assert!((light.power().r - 2.0 * PI).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::geometry::{Bounds3, Frame, Point3, Ray, Vec3};
+use crate::lights::{Light, LightSample};
+use crate::rgb::Rgb;
+use crate::sampling::warp::{uniform_cone_pdf, uniform_sample_cone};
+
+// A distant disk of uniform |radiance|, such as the sun, that covers the cone of directions
+// within an angle of the normalized direction |dir| towards it. Unlike a DistantLight, it casts
+// soft shadows and can be seen.
+pub struct SunLight {
+    dir: Vec3,
+    radiance: Rgb,
+    cos_theta_max: f32,
+    frame: Frame,
+    // The radius of the scene's bounding sphere, set by preprocess().
+    scene_radius: f32,
+}
+
+impl SunLight {
+    // |angular_radius| is in radians.
+    pub fn new(dir: Vec3, radiance: Rgb, angular_radius: f32) -> Self {
+        let dir = Vec3::normalize(dir);
+        SunLight {
+            dir,
+            radiance,
+            cos_theta_max: angular_radius.cos(),
+            frame: Frame::from_normal(dir),
+            scene_radius: 0.0,
+        }
+    }
+}
+
+impl Light for SunLight {
+    fn sample_li(&self, _p: Point3, u: (f32, f32)) -> Option<LightSample> {
+        let wi = self.frame.local_to_world(uniform_sample_cone(u, self.cos_theta_max));
+        Some(LightSample { p: None, wi, li: self.radiance,
+                           pdf: uniform_cone_pdf(self.cos_theta_max) })
+    }
+
+    // The power that falls on the disk that the scene's bounding sphere projects to.
+    fn power(&self) -> Rgb {
+        let solid_angle = 1.0 / uniform_cone_pdf(self.cos_theta_max);
+        std::f32::consts::PI * self.scene_radius * self.scene_radius * solid_angle * self.radiance
+    }
+
+    fn is_delta(&self) -> bool {
+        false
+    }
+
+    fn pdf_li(&self, _p: Point3, wi: Vec3) -> f32 {
+        if Vec3::dot(Vec3::normalize(wi), self.dir) >= self.cos_theta_max {
+            uniform_cone_pdf(self.cos_theta_max)
+        } else {
+            0.0
+        }
+    }
+
+    fn le(&self, ray: &Ray) -> Rgb {
+        if Vec3::dot(Vec3::normalize(ray.d), self.dir) >= self.cos_theta_max {
+            self.radiance
+        } else {
+            Rgb::new(0.0, 0.0, 0.0)
+        }
+    }
+
+    fn preprocess(&mut self, scene_bounds: Bounds3) {
+        if !scene_bounds.is_empty() {
+            self.scene_radius = 0.5 * scene_bounds.diagonal().len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_matches_le() {
+        let light = SunLight::new(Vec3::new(1.0, 1.0, 0.0), Rgb::new(5.0, 5.0, 5.0), 0.01);
+        let mut rng = crate::sampling::Rng::new(0);
+        for _ in 0..100 {
+            let s = light.sample_li(Point3::zeroes(), rng.uniform_2d()).unwrap();
+            assert_eq!(light.le(&Ray { p: Point3::zeroes(), d: s.wi }), s.li);
+            assert_eq!(light.pdf_li(Point3::zeroes(), s.wi), s.pdf);
+        }
+        assert!(light.le(&Ray { p: Point3::zeroes(), d: Vec3::new(0.0, 1.0, 0.0) }).is_black());
+    }
+}
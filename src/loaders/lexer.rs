@@ -0,0 +1,176 @@
+use crate::loaders::ParseError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    // A bare word - keywords, names and numbers are all words.
+    Word(String),
+    // A double-quoted string, with the quotes removed.
+    Str(String),
+    LBrace,
+    RBrace,
+    Eof,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub line: usize,
+    pub col: usize,
+}
+
+// Splits |src| into tokens. '#' starts a comment that runs to the end of the line.
+pub fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = src.chars().peekable();
+
+    let mut line = 1;
+    let mut col = 1;
+
+    while let Some(&c) = chars.peek() {
+        let (start_line, start_col) = (line, col);
+
+        if c == '\n' {
+            chars.next();
+            line += 1;
+            col = 1;
+            continue;
+        }
+
+        if c.is_whitespace() {
+            chars.next();
+            col += 1;
+            continue;
+        }
+
+        if c == '#' {
+            while let Some(&c) = chars.peek() {
+                if c == '\n' {
+                    break;
+                }
+                chars.next();
+                col += 1;
+            }
+            continue;
+        }
+
+        let kind = match c {
+            '{' => {
+                chars.next();
+                col += 1;
+                TokenKind::LBrace
+            },
+            '}' => {
+                chars.next();
+                col += 1;
+                TokenKind::RBrace
+            },
+            '"' => {
+                chars.next();
+                col += 1;
+
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => {
+                            col += 1;
+                            break;
+                        },
+                        Some('\n') | None => {
+                            return Err(ParseError::new(start_line, start_col,
+                                                       "unterminated string"));
+                        },
+                        Some(c) => {
+                            col += 1;
+                            s.push(c);
+                        },
+                    }
+                }
+                TokenKind::Str(s)
+            },
+            _ => {
+                let mut s = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '{' || c == '}' || c == '"' || c == '#' {
+                        break;
+                    }
+                    chars.next();
+                    col += 1;
+                    s.push(c);
+                }
+                TokenKind::Word(s)
+            },
+        };
+
+        tokens.push(Token { kind, line: start_line, col: start_col });
+    }
+
+    tokens.push(Token { kind: TokenKind::Eof, line, col });
+
+    Ok(tokens)
+}
+
+// Splits a single line of a line-oriented format (such as OBJ or MTL) into whitespace-separated
+// words, dropping any '#' comment. Each word is returned with its 1-based column.
+pub fn split_words(line: &str) -> Vec<(usize, &str)> {
+    let line = match line.find('#') {
+        Some(idx) => &line[..idx],
+        None => line,
+    };
+
+    let mut words = Vec::new();
+    let mut start = None;
+
+    for (idx, c) in line.char_indices().chain(std::iter::once((line.len(), ' '))) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(idx),
+            (Some(s), true) => {
+                words.push((line[..s].chars().count() + 1, &line[s..idx]));
+                start = None;
+            },
+            _ => (),
+        }
+    }
+
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(src: &str) -> Vec<TokenKind> {
+        tokenize(src).unwrap().into_iter().map(|t| t.kind).collect()
+    }
+
+    #[test]
+    fn tokens() {
+        assert_eq!(kinds("sphere { radius 2.5 } # comment\n\"a b.obj\""),
+                   vec![TokenKind::Word("sphere".to_string()),
+                        TokenKind::LBrace,
+                        TokenKind::Word("radius".to_string()),
+                        TokenKind::Word("2.5".to_string()),
+                        TokenKind::RBrace,
+                        TokenKind::Str("a b.obj".to_string()),
+                        TokenKind::Eof]);
+    }
+
+    #[test]
+    fn positions() {
+        let tokens = tokenize("a\n  b{").unwrap();
+        assert_eq!((tokens[0].line, tokens[0].col), (1, 1));
+        assert_eq!((tokens[1].line, tokens[1].col), (2, 3));
+        assert_eq!((tokens[2].line, tokens[2].col), (2, 4));
+    }
+
+    #[test]
+    fn words() {
+        assert_eq!(split_words("  f 1/2/3\t-1 # comment"),
+                   vec![(3, "f"), (5, "1/2/3"), (11, "-1")]);
+        assert!(split_words("# comment only").is_empty());
+    }
+
+    #[test]
+    fn unterminated_string() {
+        assert_eq!(tokenize("x \"abc").unwrap_err(), ParseError::new(1, 3, "unterminated string"));
+    }
+}
@@ -0,0 +1,8 @@
+pub mod error;
+pub mod lexer;
+pub mod mtl;
+pub mod obj;
+pub mod scene_file;
+
+pub use self::error::{LoadError, ParseError};
+pub use self::scene_file::load_scene;
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use crate::loaders::ParseError;
+use crate::loaders::lexer;
+use crate::material::{Material, MaterialKind};
+use crate::rgb::Rgb;
+
+// The defaults from the MTL spec for properties that a material doesn't set.
+pub fn default_material() -> Material {
+    Material {
+        ambient: Rgb::new(0.2, 0.2, 0.2),
+        diffuse: Rgb::new(0.8, 0.8, 0.8),
+        specular: Rgb::new(1.0, 1.0, 1.0),
+        reflect: false,
+        emission: Rgb::new(0.0, 0.0, 0.0),
+        kind: MaterialKind::Phong,
+    }
+}
+
+// Parses a Wavefront MTL file into a map from material name to material. Ka, Kd and Ks map onto
+// the ambient, diffuse and specular colors, Ke onto the emitted radiance, and illumination
+// model 3 (reflection on) turns on reflection. Illumination models 4, 6 and 7 (the refraction
+// models) make the material a dielectric with the index of refraction given by Ni. Other
+// statements are ignored.
+pub fn parse_mtl(src: &str) -> Result<HashMap<String, Material>, ParseError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, Material)> = None;
+    // Ni of the current material. It's tracked separately since it can appear before illum.
+    let mut ior = 1.0;
+
+    for (line_idx, line) in src.lines().enumerate() {
+        let line_num = line_idx + 1;
+        let words = lexer::split_words(line);
+
+        let (col, keyword) = match words.first() {
+            Some(&w) => w,
+            None => continue,
+        };
+
+        if keyword == "newmtl" {
+            if words.len() < 2 {
+                return Err(ParseError::new(line_num, col, "newmtl is missing a name"));
+            }
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+            let name = words[1..].iter().map(|&(_, w)| w).collect::<Vec<_>>().join(" ");
+            current = Some((name, default_material()));
+            ior = 1.0;
+            continue;
+        }
+
+        let material = match (&mut current, keyword) {
+            (Some((_, material)), _) => material,
+            (None, "Ka") | (None, "Kd") | (None, "Ks") | (None, "Ke") | (None, "Ni") |
+            (None, "illum") => {
+                return Err(ParseError::new(line_num, col,
+                                           format!("{} appears before newmtl", keyword)));
+            },
+            (None, _) => continue,
+        };
+
+        match keyword {
+            "Ka" => material.ambient = parse_rgb(&words, line_num)?,
+            "Kd" => material.diffuse = parse_rgb(&words, line_num)?,
+            "Ks" => material.specular = parse_rgb(&words, line_num)?,
+            "Ke" => material.emission = parse_rgb(&words, line_num)?,
+            "illum" => {
+                let (col, word) = *words.get(1).ok_or_else(|| {
+                    ParseError::new(line_num, col, "illum is missing a value")
+                })?;
+                let model = word.parse::<u32>().map_err(|_| {
+                    ParseError::new(line_num, col,
+                                    format!("expected an illumination model, found '{}'", word))
+                })?;
+                material.reflect = model == 3;
+                material.kind = match model {
+                    4 | 6 | 7 => {
+                        MaterialKind::Dielectric { ior, roughness: 0.0, anisotropy: 0.0 }
+                    },
+                    _ => MaterialKind::Phong,
+                };
+            },
+            "Ni" => {
+                let (col, word) = *words.get(1).ok_or_else(|| {
+                    ParseError::new(line_num, col, "Ni is missing a value")
+                })?;
+                ior = word.parse::<f32>().ok().filter(|&v| v > 0.0).ok_or_else(|| {
+                    ParseError::new(line_num, col,
+                                    format!("expected a positive number, found '{}'", word))
+                })?;
+                if let MaterialKind::Dielectric { ior: material_ior, .. } = &mut material.kind {
+                    *material_ior = ior;
+                }
+            },
+            _ => (),
+        }
+    }
+
+    if let Some((name, material)) = current {
+        materials.insert(name, material);
+    }
+
+    Ok(materials)
+}
+
+// Parses the color following the keyword in |words|. A single value is used for all three
+// channels, as allowed by the spec.
+fn parse_rgb(words: &[(usize, &str)], line_num: usize) -> Result<Rgb, ParseError> {
+    let (keyword_col, keyword) = words[0];
+
+    let mut vals = Vec::new();
+    for &(col, word) in &words[1..] {
+        vals.push(word.parse::<f32>().map_err(|_| {
+            ParseError::new(line_num, col, format!("expected a number, found '{}'", word))
+        })?);
+    }
+
+    match vals.len() {
+        1 => Ok(Rgb::new(vals[0], vals[0], vals[0])),
+        3 => Ok(Rgb::new(vals[0], vals[1], vals[2])),
+        _ => Err(ParseError::new(line_num, keyword_col,
+                                 format!("{} expects 1 or 3 values", keyword))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let src = "
+            # A comment.
+            newmtl red
+            Ka 0.1 0 0
+            Kd 0.5 0 0
+            Ns 10
+            newmtl mirror
+            Ks 0.5
+            illum 3
+            newmtl glass
+            illum 7
+            Ni 1.5
+            newmtl lamp
+            Ke 4 4 3
+        ";
+
+        let materials = parse_mtl(src).unwrap();
+        assert_eq!(materials.len(), 4);
+
+        let red = &materials["red"];
+        assert_eq!((red.ambient.r, red.ambient.g), (0.1, 0.0));
+        assert_eq!((red.diffuse.r, red.diffuse.g), (0.5, 0.0));
+        assert_eq!(red.specular.r, 1.0);
+        assert!(!red.reflect);
+        assert!(red.emission.is_black());
+
+        let mirror = &materials["mirror"];
+        assert_eq!(mirror.specular.b, 0.5);
+        assert!(mirror.reflect);
+        assert_eq!(mirror.kind, MaterialKind::Phong);
+
+        assert_eq!(materials["glass"].kind,
+                   MaterialKind::Dielectric { ior: 1.5, roughness: 0.0, anisotropy: 0.0 });
+        assert_eq!(materials["lamp"].emission, Rgb::new(4.0, 4.0, 3.0));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse_mtl("Kd 1 1 1").unwrap_err(),
+                   ParseError::new(1, 1, "Kd appears before newmtl"));
+        assert_eq!(parse_mtl("newmtl a\nKd 1 x 1").unwrap_err(),
+                   ParseError::new(2, 6, "expected a number, found 'x'"));
+        assert_eq!(parse_mtl("newmtl a\nKs 1 1").unwrap_err(),
+                   ParseError::new(2, 1, "Ks expects 1 or 3 values"));
+        assert_eq!(parse_mtl("newmtl a\nNi 0").unwrap_err(),
+                   ParseError::new(2, 4, "expected a positive number, found '0'"));
+    }
+}
@@ -0,0 +1,449 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::geometry::{Point3, Vec3};
+use crate::loaders::{LoadError, ParseError};
+use crate::loaders::lexer;
+use crate::loaders::mtl;
+use crate::material::Material;
+use crate::shapes::TriangleMesh;
+
+// A run of faces from an OBJ file that share the same group and material.
+pub struct ObjGroup {
+    pub name: String,
+    pub material: Material,
+    pub mesh: TriangleMesh,
+}
+
+// The indices of a face vertex's position, texture coordinate and normal.
+type VertexKey = (usize, Option<usize>, Option<usize>);
+
+// An ObjGroup whose mesh is still being built.
+struct PendingGroup {
+    name: String,
+    material: Material,
+    // Maps each distinct face vertex to its index in the mesh.
+    vertices: HashMap<VertexKey, u32>,
+    indices: Vec<u32>,
+    positions: Vec<Point3>,
+    uvs: Vec<Option<(f32, f32)>>,
+    normals: Vec<Option<Vec3>>,
+}
+
+impl PendingGroup {
+    fn new(name: String, material: Material) -> Self {
+        PendingGroup {
+            name,
+            material,
+            vertices: HashMap::new(),
+            indices: Vec::new(),
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+        }
+    }
+
+    // Adds the face vertex |key| to the mesh, reusing the mesh vertex if an earlier face
+    // already used the same combination of indices.
+    fn add_vertex(&mut self, key: VertexKey, positions: &[Point3], tex_coords: &[(f32, f32)],
+                  normals: &[Vec3]) {
+        let next = self.positions.len() as u32;
+        let idx = *self.vertices.entry(key).or_insert(next);
+        if idx == next {
+            let (pos_idx, tex_idx, normal_idx) = key;
+            self.positions.push(positions[pos_idx]);
+            self.uvs.push(tex_idx.map(|i| tex_coords[i]));
+            self.normals.push(normal_idx.map(|i| normals[i]));
+        }
+        self.indices.push(idx);
+    }
+
+    // Texture coordinates and normals are dropped unless every vertex has them. Tangents are
+    // only computed when there are both, since the mesh only uses them for smooth shading.
+    fn finish(self) -> ObjGroup {
+        let uvs = self.uvs.into_iter().collect::<Option<Vec<_>>>().unwrap_or_default();
+        let normals = self.normals.into_iter().collect::<Option<Vec<_>>>().unwrap_or_default();
+        let tangents = if uvs.is_empty() || normals.is_empty() {
+            Vec::new()
+        } else {
+            vertex_tangents(&self.indices, &self.positions, &uvs)
+        };
+        ObjGroup {
+            name: self.name,
+            material: self.material,
+            mesh: TriangleMesh::new(self.indices, self.positions, normals, uvs, tangents),
+        }
+    }
+}
+
+// Averages the directions in which u increases across the triangles around each vertex, so that
+// the shading frames of anisotropic materials turn smoothly from one triangle to the next.
+// Vertices whose triangles all have degenerate texture coordinates are left with a zero tangent,
+// which the mesh ignores.
+fn vertex_tangents(indices: &[u32], positions: &[Point3], uvs: &[(f32, f32)]) -> Vec<Vec3> {
+    let mut tangents = vec![Vec3::zeroes(); positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [i0, i1, i2] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+        let duv02 = (uvs[i0].0 - uvs[i2].0, uvs[i0].1 - uvs[i2].1);
+        let duv12 = (uvs[i1].0 - uvs[i2].0, uvs[i1].1 - uvs[i2].1);
+        let det = duv02.0 * duv12.1 - duv02.1 * duv12.0;
+        if det.abs() < 1e-9 {
+            continue;
+        }
+
+        let dp02 = positions[i0] - positions[i2];
+        let dp12 = positions[i1] - positions[i2];
+        let dpdu = (duv12.1 * dp02 - duv02.1 * dp12) / det;
+        if dpdu.len_sq() > 0.0 {
+            for &i in &[i0, i1, i2] {
+                tangents[i] += Vec3::normalize(dpdu);
+            }
+        }
+    }
+
+    for tangent in &mut tangents {
+        if tangent.len_sq() > 0.0 {
+            *tangent = Vec3::normalize(*tangent);
+        }
+    }
+    tangents
+}
+
+pub fn load_obj(path: &Path) -> Result<Vec<ObjGroup>, LoadError> {
+    let src = fs::read_to_string(path).map_err(|e| LoadError::Io(path.to_path_buf(), e))?;
+    parse_obj(&src, path)
+}
+
+// Parses the contents of the Wavefront OBJ file at |path|. Polygons are triangulated as fans
+// around their first vertex, and degenerate triangles are dropped. Material libraries are
+// resolved relative to the directory containing |path|.
+//
+// Each group becomes a TriangleMesh whose vertices are the distinct combinations of position,
+// texture coordinate and normal that its faces use.
+pub fn parse_obj(src: &str, path: &Path) -> Result<Vec<ObjGroup>, LoadError> {
+    let parse_err = |e: ParseError| LoadError::Parse(path.to_path_buf(), e);
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut positions = Vec::<Point3>::new();
+    let mut tex_coords = Vec::<(f32, f32)>::new();
+    let mut normals = Vec::<Vec3>::new();
+
+    let mut materials = HashMap::<String, Material>::new();
+
+    let mut groups = Vec::<PendingGroup>::new();
+    let mut group_name = "default".to_string();
+    let mut material = mtl::default_material();
+    // Set whenever the group or material changes so that the next face starts a new ObjGroup.
+    let mut start_group = true;
+
+    for (line_idx, line) in src.lines().enumerate() {
+        let line_num = line_idx + 1;
+        let words = lexer::split_words(line);
+
+        let (col, keyword) = match words.first() {
+            Some(&w) => w,
+            None => continue,
+        };
+        let args = &words[1..];
+
+        match keyword {
+            "v" => {
+                let vals = parse_floats(args, 3, 4, keyword, line_num, col).map_err(parse_err)?;
+                let w = if vals.len() == 4 { vals[3] } else { 1.0 };
+                if w == 0.0 {
+                    return Err(parse_err(ParseError::new(line_num, args[3].0,
+                                                         "vertex weight must be non-zero")));
+                }
+                positions.push(Point3::new(vals[0], vals[1], vals[2]) / w);
+            },
+            "vt" => {
+                let vals = parse_floats(args, 1, 3, keyword, line_num, col).map_err(parse_err)?;
+                tex_coords.push((vals[0], vals.get(1).copied().unwrap_or(0.0)));
+            },
+            "vn" => {
+                let vals = parse_floats(args, 3, 3, keyword, line_num, col).map_err(parse_err)?;
+                normals.push(Vec3::new(vals[0], vals[1], vals[2]));
+            },
+            "f" => {
+                if args.len() < 3 {
+                    return Err(parse_err(ParseError::new(line_num, col,
+                                                         "face needs at least 3 vertices")));
+                }
+
+                let mut face = Vec::with_capacity(args.len());
+                for &(col, word) in args {
+                    face.push(parse_face_vertex(word, positions.len(), tex_coords.len(),
+                                                normals.len(), line_num, col)
+                        .map_err(parse_err)?);
+                }
+
+                if start_group {
+                    groups.push(PendingGroup::new(group_name.clone(), material));
+                    start_group = false;
+                }
+                let group = groups.last_mut().unwrap();
+
+                for i in 1..face.len() - 1 {
+                    let triangle = [face[0], face[i], face[i + 1]];
+                    let (p0, p1, p2) = (positions[triangle[0].0], positions[triangle[1].0],
+                                        positions[triangle[2].0]);
+                    if Vec3::cross(p1 - p0, p2 - p0).len_sq() == 0.0 {
+                        continue;
+                    }
+                    for &key in &triangle {
+                        group.add_vertex(key, &positions, &tex_coords, &normals);
+                    }
+                }
+            },
+            "g" | "o" => {
+                group_name = if args.is_empty() {
+                    "default".to_string()
+                } else {
+                    args.iter().map(|&(_, w)| w).collect::<Vec<_>>().join(" ")
+                };
+                start_group = true;
+            },
+            "mtllib" => {
+                if args.is_empty() {
+                    return Err(parse_err(ParseError::new(line_num, col,
+                                                         "mtllib is missing a file name")));
+                }
+                for &(_, name) in args {
+                    let mtl_path: PathBuf = base_dir.join(name);
+                    let mtl_src = fs::read_to_string(&mtl_path)
+                        .map_err(|e| LoadError::Io(mtl_path.clone(), e))?;
+                    let lib = mtl::parse_mtl(&mtl_src)
+                        .map_err(|e| LoadError::Parse(mtl_path.clone(), e))?;
+                    materials.extend(lib);
+                }
+            },
+            "usemtl" => {
+                let name = args.iter().map(|&(_, w)| w).collect::<Vec<_>>().join(" ");
+                material = match materials.get(&name) {
+                    Some(m) => *m,
+                    None => {
+                        return Err(parse_err(ParseError::new(
+                            line_num, col, format!("undefined material '{}'", name))));
+                    },
+                };
+                start_group = true;
+            },
+            // Smoothing groups, curves, lines and points are not supported.
+            _ => (),
+        }
+    }
+
+    Ok(groups.into_iter()
+        .filter(|g| !g.indices.is_empty())
+        .map(PendingGroup::finish)
+        .collect())
+}
+
+fn parse_floats(args: &[(usize, &str)], min: usize, max: usize, keyword: &str, line_num: usize,
+                col: usize) -> Result<Vec<f32>, ParseError> {
+    if args.len() < min || args.len() > max {
+        let expected = if min == max {
+            format!("{}", min)
+        } else {
+            format!("{} to {}", min, max)
+        };
+        return Err(ParseError::new(line_num, col,
+                                   format!("{} expects {} values", keyword, expected)));
+    }
+
+    args.iter()
+        .map(|&(col, word)| {
+            word.parse::<f32>().map_err(|_| {
+                ParseError::new(line_num, col, format!("expected a number, found '{}'", word))
+            })
+        })
+        .collect()
+}
+
+// Resolves a 1-based (or negative, relative to the end) OBJ index into a 0-based index into an
+// array of |count| elements.
+fn resolve_index(word: &str, count: usize, what: &str, line_num: usize,
+                 col: usize) -> Result<usize, ParseError> {
+    let idx = word.parse::<i64>().map_err(|_| {
+        ParseError::new(line_num, col, format!("expected a {} index, found '{}'", what, word))
+    })?;
+
+    let resolved = if idx > 0 {
+        idx - 1
+    } else {
+        count as i64 + idx
+    };
+
+    if idx == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(ParseError::new(line_num, col,
+                                   format!("{} index {} is out of range", what, idx)));
+    }
+
+    Ok(resolved as usize)
+}
+
+// Parses a face vertex of the form v, v/vt, v//vn or v/vt/vn and returns its indices.
+fn parse_face_vertex(word: &str, num_positions: usize, num_tex_coords: usize, num_normals: usize,
+                     line_num: usize, col: usize) -> Result<VertexKey, ParseError> {
+    let parts = word.split('/').collect::<Vec<_>>();
+    if parts.len() > 3 {
+        return Err(ParseError::new(line_num, col, format!("invalid face vertex '{}'", word)));
+    }
+
+    let pos_idx = resolve_index(parts[0], num_positions, "vertex", line_num, col)?;
+    let tex_idx = if parts.len() > 1 && !parts[1].is_empty() {
+        Some(resolve_index(parts[1], num_tex_coords, "texture coordinate", line_num, col)?)
+    } else {
+        None
+    };
+    let normal_idx = if parts.len() > 2 {
+        Some(resolve_index(parts[2], num_normals, "normal", line_num, col)?)
+    } else {
+        None
+    };
+
+    Ok((pos_idx, tex_idx, normal_idx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(src: &str) -> Result<Vec<ObjGroup>, ParseError> {
+        parse_obj(src, Path::new("test.obj")).map_err(|e| match e {
+            LoadError::Parse(_, e) => e,
+            LoadError::Io(_, e) => panic!("unexpected io error: {}", e),
+        })
+    }
+
+    #[test]
+    fn faces() {
+        let src = "
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0
+            vt 0 0
+            vn 0 0 1
+            # A quad split into two triangles, using negative indices.
+            f -4/1/1 -3/1/1 -2/1/1 -1//1
+            g second
+            f 1 2 3
+        ";
+
+        let groups = parse(src).unwrap();
+        assert_eq!(groups.len(), 2);
+
+        assert_eq!(groups[0].name, "default");
+        let mesh = &groups[0].mesh;
+        assert_eq!(mesh.num_triangles(), 2);
+        let p = |tri: usize, i: usize| mesh.positions[mesh.indices[3 * tri + i] as usize];
+        assert_eq!(p(0, 0), Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(p(0, 2), Point3::new(1.0, 1.0, 0.0));
+        assert_eq!(p(1, 1), Point3::new(1.0, 1.0, 0.0));
+        assert_eq!(p(1, 2), Point3::new(0.0, 1.0, 0.0));
+        // The vertices are shared between the triangles, but the last one has no texture
+        // coordinate so the mesh doesn't get any.
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.normals, vec![Vec3::new(0.0, 0.0, 1.0); 4]);
+        assert!(mesh.uvs.is_empty());
+
+        assert_eq!(groups[1].name, "second");
+        assert_eq!(groups[1].mesh.num_triangles(), 1);
+        assert!(groups[1].mesh.normals.is_empty());
+    }
+
+    #[test]
+    fn vertex_attributes() {
+        let src = "
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            vt 0 0
+            vt 1 0
+            vt 0 1
+            vt 0.5
+            vn 0 0 1
+            vn 0 0 -1
+            f 1/1/1 2/2/1 3/3/1
+            # The same positions with a different normal need their own vertices.
+            f 1/1/2 3/3/2 2/4/2
+        ";
+
+        let groups = parse(src).unwrap();
+        let mesh = &groups[0].mesh;
+        assert_eq!(mesh.indices, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(mesh.uvs[1], (1.0, 0.0));
+        assert_eq!(mesh.uvs[5], (0.5, 0.0));
+        assert_eq!(mesh.normals[2], Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(mesh.normals[3], Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn tangents() {
+        let src = "
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0
+            # u runs along y.
+            vt 0 0
+            vt 0 -1
+            vt 1 -1
+            vt 1 0
+            vn 0 0 1
+            f 1/1/1 2/2/1 3/3/1 4/4/1
+            g degenerate
+            f 1/1/1 2/1/1 3/1/1
+            g flat
+            f 1/1 2/2 3/3
+        ";
+
+        let groups = parse(src).unwrap();
+        assert_eq!(groups[0].mesh.tangents, vec![Vec3::new(0.0, 1.0, 0.0); 4]);
+        assert_eq!(groups[1].mesh.tangents, vec![Vec3::zeroes(); 3]);
+        // Flat shading doesn't use tangents.
+        assert!(groups[2].mesh.tangents.is_empty());
+    }
+
+    #[test]
+    fn materials() {
+        let dir = std::env::temp_dir().join("raytracer_obj_materials");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("test.mtl"), "newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\n").unwrap();
+
+        let src = "
+            mtllib test.mtl
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            usemtl red
+            f 1 2 3
+            usemtl blue
+            f 1 2 3
+        ";
+
+        let groups = parse_obj(src, &dir.join("test.obj")).unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].material.diffuse.r, 1.0);
+        assert_eq!(groups[1].material.diffuse.b, 1.0);
+    }
+
+    #[test]
+    fn errors() {
+        let err = |src| match parse(src) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e,
+        };
+
+        assert_eq!(err("v 0 0"), ParseError::new(1, 1, "v expects 3 to 4 values"));
+        assert_eq!(err("v 0 0 0\nf 1 1 2"),
+                   ParseError::new(2, 7, "vertex index 2 is out of range"));
+        assert_eq!(err("v 0 0 0\nf 1 1 0"),
+                   ParseError::new(2, 7, "vertex index 0 is out of range"));
+        assert_eq!(err("v 0 0 0\nf 1/1 1 1"),
+                   ParseError::new(2, 3, "texture coordinate index 1 is out of range"));
+        assert_eq!(err("usemtl foo"), ParseError::new(1, 1, "undefined material 'foo'"));
+    }
+}
@@ -0,0 +1,1647 @@
+// Parser for the text scene description format, which scenes/README.md documents.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::cameras::{Aperture, Camera, PerspectiveCamera, ThinLens};
+use crate::filters::{BoxFilter, Filter, GaussianFilter, LanczosSincFilter, MitchellFilter,
+                     TriangleFilter};
+use crate::geometry::{Bounds3, Point3, Transform, Vec3};
+use crate::imageio::ToneMap;
+use crate::integrators::{PathIntegrator, WhittedIntegrator};
+use crate::imageio;
+use crate::lights::{DistantLight, EnvironmentLight, Light, PointLight, Sky, SpotLight, SunLight};
+use crate::lights::sky::{SUN_ANGULAR_RADIUS, sun_direction};
+use crate::loaders::{LoadError, ParseError};
+use crate::loaders::lexer::{self, Token, TokenKind};
+use crate::loaders::obj;
+use crate::material::{Material, MaterialKind, metal_ior};
+use crate::object::Object;
+use crate::render::AdaptiveSampling;
+use crate::rgb::Rgb;
+use crate::sampling::{HaltonSampler, IndependentSampler, Sampler, SobolSampler,
+                      StratifiedSampler};
+use crate::scene::Scene;
+use crate::shapes::{Cone, Cuboid, Cylinder, Disk, Hyperboloid, Paraboloid, PartialSphere, Plane,
+                    Quad, Quadric, QuadricSurface, Shape, Sphere, Torus, Triangle};
+
+pub fn load_scene(path: &Path) -> Result<Scene, LoadError> {
+    let src = fs::read_to_string(path).map_err(|e| LoadError::Io(path.to_path_buf(), e))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_scene(&src, base_dir).map_err(|e| LoadError::Parse(path.to_path_buf(), e))
+}
+
+// Parses a scene from |src|. Files referenced by the scene are resolved relative to |base_dir|.
+pub fn parse_scene(src: &str, base_dir: &Path) -> Result<Scene, ParseError> {
+    let mut parser = Parser {
+        tokens: lexer::tokenize(src)?,
+        pos: 0,
+        base_dir: base_dir.to_path_buf(),
+        materials: HashMap::new(),
+    };
+    parser.parse_scene()
+}
+
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Word(s) => format!("'{}'", s),
+        TokenKind::Str(s) => format!("string \"{}\"", s),
+        TokenKind::LBrace => "'{'".to_string(),
+        TokenKind::RBrace => "'}'".to_string(),
+        TokenKind::Eof => "end of file".to_string(),
+    }
+}
+
+fn error_at(tok: &Token, msg: impl Into<String>) -> ParseError {
+    ParseError::new(tok.line, tok.col, msg)
+}
+
+struct CameraParams {
+    eye: Point3,
+    target: Point3,
+    up: Vec3,
+    fov: f32,
+    lens_radius: f32,
+    focal_distance: Option<f32>,
+    aperture: Aperture,
+    // The camera statement, for reporting errors. None if the scene doesn't have one.
+    tok: Option<Token>,
+}
+
+impl Default for CameraParams {
+    fn default() -> Self {
+        CameraParams {
+            eye: Point3::zeroes(),
+            target: Point3::new(0.0, 0.0, 1.0),
+            up: Vec3::new(0.0, 1.0, 0.0),
+            fov: 45.0,
+            lens_radius: 0.0,
+            focal_distance: None,
+            aperture: Aperture::Circular,
+            tok: None,
+        }
+    }
+}
+
+struct SamplerParams {
+    kind: String,
+    spp: usize,
+    jitter: bool,
+    seed: u64,
+    adaptive_threshold: Option<f32>,
+    min_samples: usize,
+}
+
+impl Default for SamplerParams {
+    fn default() -> Self {
+        SamplerParams {
+            kind: "independent".to_string(),
+            spp: 1,
+            jitter: true,
+            seed: 0,
+            adaptive_threshold: None,
+            min_samples: 16,
+        }
+    }
+}
+
+impl SamplerParams {
+    fn build(&self) -> Box<dyn Sampler> {
+        match self.kind.as_str() {
+            "stratified" => Box::new(StratifiedSampler::with_samples(self.spp, self.jitter,
+                                                                     self.seed)),
+            "halton" => Box::new(HaltonSampler::new(self.spp, self.seed)),
+            "sobol" => Box::new(SobolSampler::new(self.spp, self.seed)),
+            _ => Box::new(IndependentSampler::new(self.spp, self.seed)),
+        }
+    }
+
+    fn adaptive(&self) -> Option<AdaptiveSampling> {
+        self.adaptive_threshold.map(|threshold| {
+            AdaptiveSampling { min_samples: self.min_samples.min(self.spp), threshold }
+        })
+    }
+}
+
+impl CameraParams {
+    fn build(&self, img_width: usize, img_height: usize) -> Result<Box<dyn Camera>, ParseError> {
+        let dir = self.target - self.eye;
+        let err = |msg| match &self.tok {
+            Some(tok) => error_at(tok, msg),
+            None => ParseError::new(1, 1, msg),
+        };
+
+        if dir.len_sq() == 0.0 {
+            return Err(err("camera eye and target must be different"));
+        }
+        if Vec3::cross(dir, self.up).len_sq() == 0.0 {
+            return Err(err("camera up must not be parallel to the viewing direction"));
+        }
+
+        let lens = if self.lens_radius > 0.0 {
+            Some(ThinLens {
+                radius: self.lens_radius,
+                focal_distance: self.focal_distance.unwrap_or_else(|| dir.len()),
+                aperture: self.aperture,
+            })
+        } else {
+            None
+        };
+
+        let camera_to_world = Transform::look_at(self.eye, self.target, self.up);
+        Ok(Box::new(PerspectiveCamera::new(camera_to_world, self.fov, img_width, img_height,
+                                           lens)))
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    base_dir: PathBuf,
+    materials: HashMap<String, Material>,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn next(&mut self) -> Token {
+        let tok = self.tokens[self.pos].clone();
+        // The last token is always Eof, so never move past it.
+        if self.pos + 1 < self.tokens.len() {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    fn expect_word(&mut self, what: &str) -> Result<(String, Token), ParseError> {
+        let tok = self.next();
+        match &tok.kind {
+            TokenKind::Word(s) => Ok((s.clone(), tok)),
+            kind => Err(error_at(&tok, format!("expected {}, found {}", what, describe(kind)))),
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Result<Token, ParseError> {
+        let tok = self.next();
+        if tok.kind == kind {
+            Ok(tok)
+        } else {
+            Err(error_at(&tok, format!("expected {}, found {}", describe(&kind),
+                                       describe(&tok.kind))))
+        }
+    }
+
+    // Parses a `{ key value... }` block, calling |f| with each key. |f| is responsible for
+    // consuming the values that follow the key.
+    fn parse_block<F>(&mut self, mut f: F) -> Result<(), ParseError>
+        where F: FnMut(&mut Self, &str, &Token) -> Result<(), ParseError> {
+
+        self.expect(TokenKind::LBrace)?;
+
+        loop {
+            match self.peek().kind {
+                TokenKind::RBrace => {
+                    self.next();
+                    return Ok(());
+                },
+                TokenKind::Eof => {
+                    return Err(error_at(self.peek(), "expected '}', found end of file"));
+                },
+                _ => {
+                    let (key, tok) = self.expect_word("a property name")?;
+                    f(self, &key, &tok)?;
+                },
+            }
+        }
+    }
+
+    fn parse_f32(&mut self) -> Result<f32, ParseError> {
+        let (s, tok) = self.expect_word("a number")?;
+        s.parse::<f32>()
+            .map_err(|_| error_at(&tok, format!("expected a number, found '{}'", s)))
+    }
+
+    fn parse_positive_f32(&mut self) -> Result<f32, ParseError> {
+        let tok = self.peek().clone();
+        let val = self.parse_f32()?;
+        if val > 0.0 {
+            Ok(val)
+        } else {
+            Err(error_at(&tok, format!("expected a positive number, found '{}'", val)))
+        }
+    }
+
+    fn parse_unit_f32(&mut self) -> Result<f32, ParseError> {
+        let tok = self.peek().clone();
+        let val = self.parse_f32()?;
+        if (0.0..=1.0).contains(&val) {
+            Ok(val)
+        } else {
+            Err(error_at(&tok, format!("expected a number between 0 and 1, found '{}'", val)))
+        }
+    }
+
+    fn parse_usize(&mut self) -> Result<usize, ParseError> {
+        let (s, tok) = self.expect_word("an integer")?;
+        s.parse::<usize>()
+            .map_err(|_| error_at(&tok, format!("expected a non-negative integer, found '{}'", s)))
+    }
+
+    fn parse_u32(&mut self) -> Result<u32, ParseError> {
+        let (s, tok) = self.expect_word("an integer")?;
+        s.parse::<u32>()
+            .map_err(|_| error_at(&tok, format!("expected a non-negative integer, found '{}'", s)))
+    }
+
+    fn parse_bool(&mut self) -> Result<bool, ParseError> {
+        let (s, tok) = self.expect_word("'true' or 'false'")?;
+        match s.as_str() {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            _ => Err(error_at(&tok, format!("expected 'true' or 'false', found '{}'", s))),
+        }
+    }
+
+    fn parse_point3(&mut self) -> Result<Point3, ParseError> {
+        Ok(Point3::new(self.parse_f32()?, self.parse_f32()?, self.parse_f32()?))
+    }
+
+    fn parse_vec3(&mut self) -> Result<Vec3, ParseError> {
+        Ok(Vec3::new(self.parse_f32()?, self.parse_f32()?, self.parse_f32()?))
+    }
+
+    // Parses a quoted file name, which is relative to the scene file's directory.
+    fn parse_path(&mut self) -> Result<(PathBuf, Token), ParseError> {
+        let file_tok = self.next();
+        match &file_tok.kind {
+            TokenKind::Str(s) => Ok((self.base_dir.join(s), file_tok)),
+            kind => Err(error_at(&file_tok, format!("expected a file name, found {}",
+                                                    describe(kind)))),
+        }
+    }
+
+    fn parse_rgb(&mut self) -> Result<Rgb, ParseError> {
+        Ok(Rgb::new(self.parse_f32()?, self.parse_f32()?, self.parse_f32()?))
+    }
+
+    fn parse_scene(&mut self) -> Result<Scene, ParseError> {
+        let mut scene = Scene {
+            img_width: 800,
+            img_height: 800,
+            exposure: 0.0,
+            tone_map: ToneMap::Linear,
+            dither: false,
+            integrator: Box::new(WhittedIntegrator { max_depth: 1, light_samples: 16 }),
+            sampler: SamplerParams::default().build(),
+            adaptive: None,
+            filter: Box::new(BoxFilter { radius: (0.5, 0.5) }),
+            camera: CameraParams::default().build(800, 800)?,
+            objs: Vec::new(),
+            lights: Vec::new(),
+        };
+
+        let mut camera = CameraParams::default();
+        let mut sampler = SamplerParams::default();
+
+        loop {
+            if self.peek().kind == TokenKind::Eof {
+                break;
+            }
+
+            let (keyword, tok) = self.expect_word("a statement")?;
+            match keyword.as_str() {
+                "image" => self.parse_image(&mut scene)?,
+                "camera" => {
+                    camera.tok = Some(tok.clone());
+                    self.parse_camera(&mut camera)?;
+                },
+                "integrator" => self.parse_integrator(&mut scene, &mut sampler)?,
+                "sampler" => self.parse_sampler(&mut sampler)?,
+                "filter" => scene.filter = self.parse_filter()?,
+                "material" => {
+                    let (name, name_tok) = self.expect_word("a material name")?;
+                    if self.materials.contains_key(&name) {
+                        return Err(error_at(&name_tok,
+                                            format!("material '{}' is already defined", name)));
+                    }
+                    let material = self.parse_material()?;
+                    self.materials.insert(name, material);
+                },
+                "point_light" => scene.lights.push(self.parse_point_light(&tok)?),
+                "spot_light" => scene.lights.push(self.parse_spot_light(&tok)?),
+                "directional_light" => scene.lights.push(self.parse_directional_light()?),
+                "environment_light" => scene.lights.push(self.parse_environment_light(&tok)?),
+                "sky_light" => scene.lights.extend(self.parse_sky_light(&tok)?),
+                "mesh" => self.parse_mesh(&tok, &mut scene)?,
+                "object" => {
+                    let obj = self.parse_object(&tok)?;
+                    scene.objs.push(obj);
+                },
+                _ => return Err(error_at(&tok, format!("unknown statement '{}'", keyword))),
+            }
+        }
+
+        // The camera depends on the image's aspect ratio, so it's built once everything else is
+        // known.
+        scene.camera = camera.build(scene.img_width, scene.img_height)?;
+        scene.sampler = sampler.build();
+        scene.adaptive = sampler.adaptive();
+
+        Ok(scene)
+    }
+
+    fn parse_image(&mut self, scene: &mut Scene) -> Result<(), ParseError> {
+        let mut tone_map = "linear".to_string();
+        let mut white = None;
+
+        self.parse_block(|p, key, key_tok| {
+            let dim = match key {
+                "width" => &mut scene.img_width,
+                "height" => &mut scene.img_height,
+                "exposure" => {
+                    scene.exposure = p.parse_f32()?;
+                    return Ok(());
+                },
+                "tone_map" => {
+                    let (name, name_tok) = p.expect_word("a tone mapping operator")?;
+                    if !["linear", "reinhard", "reinhard_extended", "hable", "aces"]
+                            .contains(&name.as_str()) {
+                        return Err(error_at(&name_tok,
+                                            format!("unknown tone mapping operator '{}'", name)));
+                    }
+                    tone_map = name;
+                    return Ok(());
+                },
+                "white" => {
+                    white = Some(p.parse_positive_f32()?);
+                    return Ok(());
+                },
+                "dither" => {
+                    scene.dither = p.parse_bool()?;
+                    return Ok(());
+                },
+                _ => return Err(unknown_property("image", key, key_tok)),
+            };
+            let val_tok = p.peek().clone();
+            *dim = p.parse_usize()?;
+            if *dim == 0 {
+                return Err(error_at(&val_tok, format!("image {} must be positive", key)));
+            }
+            Ok(())
+        })?;
+
+        scene.tone_map = match tone_map.as_str() {
+            "reinhard" => ToneMap::Reinhard,
+            "reinhard_extended" => ToneMap::ReinhardExtended { white },
+            "hable" => ToneMap::Hable,
+            "aces" => ToneMap::Aces,
+            _ => ToneMap::Linear,
+        };
+
+        Ok(())
+    }
+
+    fn parse_camera(&mut self, camera: &mut CameraParams) -> Result<(), ParseError> {
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "eye" => camera.eye = p.parse_point3()?,
+                "target" => camera.target = p.parse_point3()?,
+                "up" => camera.up = p.parse_vec3()?,
+                "fov" => {
+                    let val_tok = p.peek().clone();
+                    camera.fov = p.parse_f32()?;
+                    if camera.fov <= 0.0 || camera.fov >= 180.0 {
+                        return Err(error_at(&val_tok, "fov must be between 0 and 180 degrees"));
+                    }
+                },
+                "lens_radius" => {
+                    let val_tok = p.peek().clone();
+                    camera.lens_radius = p.parse_f32()?;
+                    if camera.lens_radius < 0.0 {
+                        return Err(error_at(&val_tok, "lens_radius must not be negative"));
+                    }
+                },
+                "focal_distance" => camera.focal_distance = Some(p.parse_positive_f32()?),
+                "aperture_blades" => {
+                    let val_tok = p.peek().clone();
+                    let blades = p.parse_u32()?;
+                    if blades < 3 {
+                        return Err(error_at(&val_tok, "aperture_blades must be at least 3"));
+                    }
+                    camera.aperture = Aperture::Polygonal(blades);
+                },
+                _ => return Err(unknown_property("camera", key, key_tok)),
+            }
+            Ok(())
+        })
+    }
+
+    fn parse_integrator(&mut self, scene: &mut Scene, sampler: &mut SamplerParams)
+                        -> Result<(), ParseError> {
+        let mut kind = "whitted".to_string();
+        let mut max_depth = None;
+        let mut light_samples = None;
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "type" => {
+                    let (name, name_tok) = p.expect_word("an integrator type")?;
+                    if name != "whitted" && name != "path" {
+                        return Err(error_at(&name_tok,
+                                            format!("unknown integrator type '{}'", name)));
+                    }
+                    kind = name;
+                },
+                "max_depth" => max_depth = Some(p.parse_u32()?),
+                "light_samples" => {
+                    let val_tok = p.peek().clone();
+                    let n = p.parse_u32()?;
+                    if n == 0 {
+                        return Err(error_at(&val_tok, "light_samples must be positive"));
+                    }
+                    light_samples = Some(n);
+                },
+                "samples" => {
+                    let val_tok = p.peek().clone();
+                    sampler.spp = p.parse_usize()?;
+                    if sampler.spp == 0 {
+                        return Err(error_at(&val_tok, "samples must be positive"));
+                    }
+                },
+                _ => return Err(unknown_property("integrator", key, key_tok)),
+            }
+            Ok(())
+        })?;
+
+        scene.integrator = match kind.as_str() {
+            "path" => Box::new(PathIntegrator {
+                max_depth: max_depth.unwrap_or(5),
+                light_samples: light_samples.unwrap_or(1),
+            }),
+            _ => Box::new(WhittedIntegrator {
+                max_depth: max_depth.unwrap_or(1),
+                light_samples: light_samples.unwrap_or(16),
+            }),
+        };
+
+        Ok(())
+    }
+
+    fn parse_sampler(&mut self, sampler: &mut SamplerParams) -> Result<(), ParseError> {
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "type" => {
+                    let (name, name_tok) = p.expect_word("a sampler type")?;
+                    if !["independent", "stratified", "halton", "sobol"]
+                            .contains(&name.as_str()) {
+                        return Err(error_at(&name_tok,
+                                            format!("unknown sampler type '{}'", name)));
+                    }
+                    sampler.kind = name;
+                },
+                "jitter" => sampler.jitter = p.parse_bool()?,
+                "seed" => sampler.seed = p.parse_usize()? as u64,
+                "adaptive_threshold" => sampler.adaptive_threshold = Some(p.parse_positive_f32()?),
+                "min_samples" => {
+                    let val_tok = p.peek().clone();
+                    sampler.min_samples = p.parse_usize()?;
+                    if sampler.min_samples == 0 {
+                        return Err(error_at(&val_tok, "min_samples must be positive"));
+                    }
+                },
+                _ => return Err(unknown_property("sampler", key, key_tok)),
+            }
+            Ok(())
+        })
+    }
+
+    fn parse_filter(&mut self) -> Result<Box<dyn Filter>, ParseError> {
+        let mut kind = "box".to_string();
+        let mut radius = None;
+        let mut alpha = 2.0;
+        let (mut b, mut c) = (1.0 / 3.0, 1.0 / 3.0);
+        let mut tau = 3.0;
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "type" => {
+                    let (name, name_tok) = p.expect_word("a filter type")?;
+                    if !["box", "triangle", "gaussian", "mitchell", "lanczos"]
+                            .contains(&name.as_str()) {
+                        return Err(error_at(&name_tok, format!("unknown filter type '{}'", name)));
+                    }
+                    kind = name;
+                },
+                "radius" => radius = Some(p.parse_positive_f32()?),
+                "alpha" => alpha = p.parse_positive_f32()?,
+                "b" => b = p.parse_f32()?,
+                "c" => c = p.parse_f32()?,
+                "tau" => tau = p.parse_positive_f32()?,
+                _ => return Err(unknown_property("filter", key, key_tok)),
+            }
+            Ok(())
+        })?;
+
+        let radius = |default: f32| {
+            let r = radius.unwrap_or(default);
+            (r, r)
+        };
+        Ok(match kind.as_str() {
+            "triangle" => Box::new(TriangleFilter { radius: radius(2.0) }),
+            "gaussian" => Box::new(GaussianFilter::new(radius(1.5), alpha)),
+            "mitchell" => Box::new(MitchellFilter { radius: radius(2.0), b, c }),
+            "lanczos" => Box::new(LanczosSincFilter { radius: radius(4.0), tau }),
+            _ => Box::new(BoxFilter { radius: radius(0.5) }),
+        })
+    }
+
+    fn parse_material(&mut self) -> Result<Material, ParseError> {
+        let mut material = Material::default();
+        let mut kind = "phong".to_string();
+        let mut ior = 1.5;
+        let (mut eta, mut k) = metal_ior("aluminium").unwrap();
+        let mut roughness = 0.0;
+        let mut anisotropy = 0.0;
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "type" => {
+                    let (name, name_tok) = p.expect_word("a material type")?;
+                    if name != "phong" && name != "conductor" && name != "dielectric" {
+                        return Err(error_at(&name_tok,
+                                            format!("unknown material type '{}'", name)));
+                    }
+                    kind = name;
+                },
+                "ambient" => material.ambient = p.parse_rgb()?,
+                "diffuse" => material.diffuse = p.parse_rgb()?,
+                "specular" => material.specular = p.parse_rgb()?,
+                "reflect" => material.reflect = p.parse_bool()?,
+                "emission" => material.emission = p.parse_rgb()?,
+                "metal" => {
+                    let (name, name_tok) = p.expect_word("a metal name")?;
+                    let (metal_eta, metal_k) = metal_ior(&name).ok_or_else(|| {
+                        error_at(&name_tok, format!("unknown metal '{}'", name))
+                    })?;
+                    eta = metal_eta;
+                    k = metal_k;
+                },
+                "eta" => eta = p.parse_rgb()?,
+                "k" => k = p.parse_rgb()?,
+                "ior" => ior = p.parse_positive_f32()?,
+                "roughness" => roughness = p.parse_unit_f32()?,
+                "anisotropy" => anisotropy = p.parse_unit_f32()?,
+                _ => return Err(unknown_property("material", key, key_tok)),
+            }
+            Ok(())
+        })?;
+
+        material.kind = match kind.as_str() {
+            "conductor" => MaterialKind::Conductor { eta, k, roughness, anisotropy },
+            "dielectric" => MaterialKind::Dielectric { ior, roughness, anisotropy },
+            _ => MaterialKind::Phong,
+        };
+
+        Ok(material)
+    }
+
+    // Parses either a reference to a named material or an inline material block.
+    fn parse_material_ref(&mut self) -> Result<Material, ParseError> {
+        if self.peek().kind == TokenKind::LBrace {
+            return self.parse_material();
+        }
+
+        let (name, tok) = self.expect_word("a material name or '{'")?;
+        match self.materials.get(&name) {
+            Some(material) => Ok(*material),
+            None => Err(error_at(&tok, format!("undefined material '{}'", name))),
+        }
+    }
+
+    fn parse_point_light(&mut self, tok: &Token) -> Result<Box<dyn Light>, ParseError> {
+        let mut position = None;
+        let mut intensity = Rgb::new(1.0, 1.0, 1.0);
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "position" => position = Some(p.parse_point3()?),
+                "intensity" => intensity = p.parse_rgb()?,
+                _ => return Err(unknown_property("point_light", key, key_tok)),
+            }
+            Ok(())
+        })?;
+
+        let p = position.ok_or_else(|| error_at(tok, "point_light is missing 'position'"))?;
+        Ok(Box::new(PointLight { p, intensity }))
+    }
+
+    fn parse_spot_light(&mut self, tok: &Token) -> Result<Box<dyn Light>, ParseError> {
+        let mut position = None;
+        let mut target = None;
+        let mut intensity = Rgb::new(1.0, 1.0, 1.0);
+        let mut cone_angle = 30.0;
+        let mut cone_delta = 5.0;
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "position" => position = Some(p.parse_point3()?),
+                "target" => target = Some(p.parse_point3()?),
+                "intensity" => intensity = p.parse_rgb()?,
+                "cone_angle" | "cone_delta" => {
+                    let val_tok = p.peek().clone();
+                    let angle = p.parse_f32()?;
+                    if !(0.0..=180.0).contains(&angle) {
+                        return Err(error_at(&val_tok,
+                                            format!("{} must be between 0 and 180 degrees", key)));
+                    }
+                    if key == "cone_angle" {
+                        cone_angle = angle;
+                    } else {
+                        cone_delta = angle;
+                    }
+                },
+                _ => return Err(unknown_property("spot_light", key, key_tok)),
+            }
+            Ok(())
+        })?;
+
+        let missing = |prop: &str| error_at(tok, format!("spot_light is missing '{}'", prop));
+        let p = position.ok_or_else(|| missing("position"))?;
+        let target = target.ok_or_else(|| missing("target"))?;
+        if target == p {
+            return Err(error_at(tok, "spot_light position and target must be different"));
+        }
+
+        let cone_delta: f32 = cone_delta.min(cone_angle);
+        Ok(Box::new(SpotLight {
+            p,
+            dir: Vec3::normalize(target - p),
+            intensity,
+            cos_total_width: cone_angle.to_radians().cos(),
+            cos_falloff_start: (cone_angle - cone_delta).to_radians().cos(),
+        }))
+    }
+
+    fn parse_directional_light(&mut self) -> Result<Box<dyn Light>, ParseError> {
+        let mut dir = Vec3::new(0.0, -1.0, 0.0);
+        let mut irradiance = Rgb::new(1.0, 1.0, 1.0);
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "direction" => {
+                    let val_tok = p.peek().clone();
+                    dir = p.parse_vec3()?;
+                    if dir.len_sq() == 0.0 {
+                        return Err(error_at(&val_tok, "direction must not be zero"));
+                    }
+                },
+                "irradiance" => irradiance = p.parse_rgb()?,
+                _ => return Err(unknown_property("directional_light", key, key_tok)),
+            }
+            Ok(())
+        })?;
+
+        Ok(Box::new(DistantLight::new(Vec3::normalize(dir), irradiance)))
+    }
+
+    fn parse_environment_light(&mut self, tok: &Token) -> Result<Box<dyn Light>, ParseError> {
+        let mut map = None;
+        let mut intensity = Rgb::new(1.0, 1.0, 1.0);
+        let mut light_to_world = Transform::identity();
+        let mut world_to_light = Transform::identity();
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "file" => {
+                    let (path, file_tok) = p.parse_path()?;
+                    map = Some(imageio::read_image(&path).map_err(|e| {
+                        error_at(&file_tok, format!("failed to load environment map: {}", e))
+                    })?);
+                },
+                "intensity" => intensity = p.parse_rgb()?,
+                "rotate" => {
+                    let (angle, axis) = p.parse_rotation()?;
+                    // Each rotation applies after the previous ones.
+                    light_to_world = Transform::rotate(angle, axis).compose(&light_to_world);
+                    world_to_light = world_to_light.compose(&Transform::rotate(-angle, axis));
+                },
+                _ => return Err(unknown_property("environment_light", key, key_tok)),
+            }
+            Ok(())
+        })?;
+
+        let map = map.ok_or_else(|| error_at(tok, "environment_light is missing 'file'"))?;
+        if map.width == 0 || map.height == 0 {
+            return Err(error_at(tok, "environment map is empty"));
+        }
+        Ok(Box::new(EnvironmentLight::new(map, intensity, light_to_world, world_to_light)))
+    }
+
+    // Returns the sky and, unless it's turned off, the sun.
+    fn parse_sky_light(&mut self, tok: &Token) -> Result<Vec<Box<dyn Light>>, ParseError> {
+        let mut sun_dir = None;
+        let mut latitude = None;
+        let (mut longitude, mut timezone, mut day, mut time) = (0.0, 0.0, 172.0, 12.0);
+        let mut turbidity = 3.0;
+        let mut intensity = 0.02;
+        let mut sun = true;
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "sun_direction" => {
+                    let val_tok = p.peek().clone();
+                    let dir = p.parse_vec3()?;
+                    if dir.len_sq() == 0.0 {
+                        return Err(error_at(&val_tok, "sun_direction must not be zero"));
+                    }
+                    sun_dir = Some(Vec3::normalize(dir));
+                },
+                "latitude" => {
+                    let val_tok = p.peek().clone();
+                    let val = p.parse_f32()?;
+                    if !(-90.0..=90.0).contains(&val) {
+                        return Err(error_at(&val_tok, "latitude must be between -90 and 90"));
+                    }
+                    latitude = Some(val);
+                },
+                "longitude" => longitude = p.parse_f32()?,
+                "timezone" => timezone = p.parse_f32()?,
+                "day" => {
+                    let val_tok = p.peek().clone();
+                    day = p.parse_f32()?;
+                    if !(1.0..=366.0).contains(&day) {
+                        return Err(error_at(&val_tok, "day must be between 1 and 366"));
+                    }
+                },
+                "time" => time = p.parse_f32()?,
+                "turbidity" => {
+                    let val_tok = p.peek().clone();
+                    turbidity = p.parse_f32()?;
+                    if !(1.7..=10.0).contains(&turbidity) {
+                        return Err(error_at(&val_tok, "turbidity must be between 1.7 and 10"));
+                    }
+                },
+                "intensity" => intensity = p.parse_positive_f32()?,
+                "sun" => sun = p.parse_bool()?,
+                _ => return Err(unknown_property("sky_light", key, key_tok)),
+            }
+            Ok(())
+        })?;
+
+        let sun_dir = match (sun_dir, latitude) {
+            (Some(_), Some(_)) => {
+                return Err(error_at(tok, "sky_light can't have both 'sun_direction' and \
+                                          'latitude'"));
+            },
+            (Some(dir), None) => dir,
+            (None, Some(latitude)) => sun_direction(latitude, longitude, timezone, day, time),
+            (None, None) => Vec3::new(0.0, 1.0, 0.0),
+        };
+        if sun_dir.y <= 0.0 {
+            return Err(error_at(tok, "the sun is below the horizon"));
+        }
+
+        // The sky is smooth enough that a small map resolves it.
+        let sky = Sky::new(sun_dir, turbidity);
+        let scale = Rgb::new(intensity, intensity, intensity);
+        let mut lights: Vec<Box<dyn Light>> = vec![
+            Box::new(EnvironmentLight::new(sky.to_environment_map(512, 256), scale,
+                                           Transform::identity(), Transform::identity())),
+        ];
+        if sun {
+            lights.push(Box::new(SunLight::new(sun_dir, intensity * sky.sun_radiance(),
+                                               SUN_ANGULAR_RADIUS)));
+        }
+        Ok(lights)
+    }
+
+    fn parse_mesh(&mut self, tok: &Token, scene: &mut Scene) -> Result<(), ParseError> {
+        let mut groups = None;
+        let mut material = None;
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "file" => {
+                    let (path, file_tok) = p.parse_path()?;
+                    groups = Some(obj::load_obj(&path).map_err(|e| {
+                        error_at(&file_tok, format!("failed to load mesh: {}", e))
+                    })?);
+                },
+                "material" => material = Some(p.parse_material_ref()?),
+                _ => return Err(unknown_property("mesh", key, key_tok)),
+            }
+            Ok(())
+        })?;
+
+        let groups = groups.ok_or_else(|| error_at(tok, "mesh is missing 'file'"))?;
+
+        for group in groups {
+            scene.objs.push(Object {
+                shape: Arc::new(group.mesh),
+                material: material.unwrap_or(group.material),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn parse_object(&mut self, obj_tok: &Token) -> Result<Object, ParseError> {
+        let mut shape: Option<Arc<dyn Shape>> = None;
+        let mut material = None;
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "sphere" | "triangle" | "plane" | "disk" | "box" | "quad" | "cylinder" |
+                "cone" | "paraboloid" | "hyperboloid" | "torus" => {
+                    if shape.is_some() {
+                        return Err(error_at(key_tok, "object already has a shape"));
+                    }
+                    shape = Some(match key {
+                        "sphere" => p.parse_sphere(key_tok)?,
+                        "plane" => p.parse_plane()?,
+                        "disk" => p.parse_disk(key_tok)?,
+                        "box" => p.parse_box(key_tok)?,
+                        "quad" => p.parse_quad(key_tok)?,
+                        "cylinder" => p.parse_cylinder(key_tok)?,
+                        "cone" => p.parse_cone(key_tok)?,
+                        "paraboloid" => p.parse_paraboloid(key_tok)?,
+                        "hyperboloid" => p.parse_hyperboloid(key_tok)?,
+                        "torus" => p.parse_torus(key_tok)?,
+                        _ => p.parse_triangle(key_tok)?,
+                    });
+                },
+                "material" => {
+                    if material.is_some() {
+                        return Err(error_at(key_tok, "object already has a material"));
+                    }
+                    material = Some(p.parse_material_ref()?);
+                },
+                _ => return Err(unknown_property("object", key, key_tok)),
+            }
+            Ok(())
+        })?;
+
+        Ok(Object {
+            shape: shape.ok_or_else(|| error_at(obj_tok, "object is missing a shape"))?,
+            material: material.unwrap_or_default(),
+        })
+    }
+
+    fn parse_sphere(&mut self, tok: &Token) -> Result<Arc<dyn Shape>, ParseError> {
+        let mut c = None;
+        let mut r = None;
+        let mut z_range = (None, None);
+        let mut quadric = QuadricParams::new();
+        let mut partial = false;
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "center" => c = Some(p.parse_point3()?),
+                "radius" => r = Some(p.parse_positive_f32()?),
+                "z_min" => z_range.0 = Some(p.parse_f32()?),
+                "z_max" => z_range.1 = Some(p.parse_f32()?),
+                "translate" => return Err(unknown_property("sphere", key, key_tok)),
+                _ => {
+                    if !p.parse_quadric_property(key, &mut quadric)? {
+                        return Err(unknown_property("sphere", key, key_tok));
+                    }
+                },
+            }
+            partial |= key != "center" && key != "radius";
+            Ok(())
+        })?;
+
+        let c = c.ok_or_else(|| error_at(tok, "sphere is missing 'center'"))?;
+        let r = r.ok_or_else(|| error_at(tok, "sphere is missing 'radius'"))?;
+        if !partial {
+            return Ok(Arc::new(Sphere { c, r }));
+        }
+
+        let (z_min, z_max) = (z_range.0.unwrap_or(-r), z_range.1.unwrap_or(r));
+        if z_min >= z_max || z_max <= -r || z_min >= r {
+            return Err(error_at(tok, "sphere z_min and z_max must cut out part of the sphere"));
+        }
+        let center = c - Point3::zeroes();
+        quadric.placement.then(Transform::translate(center), Transform::translate(-center));
+        Ok(quadric.build(|phi_max| PartialSphere::new(r, z_min, z_max, phi_max)))
+    }
+
+    fn parse_triangle(&mut self, tok: &Token) -> Result<Arc<dyn Shape>, ParseError> {
+        let mut pts = [None; 3];
+
+        self.parse_block(|p, key, key_tok| {
+            let idx = match key {
+                "p0" => 0,
+                "p1" => 1,
+                "p2" => 2,
+                _ => return Err(unknown_property("triangle", key, key_tok)),
+            };
+            pts[idx] = Some(p.parse_point3()?);
+            Ok(())
+        })?;
+
+        let missing = |name| error_at(tok, format!("triangle is missing '{}'", name));
+
+        Ok(Arc::new(Triangle {
+            p0: pts[0].ok_or_else(|| missing("p0"))?,
+            p1: pts[1].ok_or_else(|| missing("p1"))?,
+            p2: pts[2].ok_or_else(|| missing("p2"))?,
+        }))
+    }
+
+    fn parse_plane(&mut self) -> Result<Arc<dyn Shape>, ParseError> {
+        let mut point = Point3::zeroes();
+        let mut normal = Vec3::new(0.0, 1.0, 0.0);
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "point" => point = p.parse_point3()?,
+                "normal" => normal = p.parse_normal()?,
+                _ => return Err(unknown_property("plane", key, key_tok)),
+            }
+            Ok(())
+        })?;
+
+        Ok(Arc::new(Plane::new(point, normal)))
+    }
+
+    fn parse_disk(&mut self, tok: &Token) -> Result<Arc<dyn Shape>, ParseError> {
+        let mut c = None;
+        let mut normal = Vec3::new(0.0, 1.0, 0.0);
+        let mut r = None;
+        let mut inner_r = 0.0;
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "center" => c = Some(p.parse_point3()?),
+                "normal" => normal = p.parse_normal()?,
+                "radius" => r = Some(p.parse_positive_f32()?),
+                "inner_radius" => {
+                    let val_tok = p.peek().clone();
+                    inner_r = p.parse_f32()?;
+                    if inner_r < 0.0 {
+                        return Err(error_at(&val_tok, "inner_radius must not be negative"));
+                    }
+                },
+                _ => return Err(unknown_property("disk", key, key_tok)),
+            }
+            Ok(())
+        })?;
+
+        let c = c.ok_or_else(|| error_at(tok, "disk is missing 'center'"))?;
+        let r = r.ok_or_else(|| error_at(tok, "disk is missing 'radius'"))?;
+        if inner_r >= r {
+            return Err(error_at(tok, "disk inner_radius must be less than its radius"));
+        }
+        Ok(Arc::new(Disk::new(c, normal, r, inner_r)))
+    }
+
+    fn parse_box(&mut self, tok: &Token) -> Result<Arc<dyn Shape>, ParseError> {
+        let mut min = None;
+        let mut max = None;
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "min" => min = Some(p.parse_point3()?),
+                "max" => max = Some(p.parse_point3()?),
+                _ => return Err(unknown_property("box", key, key_tok)),
+            }
+            Ok(())
+        })?;
+
+        let missing = |name| error_at(tok, format!("box is missing '{}'", name));
+        // Either pair of opposite corners will do.
+        let bounds = Bounds3::new(min.ok_or_else(|| missing("min"))?,
+                                  max.ok_or_else(|| missing("max"))?);
+        Ok(Arc::new(Cuboid { min: bounds.min, max: bounds.max }))
+    }
+
+    fn parse_quad(&mut self, tok: &Token) -> Result<Arc<dyn Shape>, ParseError> {
+        let mut corner = None;
+        let mut edges = [None; 2];
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "corner" => corner = Some(p.parse_point3()?),
+                "edge1" => edges[0] = Some(p.parse_vec3()?),
+                "edge2" => edges[1] = Some(p.parse_vec3()?),
+                _ => return Err(unknown_property("quad", key, key_tok)),
+            }
+            Ok(())
+        })?;
+
+        let missing = |name| error_at(tok, format!("quad is missing '{}'", name));
+        let quad = Quad {
+            p: corner.ok_or_else(|| missing("corner"))?,
+            e1: edges[0].ok_or_else(|| missing("edge1"))?,
+            e2: edges[1].ok_or_else(|| missing("edge2"))?,
+        };
+        if quad.area() == 0.0 {
+            return Err(error_at(tok, "quad edges must be non-zero and not parallel"));
+        }
+        Ok(Arc::new(quad))
+    }
+
+    fn parse_torus(&mut self, tok: &Token) -> Result<Arc<dyn Shape>, ParseError> {
+        let mut major = None;
+        let mut minor = None;
+        let mut placement = Placement::identity();
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "major_radius" => major = Some(p.parse_positive_f32()?),
+                "minor_radius" => minor = Some(p.parse_positive_f32()?),
+                _ => {
+                    if !p.parse_placement_property(key, &mut placement)? {
+                        return Err(unknown_property("torus", key, key_tok));
+                    }
+                },
+            }
+            Ok(())
+        })?;
+
+        let missing = |name| error_at(tok, format!("torus is missing '{}'", name));
+        let major = major.ok_or_else(|| missing("major_radius"))?;
+        let minor = minor.ok_or_else(|| missing("minor_radius"))?;
+        if minor >= major {
+            return Err(error_at(tok, "torus minor_radius must be less than its major_radius"));
+        }
+        Ok(Arc::new(Torus::new(major, minor, placement.object_to_world,
+                               placement.world_to_object)))
+    }
+
+    fn parse_cylinder(&mut self, tok: &Token) -> Result<Arc<dyn Shape>, ParseError> {
+        let mut r = None;
+        let mut z_range = (0.0, 1.0);
+        let mut quadric = QuadricParams::new();
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "radius" => r = Some(p.parse_positive_f32()?),
+                "z_min" => z_range.0 = p.parse_f32()?,
+                "z_max" => z_range.1 = p.parse_f32()?,
+                _ => {
+                    if !p.parse_quadric_property(key, &mut quadric)? {
+                        return Err(unknown_property("cylinder", key, key_tok));
+                    }
+                },
+            }
+            Ok(())
+        })?;
+
+        let r = r.ok_or_else(|| error_at(tok, "cylinder is missing 'radius'"))?;
+        if z_range.0 >= z_range.1 {
+            return Err(error_at(tok, "cylinder z_min must be less than z_max"));
+        }
+        Ok(quadric.build(|phi_max| Cylinder::new(r, z_range.0, z_range.1, phi_max)))
+    }
+
+    fn parse_cone(&mut self, tok: &Token) -> Result<Arc<dyn Shape>, ParseError> {
+        let mut r = None;
+        let mut height = None;
+        let mut quadric = QuadricParams::new();
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "radius" => r = Some(p.parse_positive_f32()?),
+                "height" => height = Some(p.parse_positive_f32()?),
+                _ => {
+                    if !p.parse_quadric_property(key, &mut quadric)? {
+                        return Err(unknown_property("cone", key, key_tok));
+                    }
+                },
+            }
+            Ok(())
+        })?;
+
+        let missing = |name| error_at(tok, format!("cone is missing '{}'", name));
+        let r = r.ok_or_else(|| missing("radius"))?;
+        let height = height.ok_or_else(|| missing("height"))?;
+        Ok(quadric.build(|phi_max| Cone::new(r, height, phi_max)))
+    }
+
+    fn parse_paraboloid(&mut self, tok: &Token) -> Result<Arc<dyn Shape>, ParseError> {
+        let mut r = None;
+        let mut z_min = 0.0;
+        let mut z_max = 1.0;
+        let mut quadric = QuadricParams::new();
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "radius" => r = Some(p.parse_positive_f32()?),
+                "z_min" => {
+                    let val_tok = p.peek().clone();
+                    z_min = p.parse_f32()?;
+                    if z_min < 0.0 {
+                        return Err(error_at(&val_tok, "z_min must not be negative"));
+                    }
+                },
+                "z_max" => z_max = p.parse_positive_f32()?,
+                _ => {
+                    if !p.parse_quadric_property(key, &mut quadric)? {
+                        return Err(unknown_property("paraboloid", key, key_tok));
+                    }
+                },
+            }
+            Ok(())
+        })?;
+
+        let r = r.ok_or_else(|| error_at(tok, "paraboloid is missing 'radius'"))?;
+        if z_min >= z_max {
+            return Err(error_at(tok, "paraboloid z_min must be less than z_max"));
+        }
+        Ok(quadric.build(|phi_max| Paraboloid::new(r, z_min, z_max, phi_max)))
+    }
+
+    fn parse_hyperboloid(&mut self, tok: &Token) -> Result<Arc<dyn Shape>, ParseError> {
+        let mut pts = [None; 2];
+        let mut quadric = QuadricParams::new();
+
+        self.parse_block(|p, key, key_tok| {
+            match key {
+                "p1" => pts[0] = Some(p.parse_point3()?),
+                "p2" => pts[1] = Some(p.parse_point3()?),
+                _ => {
+                    if !p.parse_quadric_property(key, &mut quadric)? {
+                        return Err(unknown_property("hyperboloid", key, key_tok));
+                    }
+                },
+            }
+            Ok(())
+        })?;
+
+        let missing = |name| error_at(tok, format!("hyperboloid is missing '{}'", name));
+        let p1 = pts[0].ok_or_else(|| missing("p1"))?;
+        let p2 = pts[1].ok_or_else(|| missing("p2"))?;
+        if p1.z == p2.z {
+            return Err(error_at(tok, "hyperboloid p1 and p2 must be at different heights"));
+        }
+        Ok(quadric.build(|phi_max| Hyperboloid::new(p1, p2, phi_max)))
+    }
+
+    // Parses the properties that all the quadrics share. Returns false if |key| isn't one of
+    // them.
+    fn parse_quadric_property(&mut self, key: &str, quadric: &mut QuadricParams)
+                              -> Result<bool, ParseError> {
+        match key {
+            "phi_max" => {
+                let val_tok = self.peek().clone();
+                quadric.phi_max = self.parse_f32()?;
+                if quadric.phi_max <= 0.0 || quadric.phi_max > 360.0 {
+                    return Err(error_at(&val_tok, "phi_max must be between 0 and 360 degrees"));
+                }
+            },
+            _ => return self.parse_placement_property(key, &mut quadric.placement),
+        }
+        Ok(true)
+    }
+
+    // Parses the `rotate` and `translate` properties that place a shape's object space in the
+    // world. Returns false if |key| isn't one of them.
+    fn parse_placement_property(&mut self, key: &str, placement: &mut Placement)
+                                -> Result<bool, ParseError> {
+        match key {
+            "rotate" => {
+                let (angle, axis) = self.parse_rotation()?;
+                placement.then(Transform::rotate(angle, axis), Transform::rotate(-angle, axis));
+            },
+            "translate" => {
+                let delta = self.parse_vec3()?;
+                placement.then(Transform::translate(delta), Transform::translate(-delta));
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // Parses an angle in degrees followed by the axis to rotate around.
+    fn parse_rotation(&mut self) -> Result<(f32, Vec3), ParseError> {
+        let angle = self.parse_f32()?;
+        let axis_tok = self.peek().clone();
+        let axis = self.parse_vec3()?;
+        if axis.len_sq() == 0.0 {
+            return Err(error_at(&axis_tok, "rotation axis must not be zero"));
+        }
+        Ok((angle, axis))
+    }
+
+    // Parses a direction that's normalized later, so it only has to be non-zero.
+    fn parse_normal(&mut self) -> Result<Vec3, ParseError> {
+        let val_tok = self.peek().clone();
+        let n = self.parse_vec3()?;
+        if n.len_sq() == 0.0 {
+            return Err(error_at(&val_tok, "normal must not be zero"));
+        }
+        Ok(n)
+    }
+}
+
+// A rigid transform from a shape's object space to the world, together with its inverse.
+struct Placement {
+    object_to_world: Transform,
+    world_to_object: Transform,
+}
+
+impl Placement {
+    fn identity() -> Self {
+        Placement { object_to_world: Transform::identity(), world_to_object: Transform::identity() }
+    }
+
+    // Applies |t|, whose inverse is |inverse|, after the current placement.
+    fn then(&mut self, t: Transform, inverse: Transform) {
+        self.object_to_world = t.compose(&self.object_to_world);
+        self.world_to_object = self.world_to_object.compose(&inverse);
+    }
+}
+
+// The properties that all the quadrics share.
+struct QuadricParams {
+    placement: Placement,
+    // In degrees.
+    phi_max: f32,
+}
+
+impl QuadricParams {
+    fn new() -> Self {
+        QuadricParams { placement: Placement::identity(), phi_max: 360.0 }
+    }
+
+    // Places the surface that |surface| makes with phi_max.
+    fn build<S, F>(self, surface: F) -> Arc<dyn Shape>
+        where S: QuadricSurface + 'static, F: FnOnce(f32) -> S {
+
+        Arc::new(Quadric::new(surface(self.phi_max), self.placement.object_to_world,
+                              self.placement.world_to_object))
+    }
+}
+
+fn unknown_property(block: &str, key: &str, tok: &Token) -> ParseError {
+    error_at(tok, format!("unknown {} property '{}'", block, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::film::Film;
+    use crate::geometry::Ray;
+
+    fn err(src: &str) -> ParseError {
+        match parse_scene(src, Path::new("")) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let src = "
+            image { width 320 height 240 }
+            camera { eye 0 1 -2 target 0 1 0 fov 60 }
+            integrator { type path max_depth 3 samples 16 light_samples 4 }
+
+            material red { diffuse 0.5 0 0 reflect true }
+
+            point_light { position 0 10 10 }
+
+            object {
+                sphere { center 0 0 5 radius 1.5 }
+                material red
+            }
+            object {
+                triangle { p0 0 0 0 p1 1 0 0 p2 0 1 0 }
+                material { ambient 0.1 0.1 0.1 }
+            }
+        ";
+
+        let scene = parse_scene(src, Path::new("")).unwrap();
+        assert_eq!((scene.img_width, scene.img_height), (320, 240));
+        assert_eq!(scene.sampler.samples_per_pixel(), 16);
+        assert_eq!(scene.lights.len(), 1);
+        assert_eq!(scene.objs.len(), 2);
+        assert_eq!(scene.objs[0].material.diffuse.r, 0.5);
+        assert_eq!(scene.objs[0].material.kind, MaterialKind::Phong);
+        assert!(scene.objs[0].material.reflect);
+        assert_eq!(scene.objs[1].material.ambient.g, 0.1);
+        assert!(!scene.objs[1].material.reflect);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(err("foo {}"), ParseError::new(1, 1, "unknown statement 'foo'"));
+        assert_eq!(err("image {\n  width abc\n}"),
+                   ParseError::new(2, 9, "expected a non-negative integer, found 'abc'"));
+        assert_eq!(err("object {\n  sphere { center 0 0 0 }\n}"),
+                   ParseError::new(2, 3, "sphere is missing 'radius'"));
+        assert_eq!(err("object { material missing }"),
+                   ParseError::new(1, 19, "undefined material 'missing'"));
+        assert_eq!(err("object { sphere { radius -1 } }"),
+                   ParseError::new(1, 26, "expected a positive number, found '-1'"));
+        assert_eq!(err("image { width 10"),
+                   ParseError::new(1, 17, "expected '}', found end of file"));
+    }
+
+    #[test]
+    fn camera_errors() {
+        assert_eq!(err("camera {\n  eye 0 0 0\n  target 0 0 0\n}"),
+                   ParseError::new(1, 1, "camera eye and target must be different"));
+        assert_eq!(err("camera { up 0 0 2 }"),
+                   ParseError::new(1, 1,
+                                   "camera up must not be parallel to the viewing direction"));
+    }
+
+    #[test]
+    fn integrator_errors() {
+        assert_eq!(err("integrator { type foo }"),
+                   ParseError::new(1, 19, "unknown integrator type 'foo'"));
+        assert_eq!(err("integrator { light_samples 0 }"),
+                   ParseError::new(1, 28, "light_samples must be positive"));
+    }
+
+    #[test]
+    fn tone_mapping() {
+        let src = "image { dither true tone_map reinhard_extended white 2 exposure -1 }";
+        let scene = parse_scene(src, Path::new("")).unwrap();
+        assert!(scene.dither);
+        assert_eq!(scene.exposure, -1.0);
+        assert_eq!(scene.tone_map, ToneMap::ReinhardExtended { white: Some(2.0) });
+
+        assert_eq!(err("image { tone_map drago }"),
+                   ParseError::new(1, 18, "unknown tone mapping operator 'drago'"));
+    }
+
+    #[test]
+    fn sampler() {
+        let src = "
+            integrator { samples 16 }
+            sampler { type stratified jitter false seed 3 adaptive_threshold 0.05 min_samples 4 }
+        ";
+        let scene = parse_scene(src, Path::new("")).unwrap();
+        assert_eq!(scene.sampler.samples_per_pixel(), 16);
+        assert_eq!(scene.adaptive, Some(AdaptiveSampling { min_samples: 4, threshold: 0.05 }));
+        let mut sampler = scene.sampler.clone_sampler();
+        sampler.start_pixel_sample((0, 0), 0);
+        // Without jitter, samples are at the centers of the 4x4 strata.
+        let (u0, u1) = sampler.get_2d();
+        assert_eq!(((u0 * 8.0).fract(), (u1 * 8.0).fract()), (0.0, 0.0));
+    }
+
+    #[test]
+    fn sampler_errors() {
+        assert_eq!(err("sampler { type random }"),
+                   ParseError::new(1, 16, "unknown sampler type 'random'"));
+        assert_eq!(err("sampler { min_samples 0 }"),
+                   ParseError::new(1, 23, "min_samples must be positive"));
+    }
+
+    #[test]
+    fn filter() {
+        let scene = parse_scene("filter { type mitchell radius 3 b 0.5 c 0.25 }",
+                                Path::new("")).unwrap();
+        assert_eq!(scene.filter.radius(), (3.0, 3.0));
+        let mitchell = MitchellFilter { radius: (3.0, 3.0), b: 0.5, c: 0.25 };
+        assert_eq!(scene.filter.evaluate(0.5, 1.0), mitchell.evaluate(0.5, 1.0));
+
+        assert_eq!(err("filter { type sinc }"),
+                   ParseError::new(1, 15, "unknown filter type 'sinc'"));
+        assert_eq!(err("filter { radius 0 }"),
+                   ParseError::new(1, 17, "expected a positive number, found '0'"));
+    }
+
+    #[test]
+    fn materials() {
+        let src = "
+            material glass { type dielectric ior 1.33 }
+            material copper { type conductor metal copper roughness 0.5 anisotropy 0.2 }
+
+            object {
+                sphere { center 0 0 10 radius 1 }
+                material glass
+            }
+            object {
+                sphere { center 0 0 15 radius 1 }
+                material copper
+            }
+            object {
+                sphere { center 0 0 20 radius 1 }
+                material { emission 2 2 1 }
+            }
+        ";
+        let scene = parse_scene(src, Path::new("")).unwrap();
+        assert_eq!(scene.objs[0].material.kind,
+                   MaterialKind::Dielectric { ior: 1.33, roughness: 0.0, anisotropy: 0.0 });
+        let (eta, k) = metal_ior("copper").unwrap();
+        assert_eq!(scene.objs[1].material.kind,
+                   MaterialKind::Conductor { eta, k, roughness: 0.5, anisotropy: 0.2 });
+        assert!(scene.objs[0].material.emission.is_black());
+        assert_eq!(scene.objs[2].material.emission, Rgb::new(2.0, 2.0, 1.0));
+    }
+
+    #[test]
+    fn material_errors() {
+        assert_eq!(err("material m { type metal }"),
+                   ParseError::new(1, 19, "unknown material type 'metal'"));
+        assert_eq!(err("material m { metal lead }"),
+                   ParseError::new(1, 20, "unknown metal 'lead'"));
+        assert_eq!(err("material m { roughness 1.5 }"),
+                   ParseError::new(1, 24, "expected a number between 0 and 1, found '1.5'"));
+    }
+
+    #[test]
+    fn lights() {
+        let src = "
+            point_light { position 0 10 10 }
+            point_light { position 0 10 5 intensity 4 8 12 }
+            spot_light { position 0 10 0 target 0 0 0 cone_angle 20 }
+            directional_light { direction 0 -2 0 irradiance 3 3 3 }
+        ";
+        let scene = parse_scene(src, Path::new("")).unwrap();
+        assert_eq!(scene.lights.len(), 4);
+        let li = |light: &dyn Light, p: Point3| light.sample_li(p, (0.5, 0.5)).map(|s| s.li);
+        assert_eq!(li(scene.lights[0].as_ref(), Point3::new(0.0, 10.0, 9.0)),
+                   Some(Rgb::new(1.0, 1.0, 1.0)));
+        assert_eq!(li(scene.lights[1].as_ref(), Point3::new(0.0, 8.0, 5.0)),
+                   Some(Rgb::new(1.0, 2.0, 3.0)));
+        assert_eq!(li(scene.lights[2].as_ref(), Point3::new(0.0, 0.0, 0.0)),
+                   Some(Rgb::new(0.01, 0.01, 0.01)));
+        assert!(li(scene.lights[2].as_ref(), Point3::new(5.0, 0.0, 0.0)).is_none());
+        let sun = scene.lights[3].sample_li(Point3::zeroes(), (0.5, 0.5)).unwrap();
+        assert_eq!((sun.wi, sun.li), (Vec3::new(0.0, 1.0, 0.0), Rgb::new(3.0, 3.0, 3.0)));
+    }
+
+    #[test]
+    fn light_errors() {
+        assert_eq!(err("spot_light { position 0 0 0 }"),
+                   ParseError::new(1, 1, "spot_light is missing 'target'"));
+        assert_eq!(err("spot_light { cone_angle 200 }"),
+                   ParseError::new(1, 25, "cone_angle must be between 0 and 180 degrees"));
+        assert_eq!(err("directional_light { direction 0 0 0 }"),
+                   ParseError::new(1, 31, "direction must not be zero"));
+    }
+
+    #[test]
+    fn shapes() {
+        let src = "
+            object { plane { point 0 -1 0 } }
+            object { disk { center 0 5 0 normal 0 -1 0 radius 2 inner_radius 1 } }
+            object { box { min 1 2 1 max -1 0 -1 } }
+            object { quad { corner -1 5 -1 edge1 2 0 0 edge2 0 0 2 } }
+        ";
+        let scene = parse_scene(src, Path::new("")).unwrap();
+        let down = Ray { p: Point3::new(0.0, 10.0, 0.5), d: Vec3::new(0.0, -1.0, 0.0) };
+        let ts = scene.objs.iter()
+            .map(|obj| obj.shape.intersect(&down).map(|si| si.t))
+            .collect::<Vec<_>>();
+        assert_eq!(ts, vec![Some(11.0), None, Some(8.0), Some(5.0)]);
+        // The ray down the middle passes through the disk's hole, but one further out hits its
+        // back.
+        let side = Ray { p: Point3::new(0.0, 7.0, 1.5), d: Vec3::new(0.0, -1.0, 0.0) };
+        let si = scene.objs[1].shape.intersect(&side).unwrap();
+        assert_eq!((si.t, si.front_face), (2.0, false));
+    }
+
+    #[test]
+    fn shape_errors() {
+        assert_eq!(err("object { disk { center 0 0 0 radius 1 inner_radius 1 } }"),
+                   ParseError::new(1, 10, "disk inner_radius must be less than its radius"));
+        assert_eq!(err("object { plane { normal 0 0 0 } }"),
+                   ParseError::new(1, 25, "normal must not be zero"));
+        assert_eq!(err("object { quad { corner 0 0 0 edge1 1 0 0 edge2 2 0 0 } }"),
+                   ParseError::new(1, 10, "quad edges must be non-zero and not parallel"));
+        assert_eq!(err("object { box { min 0 0 0 } }"),
+                   ParseError::new(1, 10, "box is missing 'max'"));
+    }
+
+    #[test]
+    fn quadrics() {
+        let src = "
+            object { cylinder { radius 1 z_max 2 rotate -90 1 0 0 translate 0 0 5 } }
+            object { cone { radius 1 height 2 rotate -90 1 0 0 } }
+            object { paraboloid { radius 2 z_max 4 rotate -90 1 0 0 } }
+            object { hyperboloid { p1 1 -1 -1 p2 1 1 1 phi_max 180 } }
+            object { sphere { center 0 1 0 radius 1 z_max 0 } }
+            object { sphere { center 0 1 0 radius 1 } }
+            object { torus { major_radius 2 minor_radius 0.5 rotate -90 1 0 0 translate 0 1 5 } }
+        ";
+        let scene = parse_scene(src, Path::new("")).unwrap();
+        let rays = [
+            Ray { p: Point3::new(0.0, 1.0, 0.0), d: Vec3::new(0.0, 0.0, 1.0) },
+            Ray { p: Point3::new(-5.0, 1.0, 0.0), d: Vec3::new(1.0, 0.0, 0.0) },
+            Ray { p: Point3::new(1.5, 10.0, 0.0), d: Vec3::new(0.0, -1.0, 0.0) },
+            Ray { p: Point3::new(0.0, -5.0, 0.0), d: Vec3::new(0.0, 1.0, 0.0) },
+            Ray { p: Point3::new(0.0, 1.0, 5.0), d: Vec3::new(0.0, 0.0, -1.0) },
+            Ray { p: Point3::new(0.0, 1.0, 5.0), d: Vec3::new(0.0, 0.0, -1.0) },
+            Ray { p: Point3::new(2.0, 5.0, 5.0), d: Vec3::new(0.0, -1.0, 0.0) },
+        ];
+        let expected = [4.0, 4.5, 7.75, 6.0, 6.0, 4.0, 3.5];
+        for ((obj, ray), &t) in scene.objs.iter().zip(&rays).zip(&expected) {
+            assert!((obj.shape.intersect(ray).unwrap().t - t).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn quadric_errors() {
+        assert_eq!(err("object { cylinder { radius 1 z_min 1 } }"),
+                   ParseError::new(1, 10, "cylinder z_min must be less than z_max"));
+        assert_eq!(err("object { cone { radius 1 phi_max 0 } }"),
+                   ParseError::new(1, 34, "phi_max must be between 0 and 360 degrees"));
+        assert_eq!(err("object { hyperboloid { p1 1 0 0 p2 2 0 0 } }"),
+                   ParseError::new(1, 10, "hyperboloid p1 and p2 must be at different heights"));
+        assert_eq!(err("object { sphere { center 0 0 0 radius 1 z_min 2 } }"),
+                   ParseError::new(1, 10, "sphere z_min and z_max must cut out part of the \
+                                           sphere"));
+        // Partial spheres are placed around their center, so they can't be translated.
+        assert_eq!(err("object { sphere { translate 0 0 1 } }"),
+                   ParseError::new(1, 19, "unknown sphere property 'translate'"));
+    }
+
+    #[test]
+    fn torus_errors() {
+        assert_eq!(err("object { torus { major_radius 1 minor_radius 2 } }"),
+                   ParseError::new(1, 10, "torus minor_radius must be less than its \
+                                           major_radius"));
+    }
+
+    #[test]
+    fn mesh() {
+        let dir = std::env::temp_dir().join("raytracer_scene_mesh");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("quad.obj"), "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n").unwrap();
+
+        let src = "mesh { file \"quad.obj\" material { diffuse 1 0 0 } }";
+        let scene = parse_scene(src, &dir).unwrap();
+        // The quad is split into two triangles, which stay one object.
+        assert_eq!(scene.objs.len(), 1);
+        assert_eq!(scene.objs[0].shape.num_parts(), 2);
+        assert_eq!(scene.objs[0].material.diffuse.r, 1.0);
+
+        let src = "mesh {\n  file \"missing.obj\"\n}";
+        let e = match parse_scene(src, &dir) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e,
+        };
+        assert_eq!((e.line, e.col), (2, 8));
+        assert_eq!(err("mesh { file missing.obj }"),
+                   ParseError::new(1, 13, "expected a file name, found 'missing.obj'"));
+    }
+
+    #[test]
+    fn environment_light() {
+        let dir = std::env::temp_dir().join("raytracer_scene_environment");
+        fs::create_dir_all(&dir).unwrap();
+        let mut map = Film::new(4, 2);
+        for x in 0..4 {
+            map.set(x, 0, Rgb::new(2.0, 2.0, 2.0));
+        }
+        let mut bytes = Vec::new();
+        imageio::exr::write_exr(&mut bytes, &map, imageio::ExrCompression::None).unwrap();
+        fs::write(dir.join("sky.exr"), bytes).unwrap();
+
+        let src = "environment_light { file \"sky.exr\" intensity 3 3 3 rotate 90 1 0 0 }";
+        let scene = parse_scene(src, &dir).unwrap();
+        assert_eq!(scene.lights.len(), 1);
+        // After the rotation, the top row of the map is in the +z direction.
+        let ray = Ray { p: Point3::zeroes(), d: Vec3::new(0.01, 0.0, 1.0) };
+        assert_eq!(scene.lights[0].le(&ray), Rgb::new(6.0, 6.0, 6.0));
+
+        let src = "environment_light {\n  file \"missing.hdr\"\n}";
+        let e = match parse_scene(src, &dir) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e,
+        };
+        assert_eq!((e.line, e.col), (2, 8));
+        assert_eq!(err("environment_light { intensity 1 1 1 }"),
+                   ParseError::new(1, 1, "environment_light is missing 'file'"));
+    }
+
+    #[test]
+    fn sky_light() {
+        let scene = parse_scene("sky_light { sun_direction 0 1 1 intensity 0.5 }",
+                                Path::new("")).unwrap();
+        assert_eq!(scene.lights.len(), 2);
+        let up = Ray { p: Point3::zeroes(), d: Vec3::new(0.0, 1.0, 0.0) };
+        let sky = Sky::new(Vec3::new(0.0, 1.0, 1.0), 3.0);
+        let expected = 0.5 * sky.radiance(up.d);
+        assert!((scene.lights[0].le(&up).g - expected.g).abs() < 0.01 * expected.g);
+        assert!(scene.lights[1].le(&up).is_black());
+        let to_sun = Ray { p: Point3::zeroes(), d: Vec3::new(0.0, 1.0, 1.0) };
+        assert_eq!(scene.lights[1].le(&to_sun), 0.5 * sky.sun_radiance());
+
+        let src = "sky_light { latitude 45 day 80 time 12 turbidity 2 sun false }";
+        assert_eq!(parse_scene(src, Path::new("")).unwrap().lights.len(), 1);
+    }
+
+    #[test]
+    fn sky_light_errors() {
+        assert_eq!(err("sky_light { turbidity 20 }"),
+                   ParseError::new(1, 23, "turbidity must be between 1.7 and 10"));
+        assert_eq!(err("sky_light { sun_direction 0 1 0 latitude 10 }"),
+                   ParseError::new(1, 1, "sky_light can't have both 'sun_direction' and \
+                                          'latitude'"));
+        assert_eq!(err("sky_light { latitude 45 time 0 }"),
+                   ParseError::new(1, 1, "the sun is below the horizon"));
+    }
+}
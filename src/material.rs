@@ -0,0 +1,114 @@
+use crate::bxdfs::{BlinnPhong, Bsdf, FresnelSpecular, Lambertian, MicrofacetConductor,
+                   MicrofacetDielectric, SpecularReflection, TrowbridgeReitz};
+use crate::geometry::Frame;
+use crate::rgb::Rgb;
+
+// Exponent of the specular highlight.
+pub const SPECULAR_EXPONENT: f32 = 100.0;
+
+// Fraction of light that reflective materials reflect.
+pub const REFLECTANCE: f32 = 0.3;
+
+// The physically based materials below ignore the Phong colors. Their |roughness| in [0, 1] is
+// the perceptual roughness of a GGX microfacet distribution, where 0 is perfectly smooth, and
+// |anisotropy| in [0, 1] stretches the highlights along the direction in which the shape's u
+// parameter increases.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MaterialKind {
+    // Ambient, diffuse and Blinn-Phong specular shading, plus a mirror reflection if |reflect| is
+    // set.
+    Phong,
+    // A metal with the complex index of refraction |eta| + i|k| relative to the surroundings.
+    Conductor { eta: Rgb, k: Rgb, roughness: f32, anisotropy: f32 },
+    // A transmissive material such as glass or water that reflects and refracts according to the
+    // Fresnel equations. |ior| is the index of refraction relative to the surroundings. The
+    // Whitted integrator renders it as if it were smooth.
+    //
+    // Rays are considered to be entering the material when they hit the front of the surface,
+    // so closed meshes need triangles that wind counterclockwise seen from outside.
+    Dielectric { ior: f32, roughness: f32, anisotropy: f32 },
+}
+
+// Returns the complex index of refraction (eta, k) of the metal called |name|, sampled at
+// wavelengths of roughly 650, 550 and 450nm.
+pub fn metal_ior(name: &str) -> Option<(Rgb, Rgb)> {
+    let (eta, k) = match name {
+        "aluminium" => ((1.657, 0.880, 0.521), (9.224, 6.270, 4.837)),
+        "copper" => ((0.200, 0.924, 1.102), (3.912, 2.452, 2.142)),
+        "gold" => ((0.143, 0.374, 1.442), (3.983, 2.385, 1.603)),
+        "silver" => ((0.155, 0.117, 0.138), (4.828, 3.122, 2.147)),
+        _ => return None,
+    };
+    Some((Rgb::new(eta.0, eta.1, eta.2), Rgb::new(k.0, k.1, k.2)))
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Material {
+    pub ambient: Rgb,
+    pub diffuse: Rgb,
+    pub specular: Rgb,
+    pub reflect: bool,
+    // Radiance emitted from both sides of the surface. Objects that emit light become area
+    // lights.
+    pub emission: Rgb,
+    pub kind: MaterialKind,
+}
+
+impl Material {
+    // Returns the BSDF used by the path tracer at a point with the shading coordinate system
+    // |frame|. The ambient term approximates indirect light, which the path tracer computes
+    // instead, so it's ignored.
+    pub fn bsdf(&self, frame: Frame) -> Bsdf {
+        let mut bsdf = Bsdf::new(frame);
+
+        match self.kind {
+            MaterialKind::Phong => (),
+            MaterialKind::Conductor { eta, k, roughness, anisotropy } => {
+                let distrib = TrowbridgeReitz::from_roughness(roughness, anisotropy);
+                bsdf.add(Box::new(MicrofacetConductor { distrib, eta, k }));
+                return bsdf;
+            },
+            MaterialKind::Dielectric { ior, roughness, anisotropy } => {
+                let distrib = TrowbridgeReitz::from_roughness(roughness, anisotropy);
+                if distrib.effectively_smooth() {
+                    bsdf.add(Box::new(FresnelSpecular {
+                        r: Rgb::new(1.0, 1.0, 1.0),
+                        t: Rgb::new(1.0, 1.0, 1.0),
+                        eta_a: 1.0,
+                        eta_b: ior,
+                    }));
+                } else {
+                    bsdf.add(Box::new(MicrofacetDielectric { distrib, eta: ior }));
+                }
+                return bsdf;
+            },
+        }
+
+        if !self.diffuse.is_black() {
+            bsdf.add(Box::new(Lambertian { r: self.diffuse }));
+        }
+        if !self.specular.is_black() {
+            bsdf.add(Box::new(BlinnPhong { r: self.specular, exponent: SPECULAR_EXPONENT }));
+        }
+        if self.reflect {
+            bsdf.add(Box::new(SpecularReflection {
+                r: Rgb::new(REFLECTANCE, REFLECTANCE, REFLECTANCE),
+            }));
+        }
+
+        bsdf
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            ambient: Rgb::new(0.0, 0.0, 0.0),
+            diffuse: Rgb::new(0.0, 0.0, 0.0),
+            specular: Rgb::new(0.0, 0.0, 0.0),
+            reflect: false,
+            emission: Rgb::new(0.0, 0.0, 0.0),
+            kind: MaterialKind::Phong,
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod polynomial;
+
+pub use self::polynomial::{solve_quadratic, solve_quartic};
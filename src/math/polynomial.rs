@@ -0,0 +1,302 @@
+// Real roots of polynomials up to degree four. Everything is in double precision, since the
+// coefficients that ray intersections produce are often badly conditioned.
+
+use std::f64::consts::PI;
+
+// Up to four real roots, in increasing order.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Roots {
+    roots: [f64; 4],
+    len: usize,
+}
+
+impl Roots {
+    fn new() -> Self {
+        Roots { roots: [0.0; 4], len: 0 }
+    }
+
+    fn push(&mut self, root: f64) {
+        self.roots[self.len] = root;
+        self.len += 1;
+    }
+
+    fn sort(&mut self) {
+        self.roots[..self.len].sort_by(|a, b| a.partial_cmp(b).unwrap());
+    }
+
+    pub fn as_slice(&self) -> &[f64] {
+        &self.roots[..self.len]
+    }
+}
+
+// Returns the real roots of a * x^2 + b * x + c in increasing order, or one root twice if the
+// equation is linear.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    if a == 0.0 {
+        if b == 0.0 {
+            return None;
+        }
+        return Some((-c / b, -c / b));
+    }
+
+    let discrm = b * b - 4.0 * a * c;
+    if discrm < 0.0 {
+        return None;
+    }
+
+    // Avoids subtracting nearly equal numbers in the textbook formula when b^2 >> 4ac.
+    let q = if b < 0.0 { -0.5 * (b - discrm.sqrt()) } else { -0.5 * (b + discrm.sqrt()) };
+    if q == 0.0 {
+        return Some((0.0, 0.0));
+    }
+    let (x0, x1) = (q / a, c / q);
+    Some((x0.min(x1), x0.max(x1)))
+}
+
+// Returns the real roots of a * x^3 + b * x^2 + c * x + d. A repeated root may be returned more
+// than once.
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Roots {
+    let mut roots = Roots::new();
+    if a == 0.0 {
+        if let Some((x0, x1)) = solve_quadratic(b, c, d) {
+            roots.push(x0);
+            if b != 0.0 {
+                roots.push(x1);
+            }
+        }
+        return roots;
+    }
+
+    // Substituting x = t - b / 3 gives the depressed cubic t^3 + p * t + q.
+    let (b, c, d) = (b / a, c / a, d / a);
+    let shift = -b / 3.0;
+    let p = c - b * b / 3.0;
+    let q = 2.0 * b * b * b / 27.0 - b * c / 3.0 + d;
+
+    let discrm = 0.25 * q * q + p * p * p / 27.0;
+    if discrm > 0.0 {
+        // One real root by Cardano's formula, picking the cube root that doesn't cancel.
+        let u = (-0.5 * q - q.signum() * discrm.sqrt()).cbrt();
+        let t = if u == 0.0 { 0.0 } else { u - p / (3.0 * u) };
+        roots.push(t + shift);
+    } else if p == 0.0 {
+        roots.push(shift);
+    } else {
+        // Three real roots, which are the projections of an equilateral triangle's corners.
+        let r = (-p / 3.0).sqrt();
+        let phi = (-0.5 * q / (r * r * r)).clamp(-1.0, 1.0).acos();
+        for k in 0..3 {
+            roots.push(2.0 * r * ((phi + 2.0 * PI * k as f64) / 3.0).cos() + shift);
+        }
+    }
+
+    for x in &mut roots.roots[..roots.len] {
+        *x = polish(&[a, a * b, a * c, a * d], *x);
+    }
+    roots.sort();
+    roots
+}
+
+// Returns the real roots of a * x^4 + b * x^3 + c * x^2 + d * x + e by Ferrari's method, with
+// each root refined by Newton's method. Double roots, which a grazing ray produces, are found
+// even when rounding makes them slightly complex, and are returned twice.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Roots {
+    if a == 0.0 {
+        return solve_cubic(b, c, d, e);
+    }
+
+    // Substituting x = y - b / 4 gives the depressed quartic y^4 + p * y^2 + q * y + r.
+    let coeffs = [a, b, c, d, e];
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+    let shift = -0.25 * b;
+    let b_sq = b * b;
+    let p = c - 0.375 * b_sq;
+    let q = d - 0.5 * b * c + 0.125 * b_sq * b;
+    let r = e - 0.25 * b * d + b_sq * c / 16.0 - 3.0 * b_sq * b_sq / 256.0;
+
+    let mut roots = Roots::new();
+    let mut push_quadratic = |b: f64, c: f64| {
+        for y in solve_monic_quadratic(b, c).iter().flatten() {
+            roots.push(y + shift);
+        }
+    };
+
+    // The largest root m of the resolvent cubic splits the quartic into the two quadratics
+    // (y^2 + p / 2 + m)^2 = 2m * (y - q / (4m))^2. It's positive unless q is 0.
+    let m = solve_cubic(1.0, p, 0.25 * p * p - r, -0.125 * q * q)
+        .as_slice()
+        .last()
+        .copied()
+        .unwrap_or(0.0);
+
+    if m <= 0.0 || q.abs() <= 1e-14 * m.max(p.abs()).max(r.abs().sqrt()).max(1.0).powf(1.5) {
+        // A biquadratic in y^2.
+        for z in solve_monic_quadratic(p, r).iter().flatten() {
+            if *z >= 0.0 {
+                roots.push(shift - z.sqrt());
+                roots.push(shift + z.sqrt());
+            }
+        }
+    } else {
+        let s = (2.0 * m).sqrt();
+        let t = q / (2.0 * s);
+        push_quadratic(-s, 0.5 * p + m + t);
+        push_quadratic(s, 0.5 * p + m - t);
+    }
+
+    for x in &mut roots.roots[..roots.len] {
+        *x = polish(&coeffs, *x);
+    }
+    roots.sort();
+    roots
+}
+
+// Returns the roots of x^2 + b * x + c, treating a discriminant that's negative only by
+// rounding error as a double root.
+fn solve_monic_quadratic(b: f64, c: f64) -> [Option<f64>; 2] {
+    let mut discrm = b * b - 4.0 * c;
+    if discrm < 0.0 {
+        if discrm < -1e-12 * (b * b + 4.0 * c.abs()) {
+            return [None, None];
+        }
+        discrm = 0.0;
+    }
+
+    let q = if b < 0.0 { -0.5 * (b - discrm.sqrt()) } else { -0.5 * (b + discrm.sqrt()) };
+    if q == 0.0 {
+        return [Some(0.0), Some(0.0)];
+    }
+    [Some(q), Some(c / q)]
+}
+
+// Evaluates the polynomial with the coefficients |coeffs|, highest degree first, and its
+// derivative at |x|.
+fn evaluate(coeffs: &[f64], x: f64) -> (f64, f64) {
+    coeffs.iter().fold((0.0, 0.0), |(f, df), &c| (f * x + c, df * x + f))
+}
+
+// Refines the root |x| with Newton's method for as long as that improves it.
+fn polish(coeffs: &[f64], mut x: f64) -> f64 {
+    let (mut f, mut df) = evaluate(coeffs, x);
+    for _ in 0..8 {
+        if f == 0.0 || df == 0.0 {
+            break;
+        }
+        let next = x - f / df;
+        let (next_f, next_df) = evaluate(coeffs, next);
+        if next_f.abs() >= f.abs() {
+            break;
+        }
+        x = next;
+        f = next_f;
+        df = next_df;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(roots: Roots, expected: &[f64], tolerance: f64) {
+        assert_eq!(roots.as_slice().len(), expected.len(), "{:?} vs {:?}", roots, expected);
+        for (x, e) in roots.as_slice().iter().zip(expected) {
+            assert!((x - e).abs() <= tolerance * e.abs().max(1.0), "{:?} vs {:?}", roots,
+                    expected);
+        }
+    }
+
+    // The coefficients of the monic polynomial with the given roots, highest degree first.
+    fn from_roots(roots: &[f64]) -> Vec<f64> {
+        roots.iter().fold(vec![1.0], |coeffs, &root| {
+            let mut next = coeffs.clone();
+            next.push(0.0);
+            for (i, c) in coeffs.iter().enumerate() {
+                next[i + 1] -= root * c;
+            }
+            next
+        })
+    }
+
+    fn quartic(roots: &[f64]) -> Roots {
+        let c = from_roots(roots);
+        solve_quartic(c[0], c[1], c[2], c[3], c[4])
+    }
+
+    #[test]
+    fn quadratic() {
+        assert_eq!(solve_quadratic(1.0, -3.0, 2.0), Some((1.0, 2.0)));
+        assert_eq!(solve_quadratic(-1.0, 3.0, -2.0), Some((1.0, 2.0)));
+        assert_eq!(solve_quadratic(0.0, 2.0, -4.0), Some((2.0, 2.0)));
+        assert_eq!(solve_quadratic(1.0, 0.0, 1.0), None);
+        assert_eq!(solve_quadratic(0.0, 0.0, 1.0), None);
+
+        // The small root is accurate even when b^2 >> 4ac.
+        let (x0, _) = solve_quadratic(1.0, 1e8, 1.0).unwrap();
+        assert!((x0 / -1e8 - 1.0).abs() < 1e-12);
+        let (_, x1) = solve_quadratic(1.0, -1e8, 1.0).unwrap();
+        assert!((x1 / 1e8 - 1.0).abs() < 1e-12);
+        let (x0, x1) = solve_quadratic(1.0, 1e8, 1.0).unwrap();
+        assert!((x0 / -1e8 - 1.0).abs() < 1e-12 && (x1 / -1e-8 - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn cubic() {
+        assert_roots(solve_cubic(1.0, -6.0, 11.0, -6.0), &[1.0, 2.0, 3.0], 1e-12);
+        assert_roots(solve_cubic(2.0, 0.0, 0.0, -16.0), &[2.0], 1e-12);
+        assert_roots(solve_cubic(1.0, -3.0, 3.0, -1.0), &[1.0], 1e-12);
+        assert_roots(solve_cubic(0.0, 1.0, -3.0, 2.0), &[1.0, 2.0], 1e-12);
+        // Roots of very different sizes.
+        assert_roots(solve_cubic(1.0, -(1e6 + 1e-3 + 1.0), 1e6 * 1e-3 + 1e6 + 1e-3, -1e3),
+                     &[1e-3, 1.0, 1e6], 1e-9);
+    }
+
+    #[test]
+    fn quartic_roots() {
+        assert_roots(quartic(&[1.0, 2.0, 3.0, 4.0]), &[1.0, 2.0, 3.0, 4.0], 1e-10);
+        assert_roots(quartic(&[-5.0, -0.5, 0.25, 7.0]), &[-5.0, -0.5, 0.25, 7.0], 1e-10);
+        // Biquadratic.
+        assert_roots(quartic(&[-2.0, -1.0, 1.0, 2.0]), &[-2.0, -1.0, 1.0, 2.0], 1e-10);
+        // Scaled, and with a leading coefficient of 0.
+        assert_roots(solve_quartic(3.0, -30.0, 105.0, -150.0, 72.0), &[1.0, 2.0, 3.0, 4.0],
+                     1e-10);
+        assert_roots(solve_quartic(0.0, 1.0, -6.0, 11.0, -6.0), &[1.0, 2.0, 3.0], 1e-12);
+
+        // x^4 + 1 and (x^2 + 1) * (x^2 + 4) have no real roots.
+        assert_roots(solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0), &[], 0.0);
+        assert_roots(solve_quartic(1.0, 0.0, 5.0, 0.0, 4.0), &[], 0.0);
+        // (x - 1) * (x - 2) * (x^2 + 1) has two.
+        assert_roots(solve_quartic(1.0, -3.0, 3.0, -3.0, 2.0), &[1.0, 2.0], 1e-10);
+    }
+
+    #[test]
+    fn quartic_ill_conditioned() {
+        // A grazing ray gives double roots, which shouldn't be lost to rounding.
+        let roots = quartic(&[1.0, 1.0, 3.0, 3.0]);
+        assert_roots(roots, &[1.0, 1.0, 3.0, 3.0], 1e-6);
+        let roots = quartic(&[-2.0, 0.7, 0.7, 5.0]);
+        assert_roots(roots, &[-2.0, 0.7, 0.7, 5.0], 1e-6);
+
+        // Roots that are close together or far apart.
+        assert_roots(quartic(&[1.0, 1.0 + 1e-4, 2.0, 3.0]), &[1.0, 1.0 + 1e-4, 2.0, 3.0], 1e-8);
+        assert_roots(quartic(&[1e-3, 1.0, 1e2, 1e4]), &[1e-3, 1.0, 1e2, 1e4], 1e-8);
+
+        // A large offset, like a ray starting far away from a torus.
+        let far = [1e4 - 2.0, 1e4 - 1.0, 1e4 + 1.0, 1e4 + 2.0];
+        assert_roots(quartic(&far), &far, 1e-8);
+
+        // Random roots, checked by the size of the polynomial at them.
+        let mut rng = crate::sampling::Rng::new(7);
+        for _ in 0..1000 {
+            let mut expected = (0..4).map(|_| 20.0 * rng.uniform_f32() as f64 - 10.0)
+                .collect::<Vec<_>>();
+            expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let coeffs = from_roots(&expected);
+            let roots = solve_quartic(coeffs[0], coeffs[1], coeffs[2], coeffs[3], coeffs[4]);
+            assert_eq!(roots.as_slice().len(), 4, "{:?}", expected);
+            for (x, e) in roots.as_slice().iter().zip(&expected) {
+                assert!((x - e).abs() < 1e-5, "{:?} vs {:?}", roots, expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,10 @@
+use std::sync::Arc;
+
+use crate::material::Material;
+use crate::shapes::Shape;
+
+pub struct Object {
+    // Shared with the area light when the material is emissive.
+    pub shape: Arc<dyn Shape>,
+    pub material: Material,
+}
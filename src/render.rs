@@ -0,0 +1,170 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::film::{Film, FilmTile, PixelStats, sample_counts};
+use crate::filters::Filter;
+use crate::rgb::Rgb;
+
+// Width and height of the square tiles that the image is split into.
+pub const TILE_SIZE: usize = 16;
+
+// Pixels with a mean luminance below this are judged by their error relative to it.
+const ADAPTIVE_MIN_MEAN: f32 = 0.01;
+
+// Settings for taking samples in a pixel only until its noise is low enough.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AdaptiveSampling {
+    // Every pixel takes at least this many samples, and is checked again after every further
+    // this many.
+    pub min_samples: usize,
+    // Sampling stops once the standard error of the pixel's mean luminance, relative to the
+    // mean, is below this.
+    pub threshold: f32,
+}
+
+impl AdaptiveSampling {
+    // Returns whether a pixel whose samples so far have |stats| can stop.
+    pub fn converged(&self, stats: &PixelStats) -> bool {
+        let n = stats.count() as usize;
+        n >= self.min_samples && n.is_multiple_of(self.min_samples) &&
+            stats.relative_error(ADAPTIVE_MIN_MEAN) < self.threshold
+    }
+}
+
+// Renders a |width| x |height| film by splitting it into tiles that are handed out to
+// |num_threads| worker threads. |f| takes the samples for the pixel at column x and row y and adds
+// them to the tile, which weights them with |filter|. It's called exactly once per pixel.
+//
+// Returns the film and the number of samples taken in each pixel. Since each pixel's samples only
+// depend on its coordinates and the tiles are combined in a fixed order, the result doesn't
+// depend on the number of threads or on the order in which the tiles are rendered.
+pub fn render_tiles<F>(width: usize, height: usize, filter: &dyn Filter, num_threads: usize,
+                       f: F) -> (Film, Vec<u32>)
+    where F: Fn(usize, usize, &mut FilmTile) + Sync {
+
+    let tiles_x = width.div_ceil(TILE_SIZE);
+    let tiles_y = height.div_ceil(TILE_SIZE);
+    let num_tiles = tiles_x * tiles_y;
+
+    let next_tile = AtomicUsize::new(0);
+    let tiles = Mutex::new((0..num_tiles).map(|_| None).collect::<Vec<_>>());
+
+    thread::scope(|s| {
+        for _ in 0..num_threads.max(1) {
+            s.spawn(|| {
+                loop {
+                    let tile = next_tile.fetch_add(1, Ordering::Relaxed);
+                    if tile >= num_tiles {
+                        break;
+                    }
+
+                    let x0 = (tile % tiles_x) * TILE_SIZE;
+                    let y0 = (tile / tiles_x) * TILE_SIZE;
+                    let x1 = (x0 + TILE_SIZE).min(width);
+                    let y1 = (y0 + TILE_SIZE).min(height);
+
+                    let mut film_tile = FilmTile::new(x0, y0, x1, y1, filter.radius(), width,
+                                                      height);
+                    for y in y0..y1 {
+                        for x in x0..x1 {
+                            f(x, y, &mut film_tile);
+                        }
+                    }
+
+                    tiles.lock().unwrap()[tile] = Some(film_tile);
+                }
+            });
+        }
+    });
+
+    let tiles = tiles.into_inner().unwrap().into_iter().map(Option::unwrap).collect::<Vec<_>>();
+    (Film::from_tiles(width, height, &tiles), sample_counts(width, height, &tiles))
+}
+
+// Visualizes per-pixel sample counts, with the fewest samples in blue and then cyan, green,
+// yellow and red as the counts go up to the largest.
+pub fn sample_heatmap(width: usize, height: usize, counts: &[u32]) -> Film {
+    const COLORS: [(f32, f32, f32); 5] =
+        [(0.0, 0.0, 1.0), (0.0, 1.0, 1.0), (0.0, 1.0, 0.0), (1.0, 1.0, 0.0), (1.0, 0.0, 0.0)];
+
+    let min = counts.iter().copied().min().unwrap_or(0);
+    let max = counts.iter().copied().max().unwrap_or(0);
+    let pixels = counts.iter().map(|&count| {
+        let t = if max > min { (count - min) as f32 / (max - min) as f32 } else { 0.0 };
+        let pos = t * (COLORS.len() - 1) as f32;
+        let i = (pos as usize).min(COLORS.len() - 2);
+        let frac = pos - i as f32;
+        let (a, b) = (COLORS[i], COLORS[i + 1]);
+        Rgb::new(a.0 + frac * (b.0 - a.0), a.1 + frac * (b.1 - a.1), a.2 + frac * (b.2 - a.2))
+    }).collect();
+    Film::from_pixels(width, height, pixels)
+}
+
+// Returns the number of threads to render with when the user doesn't specify one.
+pub fn default_num_threads() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::{BoxFilter, TriangleFilter};
+
+    #[test]
+    fn render_tiles_deterministic() {
+        // Sizes that aren't multiples of the tile size exercise the partial tiles at the edges.
+        let (width, height) = (3 * TILE_SIZE + 5, 2 * TILE_SIZE + 1);
+        let render = |filter: &dyn Filter, num_threads: usize| {
+            render_tiles(width, height, filter, num_threads, |x, y, tile| {
+                for &(dx, dy) in &[(0.1, 0.2), (0.5, 0.5), (0.9, 0.7)] {
+                    let p = (x as f32 + dx, y as f32 + dy);
+                    tile.add_sample(p, Rgb::new(x as f32, y as f32, dx), filter);
+                }
+            })
+        };
+
+        let filter = BoxFilter { radius: (0.5, 0.5) };
+        let (film, counts) = render(&filter, 1);
+        assert!(counts.iter().all(|&n| n == 3));
+        let expected = film.pixels;
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = expected[y * width + x];
+                assert_eq!((pixel.r, pixel.g), (x as f32, y as f32));
+            }
+        }
+        for num_threads in &[2, 3, 8] {
+            assert_eq!(render(&filter, *num_threads).0.pixels, expected);
+        }
+
+        // Wide filters splat samples into the neighbouring tiles.
+        let filter = TriangleFilter { radius: (2.0, 2.0) };
+        let expected = render(&filter, 1).0.pixels;
+        for num_threads in &[2, 3, 8] {
+            assert_eq!(render(&filter, *num_threads).0.pixels, expected);
+        }
+    }
+
+    #[test]
+    fn adaptive_sampling() {
+        let adaptive = AdaptiveSampling { min_samples: 4, threshold: 0.1 };
+        let mut flat = PixelStats::default();
+        let mut noisy = PixelStats::default();
+        for i in 0..8 {
+            flat.add(1.0);
+            noisy.add((i % 2) as f32 * 10.0);
+            // Only checked after every 4 samples.
+            assert_eq!(adaptive.converged(&flat), i == 3 || i == 7);
+            assert!(!adaptive.converged(&noisy));
+        }
+    }
+
+    #[test]
+    fn heatmap() {
+        let film = sample_heatmap(3, 1, &[4, 10, 16]);
+        assert_eq!(film.pixels, vec![Rgb::new(0.0, 0.0, 1.0), Rgb::new(0.0, 1.0, 0.0),
+                                     Rgb::new(1.0, 0.0, 0.0)]);
+        assert_eq!(sample_heatmap(1, 1, &[5]).pixels, vec![Rgb::new(0.0, 0.0, 1.0)]);
+    }
+}
@@ -1,6 +1,6 @@
-use std::ops::{Add, AddAssign, Mul, MulAssign};
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Rgb {
     pub r: f32,
     pub g: f32,
@@ -12,6 +12,19 @@ impl Rgb {
         Rgb { r, g, b, }
     }
 
+    pub fn is_black(&self) -> bool {
+        self.r == 0.0 && self.g == 0.0 && self.b == 0.0
+    }
+
+    pub fn max_component(&self) -> f32 {
+        self.r.max(self.g).max(self.b)
+    }
+
+    // Returns the luminance of the color, assuming linear sRGB primaries.
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
     // Truncates all components so that their values are within the range [0.0, 1.0].
     pub fn clamp_to_unit(self) -> Self {
         Rgb { 
@@ -81,8 +94,29 @@ impl Mul<Rgb> for f32 {
 impl MulAssign for Rgb {
     fn mul_assign(&mut self, other: Self) {
         self.r *= other.r;
-        self.g *= other.b;
+        self.g *= other.g;
         self.b *= other.b;
     }
 }
 
+
+impl MulAssign<f32> for Rgb {
+    fn mul_assign(&mut self, rhs: f32) {
+        self.r *= rhs;
+        self.g *= rhs;
+        self.b *= rhs;
+    }
+}
+
+impl Div<f32> for Rgb {
+    type Output = Self;
+
+    fn div(self, rhs: f32) -> Self::Output {
+        assert!(rhs != 0.0);
+        Rgb {
+            r: self.r / rhs,
+            g: self.g / rhs,
+            b: self.b / rhs,
+        }
+    }
+}
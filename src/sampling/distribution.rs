@@ -0,0 +1,145 @@
+// Piecewise-constant distributions for importance sampling tabulated functions, such as the
+// brightness of an environment map (Pharr et al., "Physically Based Rendering", section 13.6.7).
+
+// A distribution over [0, 1) that's proportional to a step function with equally sized steps.
+pub struct Distribution1D {
+    func: Vec<f32>,
+    // cdf[i] is the integral of the normalized function over [0, i / n).
+    cdf: Vec<f32>,
+    func_int: f32,
+}
+
+impl Distribution1D {
+    // |func| holds the non-negative values of the steps. If they're all 0, the distribution is
+    // uniform.
+    pub fn new(func: &[f32]) -> Self {
+        let n = func.len();
+        assert!(n > 0);
+
+        let mut cdf = Vec::with_capacity(n + 1);
+        cdf.push(0.0);
+        for i in 0..n {
+            cdf.push(cdf[i] + func[i] / n as f32);
+        }
+
+        let func_int = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if func_int == 0.0 { i as f32 / n as f32 } else { *c / func_int };
+        }
+
+        Distribution1D { func: func.to_vec(), cdf, func_int }
+    }
+
+    // Returns the integral of the function over [0, 1).
+    pub fn integral(&self) -> f32 {
+        self.func_int
+    }
+
+    // Maps |u| to a point x in [0, 1). Returns x, its density and the index of its step.
+    pub fn sample_continuous(&self, u: f32) -> (f32, f32, usize) {
+        let n = self.func.len();
+        // The last step whose cdf at the start is at most u.
+        let offset = self.cdf.partition_point(|&c| c <= u).clamp(1, n) - 1;
+
+        let mut du = u - self.cdf[offset];
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        if width > 0.0 {
+            du /= width;
+        }
+
+        let x = ((offset as f32 + du) / n as f32).min(1.0 - f32::EPSILON / 2.0);
+        (x, self.pdf_at(offset), offset)
+    }
+
+    // Returns the density of the point x in [0, 1).
+    pub fn pdf(&self, x: f32) -> f32 {
+        let n = self.func.len();
+        self.pdf_at(((x * n as f32) as usize).min(n - 1))
+    }
+
+    fn pdf_at(&self, offset: usize) -> f32 {
+        if self.func_int == 0.0 { 1.0 } else { self.func[offset] / self.func_int }
+    }
+}
+
+// A distribution over [0, 1)^2 that's proportional to a function tabulated on an |nu| x |nv|
+// grid. Samples v from the marginal distribution and then u from the conditional distribution
+// of the row that v falls in.
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    // |func| is in row-major order, with |nu| values in each of the |nv| rows.
+    pub fn new(func: &[f32], nu: usize, nv: usize) -> Self {
+        assert_eq!(func.len(), nu * nv);
+
+        let conditional = func.chunks(nu).map(Distribution1D::new).collect::<Vec<_>>();
+        let marginal_func = conditional.iter().map(|d| d.integral()).collect::<Vec<_>>();
+        Distribution2D { conditional, marginal: Distribution1D::new(&marginal_func) }
+    }
+
+    // Maps |u| to a point in [0, 1)^2 and returns it along with its density.
+    pub fn sample_continuous(&self, u: (f32, f32)) -> ((f32, f32), f32) {
+        let (v, pdf_v, row) = self.marginal.sample_continuous(u.1);
+        let (u, pdf_u, _) = self.conditional[row].sample_continuous(u.0);
+        ((u, v), pdf_u * pdf_v)
+    }
+
+    pub fn pdf(&self, p: (f32, f32)) -> f32 {
+        let nv = self.conditional.len();
+        let row = ((p.1 * nv as f32) as usize).min(nv - 1);
+        self.conditional[row].pdf(p.0) * self.marginal.pdf(p.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::Rng;
+
+    #[test]
+    fn distribution_1d() {
+        let d = Distribution1D::new(&[1.0, 3.0, 0.0, 4.0]);
+        assert_eq!(d.integral(), 2.0);
+
+        // A quarter of the mass is in [0.25, 0.5) at density 1.5.
+        let (x, pdf, offset) = d.sample_continuous(0.125 + 0.1875);
+        assert!((x - 0.375).abs() < 1e-6);
+        assert_eq!((pdf, offset), (1.5, 1));
+        assert_eq!(d.pdf(0.6), 0.0);
+
+        // Steps with no mass are never sampled.
+        let mut rng = Rng::new(0);
+        for _ in 0..1000 {
+            let (x, pdf, _) = d.sample_continuous(rng.uniform_f32());
+            assert!(!(0.5..0.75).contains(&x));
+            assert_eq!(pdf, d.pdf(x));
+        }
+
+        let uniform = Distribution1D::new(&[0.0, 0.0]);
+        assert_eq!(uniform.sample_continuous(0.3), (0.3, 1.0, 0));
+    }
+
+    #[test]
+    fn distribution_2d() {
+        let func = [0.0, 1.0,
+                    2.0, 5.0];
+        let d = Distribution2D::new(&func, 2, 2);
+
+        let mut rng = Rng::new(0);
+        let mut counts = [0; 4];
+        let n = 40000;
+        for _ in 0..n {
+            let ((u, v), pdf) = d.sample_continuous(rng.uniform_2d());
+            assert!((pdf - d.pdf((u, v))).abs() < 1e-5);
+            counts[(2.0 * v) as usize * 2 + (2.0 * u) as usize] += 1;
+        }
+
+        // Each cell is sampled in proportion to its value.
+        for (count, f) in counts.iter().zip(&func) {
+            assert!((*count as f32 / n as f32 - f / 8.0).abs() < 0.01);
+        }
+    }
+}
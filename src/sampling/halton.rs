@@ -0,0 +1,79 @@
+use crate::sampling::low_discrepancy::{PRIMES, hash, owen_scrambled_radical_inverse};
+use crate::sampling::Sampler;
+
+// The Halton sequence, whose dimension d is the radical inverse of the sample index in the dth
+// prime base. Each pixel and dimension gets its own Owen scrambling, which removes the
+// correlation between the higher dimensions that the plain sequence suffers from. Dimensions
+// past the table of primes reuse its bases with different scrambling.
+#[derive(Clone)]
+pub struct HaltonSampler {
+    spp: usize,
+    seed: u64,
+    pixel: (usize, usize),
+    index: usize,
+    dimension: usize,
+}
+
+impl HaltonSampler {
+    pub fn new(spp: usize, seed: u64) -> Self {
+        HaltonSampler { spp, seed, pixel: (0, 0), index: 0, dimension: 0 }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn samples_per_pixel(&self) -> usize {
+        self.spp
+    }
+
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let seed = hash(&[self.pixel.0 as u64, self.pixel.1 as u64, self.dimension as u64,
+                          self.seed]);
+        let base = PRIMES[self.dimension % PRIMES.len()];
+        self.dimension += 1;
+        owen_scrambled_radical_inverse(base, self.index as u64, seed)
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        let u0 = self.get_1d();
+        (u0, self.get_1d())
+    }
+
+    fn clone_sampler(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stratified_in_each_dimension() {
+        // The first 2^k samples of the base 2 dimension and 3^k of the base 3 one each fall into
+        // separate intervals.
+        let mut sampler = HaltonSampler::new(9, 0);
+        let mut seen = (vec![false; 8], vec![false; 9]);
+        for index in 0..9 {
+            sampler.start_pixel_sample((3, 4), index);
+            let (u0, u1) = sampler.get_2d();
+            if index < 8 {
+                assert!(!seen.0[(u0 * 8.0) as usize]);
+                seen.0[(u0 * 8.0) as usize] = true;
+            }
+            assert!(!seen.1[(u1 * 9.0) as usize]);
+            seen.1[(u1 * 9.0) as usize] = true;
+        }
+
+        // Other pixels get different points.
+        sampler.start_pixel_sample((3, 5), 0);
+        let other = sampler.get_2d();
+        sampler.start_pixel_sample((3, 4), 0);
+        assert_ne!(sampler.get_2d(), other);
+    }
+}
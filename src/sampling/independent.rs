@@ -0,0 +1,39 @@
+use crate::sampling::low_discrepancy::hash;
+use crate::sampling::{Rng, Sampler};
+
+// Uniform random samples, independent of each other. Converges the slowest, but works for any
+// number of samples and dimensions.
+#[derive(Clone)]
+pub struct IndependentSampler {
+    spp: usize,
+    seed: u64,
+    rng: Rng,
+}
+
+impl IndependentSampler {
+    pub fn new(spp: usize, seed: u64) -> Self {
+        IndependentSampler { spp, seed, rng: Rng::new(seed) }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn samples_per_pixel(&self) -> usize {
+        self.spp
+    }
+
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.rng = Rng::new(hash(&[pixel.0 as u64, pixel.1 as u64, index as u64, self.seed]));
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        self.rng.uniform_f32()
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        self.rng.uniform_2d()
+    }
+
+    fn clone_sampler(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,204 @@
+// Building blocks for low-discrepancy samplers: hashing, random permutations, and the
+// scrambled radical inverse and Sobol' sequences.
+
+use crate::sampling::rng::ONE_MINUS_EPSILON;
+
+// Scrambles the bits of |v| (Stafford's "Mix13" variant of the MurmurHash3 finalizer).
+pub fn mix_bits(mut v: u64) -> u64 {
+    v ^= v >> 31;
+    v = v.wrapping_mul(0x7fb5_d329_728e_a185);
+    v ^= v >> 27;
+    v = v.wrapping_mul(0x81da_def4_bc2d_d44d);
+    v ^= v >> 33;
+    v
+}
+
+// Hashes a sequence of values into one.
+pub fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0, |h, &v| mix_bits(h ^ v.wrapping_add(0x9e37_79b9_7f4a_7c15)))
+}
+
+// Returns element |i| of a random permutation of 0..|len| selected by |seed| (Kensler,
+// "Correlated Multi-Jittered Sampling", 2013), without building the permutation.
+pub fn permutation_element(mut i: u32, len: u32, seed: u32) -> u32 {
+    let p = seed;
+    let mut w = len - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    // Permute within the next power of two and retry until the result is in range.
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < len {
+            break;
+        }
+    }
+    (i.wrapping_add(p)) % len
+}
+
+// The bases of the Halton sequence's first dimensions.
+pub const PRIMES: [u32; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89,
+    97, 101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191,
+    193, 197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293,
+    307, 311,
+];
+
+// Mirrors the digits of |a| in |base| around the radix point, permuting each digit with a
+// permutation selected by |seed| and the digits before it. This is Owen scrambling: every
+// subinterval gets its own permutation, so the result is uniformly distributed while keeping the
+// stratification of the unscrambled sequence.
+pub fn owen_scrambled_radical_inverse(base: u32, mut a: u64, seed: u64) -> f32 {
+    let base64 = base as u64;
+    let inv_base = 1.0 / base as f64;
+    let mut reversed_digits = 0u64;
+    let mut inv_base_m = 1.0f64;
+
+    // Keep going past the digits of |a|, whose zeros get scrambled too, until the digits no
+    // longer affect an f32.
+    while 1.0 - (base - 1) as f32 * (inv_base_m as f32) < 1.0 {
+        let next = a / base64;
+        let digit = (a - next * base64) as u32;
+        let digit_seed = mix_bits(seed ^ reversed_digits) as u32;
+        let digit = permutation_element(digit, base, digit_seed);
+        reversed_digits = reversed_digits * base64 + digit as u64;
+        inv_base_m *= inv_base;
+        a = next;
+    }
+
+    ((reversed_digits as f64 * inv_base_m) as f32).min(ONE_MINUS_EPSILON)
+}
+
+// Returns dimension |dim|, which is 0 or 1, of point |a| of the Sobol' sequence as 32 bits of
+// fixed point. The two dimensions form a (0, 2)-sequence: every power of two of consecutive
+// points, starting at a multiple of it, is stratified over every set of equal rectangles of that
+// many cells.
+pub fn sobol_2d(mut a: u32, dim: usize) -> u32 {
+    // The first dimension's generator matrix is the identity, which gives the van der Corput
+    // sequence. The second one's column i is row i of Pascal's triangle mod 2.
+    let mut v = 1u32 << 31;
+    let mut x = 0;
+    while a != 0 {
+        if a & 1 != 0 {
+            x ^= v;
+        }
+        a >>= 1;
+        v = if dim == 0 { v >> 1 } else { v ^ (v >> 1) };
+    }
+    x
+}
+
+// Owen scrambles the 32 bits of fixed point |v|: each bit is flipped based on a hash of |seed|
+// and the bits above it.
+pub fn owen_scramble(mut v: u32, seed: u64) -> u32 {
+    if seed & 1 != 0 {
+        v ^= 1 << 31;
+    }
+    for b in 1..32 {
+        let mask = !0u32 << (32 - b);
+        if mix_bits((v & mask) as u64 ^ seed) & (1 << b) != 0 {
+            v ^= 1 << (31 - b);
+        }
+    }
+    v
+}
+
+// Converts 32 bits of fixed point to a float in [0, 1).
+pub fn fixed_to_f32(v: u32) -> f32 {
+    (v as f32 * (1.0 / 4_294_967_296.0)).min(ONE_MINUS_EPSILON)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permutation() {
+        for &len in &[1, 2, 5, 16, 100] {
+            for seed in 0..4 {
+                let mut seen = vec![false; len as usize];
+                for i in 0..len {
+                    seen[permutation_element(i, len, seed * 7919) as usize] = true;
+                }
+                assert!(seen.iter().all(|&s| s));
+            }
+        }
+    }
+
+    // Checks that |points| has one point in each cell of every |cols| x |rows| grid with
+    // |cols| * |rows| = |points.len()|.
+    fn is_net(points: &[(f32, f32)]) -> bool {
+        let n = points.len();
+        let mut cols = 1;
+        while cols <= n {
+            let rows = n / cols;
+            let mut seen = vec![false; n];
+            for &(x, y) in points {
+                let cell = (y * rows as f32) as usize * cols + (x * cols as f32) as usize;
+                if seen[cell] {
+                    return false;
+                }
+                seen[cell] = true;
+            }
+            cols *= 2;
+        }
+        true
+    }
+
+    #[test]
+    fn sobol_stratification() {
+        let points = |seed: Option<(u64, u64)>| {
+            (0..64).map(|a| {
+                let (x, y) = (sobol_2d(a, 0), sobol_2d(a, 1));
+                let (x, y) = match seed {
+                    Some((s0, s1)) => (owen_scramble(x, s0), owen_scramble(y, s1)),
+                    None => (x, y),
+                };
+                (fixed_to_f32(x), fixed_to_f32(y))
+            }).collect::<Vec<_>>()
+        };
+
+        assert!(is_net(&points(None)));
+        // Scrambling randomizes the points but keeps them stratified.
+        let scrambled = points(Some((12345, 678)));
+        assert!(is_net(&scrambled));
+        assert_ne!(scrambled, points(None));
+    }
+
+    #[test]
+    fn radical_inverse() {
+        // Scrambled digits still stratify: the first b^k points of each base fall into
+        // separate intervals of width 1/b^k.
+        for &base in &[2, 3, 5] {
+            let n = base * base * base;
+            let mut seen = vec![false; n as usize];
+            for a in 0..n {
+                let v = owen_scrambled_radical_inverse(base, a as u64, 42);
+                assert!((0.0..1.0).contains(&v));
+                let cell = (v * n as f32) as usize;
+                assert!(!seen[cell]);
+                seen[cell] = true;
+            }
+        }
+    }
+}
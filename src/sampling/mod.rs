@@ -0,0 +1,16 @@
+pub mod distribution;
+pub mod halton;
+pub mod independent;
+pub mod low_discrepancy;
+pub mod rng;
+pub mod sampler;
+pub mod sobol;
+pub mod stratified;
+pub mod warp;
+
+pub use self::halton::HaltonSampler;
+pub use self::independent::IndependentSampler;
+pub use self::rng::Rng;
+pub use self::sampler::Sampler;
+pub use self::sobol::SobolSampler;
+pub use self::stratified::StratifiedSampler;
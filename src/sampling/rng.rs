@@ -0,0 +1,78 @@
+// The PCG32 random number generator (O'Neill, "PCG: A Family of Simple Fast Space-Efficient
+// Statistically Good Algorithms for Random Number Generation").
+//
+// Renders seed a separate sequence per pixel so that the output is reproducible no matter how
+// the pixels are distributed between threads.
+
+const DEFAULT_STATE: u64 = 0x853c_49e6_748f_ea9b;
+const MULT: u64 = 0x5851_f42d_4c95_7f2d;
+
+// The largest f32 that is less than 1.
+pub const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON / 2.0;
+
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+    inc: u64,
+}
+
+impl Rng {
+    // Creates a generator for the sequence with index |seq|. Different sequences are
+    // independent of each other.
+    pub fn new(seq: u64) -> Self {
+        let mut rng = Rng { state: 0, inc: (seq << 1) | 1 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(DEFAULT_STATE);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULT).wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    // Returns a uniformly distributed value in [0, 1).
+    pub fn uniform_f32(&mut self) -> f32 {
+        (self.next_u32() as f32 * (1.0 / 4_294_967_296.0)).min(ONE_MINUS_EPSILON)
+    }
+
+    pub fn uniform_2d(&mut self) -> (f32, f32) {
+        let u0 = self.uniform_f32();
+        (u0, self.uniform_f32())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deterministic() {
+        let mut rng1 = Rng::new(7);
+        let mut rng2 = Rng::new(7);
+        let mut rng3 = Rng::new(8);
+
+        let seq1 = (0..16).map(|_| rng1.next_u32()).collect::<Vec<_>>();
+        let seq2 = (0..16).map(|_| rng2.next_u32()).collect::<Vec<_>>();
+        let seq3 = (0..16).map(|_| rng3.next_u32()).collect::<Vec<_>>();
+
+        assert_eq!(seq1, seq2);
+        assert_ne!(seq1, seq3);
+    }
+
+    #[test]
+    fn uniform_range() {
+        let mut rng = Rng::new(0);
+        let mut sum = 0.0;
+        for _ in 0..10000 {
+            let u = rng.uniform_f32();
+            assert!((0.0..1.0).contains(&u));
+            sum += u;
+        }
+        assert!((sum / 10000.0 - 0.5).abs() < 0.02);
+    }
+}
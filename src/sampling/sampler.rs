@@ -0,0 +1,19 @@
+// Samplers supply the random numbers for each sample of a pixel as a sequence of 1D and 2D
+// dimensions, in [0, 1). The values only depend on the pixel, the index of the sample and the
+// order in which dimensions are requested, so renders are reproducible no matter how the pixels
+// are distributed between threads.
+pub trait Sampler: Send + Sync {
+    fn samples_per_pixel(&self) -> usize;
+
+    // Starts the |index|th sample of the pixel at column |pixel.0| and row |pixel.1|, from its
+    // first dimension.
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize);
+
+    fn get_1d(&mut self) -> f32;
+
+    fn get_2d(&mut self) -> (f32, f32);
+
+    // Returns a sampler in its initial state that generates the same samples, so that each
+    // render thread can have its own.
+    fn clone_sampler(&self) -> Box<dyn Sampler>;
+}
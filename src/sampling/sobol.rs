@@ -0,0 +1,80 @@
+use crate::sampling::low_discrepancy::{fixed_to_f32, hash, owen_scramble, permutation_element,
+                                       sobol_2d};
+use crate::sampling::Sampler;
+
+// Owen-scrambled Sobol' points. Rather than using the higher dimensions of the Sobol' sequence,
+// every 1D or 2D request takes its own scrambling of the first two, with the samples visited in
+// a random order, as in pbrt-v4's padded Sobol' sampler. The 2D points of each request are then
+// well stratified on their own. Works best with a power of two samples per pixel.
+#[derive(Clone)]
+pub struct SobolSampler {
+    spp: usize,
+    seed: u64,
+    pixel: (usize, usize),
+    index: usize,
+    dimension: u64,
+}
+
+impl SobolSampler {
+    pub fn new(spp: usize, seed: u64) -> Self {
+        SobolSampler { spp, seed, pixel: (0, 0), index: 0, dimension: 0 }
+    }
+
+    // Returns the index of the point that the current sample uses in the current dimension and
+    // the seed for scrambling it.
+    fn next_point(&mut self) -> (u32, u64) {
+        let seed = hash(&[self.pixel.0 as u64, self.pixel.1 as u64, self.dimension, self.seed]);
+        self.dimension += 1;
+        let index = permutation_element(self.index as u32 % self.spp as u32, self.spp as u32,
+                                        seed as u32);
+        (index, seed)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn samples_per_pixel(&self) -> usize {
+        self.spp
+    }
+
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let (index, seed) = self.next_point();
+        fixed_to_f32(owen_scramble(sobol_2d(index, 0), seed))
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        let (index, seed) = self.next_point();
+        (fixed_to_f32(owen_scramble(sobol_2d(index, 0), seed)),
+         fixed_to_f32(owen_scramble(sobol_2d(index, 1), seed.rotate_left(32))))
+    }
+
+    fn clone_sampler(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stratified_2d() {
+        // Each 2D dimension of 16 samples has a point in each cell of a 4x4 grid.
+        let mut sampler = SobolSampler::new(16, 0);
+        let mut seen = vec![vec![false; 16]; 3];
+        for index in 0..16 {
+            sampler.start_pixel_sample((1, 2), index);
+            for cells in &mut seen {
+                let (x, y) = sampler.get_2d();
+                let cell = (y * 4.0) as usize * 4 + (x * 4.0) as usize;
+                assert!(!cells[cell]);
+                cells[cell] = true;
+            }
+        }
+    }
+}
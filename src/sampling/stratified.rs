@@ -0,0 +1,113 @@
+use crate::sampling::low_discrepancy::{hash, permutation_element};
+use crate::sampling::rng::ONE_MINUS_EPSILON;
+use crate::sampling::{Rng, Sampler};
+
+// Splits each dimension into as many strata as there are samples per pixel, 2D dimensions into
+// a grid of |x_strata| x |y_strata| cells, and puts one sample in each, at a random position
+// within it if |jitter| is set and at its center otherwise. Every dimension visits the strata in
+// a different random order so that the dimensions aren't correlated.
+#[derive(Clone)]
+pub struct StratifiedSampler {
+    x_strata: usize,
+    y_strata: usize,
+    jitter: bool,
+    seed: u64,
+    pixel: (usize, usize),
+    index: usize,
+    dimension: u64,
+    rng: Rng,
+}
+
+impl StratifiedSampler {
+    pub fn new(x_strata: usize, y_strata: usize, jitter: bool, seed: u64) -> Self {
+        StratifiedSampler {
+            x_strata,
+            y_strata,
+            jitter,
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+            rng: Rng::new(seed),
+        }
+    }
+
+    // Splits |spp| samples into a grid that's as close to square as possible.
+    pub fn with_samples(spp: usize, jitter: bool, seed: u64) -> Self {
+        let mut x_strata = (spp as f32).sqrt() as usize;
+        while !spp.is_multiple_of(x_strata) {
+            x_strata -= 1;
+        }
+        StratifiedSampler::new(x_strata, spp / x_strata, jitter, seed)
+    }
+
+    // Returns which stratum of the current dimension the current sample goes in.
+    fn next_stratum(&mut self) -> usize {
+        let spp = self.samples_per_pixel() as u32;
+        let seed = hash(&[self.pixel.0 as u64, self.pixel.1 as u64, self.dimension, self.seed]);
+        self.dimension += 1;
+        permutation_element(self.index as u32 % spp, spp, seed as u32) as usize
+    }
+
+    fn offset(&mut self) -> f32 {
+        if self.jitter {
+            self.rng.uniform_f32()
+        } else {
+            0.5
+        }
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn samples_per_pixel(&self) -> usize {
+        self.x_strata * self.y_strata
+    }
+
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+        self.rng = Rng::new(hash(&[pixel.0 as u64, pixel.1 as u64, index as u64, self.seed]));
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let stratum = self.next_stratum();
+        let delta = self.offset();
+        ((stratum as f32 + delta) / self.samples_per_pixel() as f32).min(ONE_MINUS_EPSILON)
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        let stratum = self.next_stratum();
+        let (x, y) = (stratum % self.x_strata, stratum / self.x_strata);
+        let (dx, dy) = (self.offset(), self.offset());
+        (((x as f32 + dx) / self.x_strata as f32).min(ONE_MINUS_EPSILON),
+         ((y as f32 + dy) / self.y_strata as f32).min(ONE_MINUS_EPSILON))
+    }
+
+    fn clone_sampler(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_sample_per_stratum() {
+        let mut sampler = StratifiedSampler::with_samples(12, true, 0);
+        assert_eq!((sampler.x_strata, sampler.y_strata), (3, 4));
+
+        let mut seen_1d = [false; 12];
+        let mut seen_2d = [false; 12];
+        for index in 0..12 {
+            sampler.start_pixel_sample((5, 7), index);
+            let u = sampler.get_1d();
+            seen_1d[(u * 12.0) as usize] = true;
+            let (x, y) = sampler.get_2d();
+            seen_2d[(y * 4.0) as usize * 3 + (x * 3.0) as usize] = true;
+        }
+        assert!(seen_1d.iter().all(|&s| s));
+        assert!(seen_2d.iter().all(|&s| s));
+    }
+}
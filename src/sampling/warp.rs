@@ -0,0 +1,147 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+use crate::geometry::Vec3;
+
+// Functions that map uniform samples in [0, 1)^2 to other distributions, along with their pdfs.
+
+// Maps |u| to a point on the unit disk, using Shirley's concentric mapping to keep strata
+// compact.
+pub fn concentric_sample_disk(u: (f32, f32)) -> (f32, f32) {
+    let (ox, oy) = (2.0 * u.0 - 1.0, 2.0 * u.1 - 1.0);
+    if ox == 0.0 && oy == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let (r, theta) = if ox.abs() > oy.abs() {
+        (ox, FRAC_PI_4 * (oy / ox))
+    } else {
+        (oy, FRAC_PI_2 - FRAC_PI_4 * (ox / oy))
+    };
+
+    (r * theta.cos(), r * theta.sin())
+}
+
+// Maps |u| uniformly to a point inside the regular polygon with |n| vertices inscribed in the unit
+// circle, with the first vertex on the +x axis. |n| must be at least 3.
+pub fn uniform_sample_polygon(u: (f32, f32), n: u32) -> (f32, f32) {
+    // Pick one of the n triangles fanning out from the center, then remap the sample to [0, 1)
+    // and sample the triangle uniformly.
+    let k = ((u.0 * n as f32) as u32).min(n - 1);
+    let u0 = u.0 * n as f32 - k as f32;
+
+    let theta0 = 2.0 * PI * k as f32 / n as f32;
+    let theta1 = 2.0 * PI * (k + 1) as f32 / n as f32;
+
+    let su0 = u0.sqrt();
+    let b1 = (1.0 - u.1) * su0;
+    let b2 = u.1 * su0;
+
+    (b1 * theta0.cos() + b2 * theta1.cos(), b1 * theta0.sin() + b2 * theta1.sin())
+}
+
+// Samples a direction in the z > 0 hemisphere with density proportional to cos(theta).
+pub fn cosine_sample_hemisphere(u: (f32, f32)) -> Vec3 {
+    let (x, y) = concentric_sample_disk(u);
+    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+    Vec3::new(x, y, z)
+}
+
+pub fn cosine_hemisphere_pdf(cos_theta: f32) -> f32 {
+    cos_theta / PI
+}
+
+pub fn uniform_sample_hemisphere(u: (f32, f32)) -> Vec3 {
+    let z = u.0;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn uniform_hemisphere_pdf() -> f32 {
+    1.0 / (2.0 * PI)
+}
+
+pub fn uniform_sample_sphere(u: (f32, f32)) -> Vec3 {
+    let z = 1.0 - 2.0 * u.0;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn uniform_sphere_pdf() -> f32 {
+    1.0 / (4.0 * PI)
+}
+
+// Samples a direction uniformly inside the cone around +z whose half-angle has cosine
+// |cos_theta_max|.
+pub fn uniform_sample_cone(u: (f32, f32), cos_theta_max: f32) -> Vec3 {
+    let cos_theta = (1.0 - u.0) + u.0 * cos_theta_max;
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}
+
+pub fn uniform_cone_pdf(cos_theta_max: f32) -> f32 {
+    1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+}
+
+// Returns barycentric coordinates (b0, b1) of a point distributed uniformly over a triangle.
+pub fn uniform_sample_triangle(u: (f32, f32)) -> (f32, f32) {
+    let su0 = u.0.sqrt();
+    (1.0 - su0, u.1 * su0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disk() {
+        for &u in &[(0.0, 0.0), (0.5, 0.5), (0.99, 0.01), (0.3, 0.8)] {
+            let (x, y) = concentric_sample_disk(u);
+            assert!(x * x + y * y <= 1.0 + 1e-6);
+        }
+        assert_eq!(concentric_sample_disk((0.5, 0.5)), (0.0, 0.0));
+    }
+
+    #[test]
+    fn polygon() {
+        // Every sample of a square aperture with a vertex on +x lies inside |x| + |y| <= 1.
+        let mut rng = crate::sampling::Rng::new(0);
+        let mut sum = (0.0, 0.0);
+        for _ in 0..1000 {
+            let (x, y) = uniform_sample_polygon(rng.uniform_2d(), 4);
+            assert!(x.abs() + y.abs() <= 1.0 + 1e-5);
+            sum = (sum.0 + x, sum.1 + y);
+        }
+
+        // The polygon is centered on the origin.
+        assert!(sum.0.abs() / 1000.0 < 0.05 && sum.1.abs() / 1000.0 < 0.05);
+    }
+
+    #[test]
+    fn hemisphere() {
+        for &u in &[(0.0, 0.0), (0.5, 0.5), (0.99, 0.01), (0.3, 0.8)] {
+            let w = cosine_sample_hemisphere(u);
+            assert!(w.z >= 0.0);
+            assert!((w.len() - 1.0).abs() < 1e-5);
+
+            let w = uniform_sample_hemisphere(u);
+            assert!(w.z >= 0.0);
+            assert!((w.len() - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn cone() {
+        let cos_theta_max = 0.8;
+        for &u in &[(0.0, 0.0), (0.5, 0.5), (0.99, 0.01), (0.3, 0.8)] {
+            let w = uniform_sample_cone(u, cos_theta_max);
+            assert!(w.z >= cos_theta_max - 1e-6);
+            assert!((w.len() - 1.0).abs() < 1e-5);
+        }
+
+        // The whole sphere is the cone with cos(theta_max) = -1.
+        assert!((uniform_cone_pdf(-1.0) - uniform_sphere_pdf()).abs() < 1e-7);
+    }
+}
@@ -0,0 +1,31 @@
+use crate::cameras::Camera;
+use crate::filters::Filter;
+use crate::imageio::ToneMap;
+use crate::integrators::Integrator;
+use crate::lights::Light;
+use crate::object::Object;
+use crate::render::AdaptiveSampling;
+use crate::sampling::Sampler;
+
+pub struct Scene {
+    pub img_width: usize,
+    pub img_height: usize,
+    // How the image is converted for display when it's written in an 8-bit format.
+    pub exposure: f32,
+    pub tone_map: ToneMap,
+    pub dither: bool,
+
+    pub integrator: Box<dyn Integrator>,
+    // Generates the samples for each pixel, and decides how many there are.
+    pub sampler: Box<dyn Sampler>,
+    // If set, pixels stop taking samples once they're converged, with the sampler's count as the
+    // maximum.
+    pub adaptive: Option<AdaptiveSampling>,
+    // Reconstruction filter that the samples are weighted with.
+    pub filter: Box<dyn Filter>,
+
+    pub camera: Box<dyn Camera>,
+
+    pub objs: Vec<Object>,
+    pub lights: Vec<Box<dyn Light>>,
+}
@@ -0,0 +1,104 @@
+use crate::geometry::{Bounds3, Point3, Vec3};
+use crate::shapes::quadric::{QuadricSurface, phi_max_radians};
+
+// An open cone around the z axis with a base of |radius| at z = 0 and its apex at z = |height|.
+pub struct Cone {
+    radius: f32,
+    height: f32,
+    phi_max: f32,
+}
+
+impl Cone {
+    // |phi_max| is in degrees.
+    pub fn new(radius: f32, height: f32, phi_max: f32) -> Self {
+        Cone { radius, height, phi_max: phi_max_radians(phi_max) }
+    }
+}
+
+impl QuadricSurface for Cone {
+    // The cone is x^2 + y^2 = k * (z - height)^2.
+    fn coefficients(&self, o: Point3, d: Vec3) -> (f32, f32, f32) {
+        let k = (self.radius / self.height).powi(2);
+        let oz = o.z - self.height;
+        (d.x * d.x + d.y * d.y - k * d.z * d.z,
+         2.0 * (d.x * o.x + d.y * o.y - k * d.z * oz),
+         o.x * o.x + o.y * o.y - k * oz * oz)
+    }
+
+    fn z_range(&self) -> (f32, f32) {
+        (0.0, self.height)
+    }
+
+    fn phi_max(&self) -> f32 {
+        self.phi_max
+    }
+
+    fn v(&self, p: Point3) -> f32 {
+        p.z / self.height
+    }
+
+    // Only depends on the angle around the axis, which also gives the apex a normal.
+    fn normal(&self, p: Point3) -> Vec3 {
+        let phi = p.y.atan2(p.x);
+        Vec3::new(self.height * phi.cos(), self.height * phi.sin(), self.radius)
+    }
+
+    // Straight down the side from the base to the apex.
+    fn v_derivatives(&self, p: Point3) -> (Vec3, Vec3) {
+        let phi = p.y.atan2(p.x);
+        (Vec3::new(-self.radius * phi.cos(), -self.radius * phi.sin(), self.height),
+         Vec3::zeroes())
+    }
+
+    fn bounds(&self) -> Bounds3 {
+        Bounds3::new(Point3::new(-self.radius, -self.radius, 0.0),
+                     Point3::new(self.radius, self.radius, self.height))
+    }
+
+    fn area(&self) -> f32 {
+        let slant = (self.radius * self.radius + self.height * self.height).sqrt();
+        0.5 * self.radius * slant * self.phi_max
+    }
+
+    fn sample(&self, u: (f32, f32)) -> Point3 {
+        // The area within a distance s of the apex grows with s^2.
+        let s = u.0.sqrt();
+        let r = s * self.radius;
+        let phi = u.1 * self.phi_max;
+        Point3::new(r * phi.cos(), r * phi.sin(), (1.0 - s) * self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interaction::SurfaceInteraction;
+    use std::f32::consts::PI;
+    use crate::geometry::{Ray, Transform};
+    use crate::shapes::{Quadric, Shape};
+
+    #[test]
+    fn cone() {
+        let cone = Quadric::new(Cone::new(1.0, 2.0, 360.0), Transform::identity(),
+                                Transform::identity());
+
+        // Halfway up, the radius is 0.5 and the normal leans towards the apex.
+        let ray = Ray { p: Point3::new(-5.0, 0.0, 1.0), d: Vec3::new(1.0, 0.0, 0.0) };
+        let SurfaceInteraction { t, p, n, .. } = cone.intersect(&ray).unwrap();
+        assert!((t - 4.5).abs() < 1e-5);
+        assert!((n - Vec3::normalize(Vec3::new(-2.0, 0.0, 1.0))).len() < 1e-5);
+        let (u, v) = cone.uv(p);
+        assert!((u - 0.5).abs() < 1e-5 && (v - 0.5).abs() < 1e-5);
+
+        // The other nappe above the apex isn't part of the cone.
+        let ray = Ray { p: Point3::new(-5.0, 0.0, 3.0), d: Vec3::new(1.0, 0.0, 0.0) };
+        assert!(cone.intersect(&ray).is_none());
+
+        assert!((cone.area() - PI * 5f32.sqrt()).abs() < 1e-5);
+        for &u in &[(0.0, 0.0), (0.25, 0.5), (0.99, 0.3)] {
+            let s = cone.sample(u);
+            let r = (s.p.x * s.p.x + s.p.y * s.p.y).sqrt();
+            assert!((r - 0.5 * (2.0 - s.p.z)).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use crate::geometry::{Bounds3, Point3, Ray, Vec3};
+use crate::interaction::SurfaceInteraction;
+use crate::shapes::{Shape, ShapeSample};
+
+// An axis-aligned box between the corners |min| and |max|.
+pub struct Cuboid {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Cuboid {
+    // Returns the axis of the face that |p| is on, and whether it's the face at |max|.
+    fn face(&self, p: Point3) -> (usize, bool) {
+        let mut best = (0, false);
+        let mut best_dist = f32::INFINITY;
+        for axis in 0..3 {
+            for &at_max in &[false, true] {
+                let plane = if at_max { self.max[axis] } else { self.min[axis] };
+                let dist = (p[axis] - plane).abs();
+                if dist < best_dist {
+                    best_dist = dist;
+                    best = (axis, at_max);
+                }
+            }
+        }
+        best
+    }
+
+    fn face_normal(axis: usize, at_max: bool) -> Vec3 {
+        let mut n = Vec3::zeroes();
+        n[axis] = if at_max { 1.0 } else { -1.0 };
+        n
+    }
+}
+
+impl Shape for Cuboid {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction<'_>> {
+        // Clip the ray against the three slabs between the pairs of faces.
+        let mut t0 = f32::NEG_INFINITY;
+        let mut t1 = f32::INFINITY;
+        for axis in 0..3 {
+            let inv_d = 1.0 / ray.d[axis];
+            let mut t_near = (self.min[axis] - ray.p[axis]) * inv_d;
+            let mut t_far = (self.max[axis] - ray.p[axis]) * inv_d;
+            if t_near > t_far {
+                std::mem::swap(&mut t_near, &mut t_far);
+            }
+            // NaNs, from rays that lie in a face's plane, leave the interval as it is.
+            if t_near > t0 {
+                t0 = t_near;
+            }
+            if t_far < t1 {
+                t1 = t_far;
+            }
+        }
+        if t0 > t1 {
+            return None;
+        }
+
+        // Like spheres, boxes are closed and rays that start inside hit the far side.
+        let t = if t0 >= 0.0 {
+            t0
+        } else if t1 >= 0.0 {
+            t1
+        } else {
+            return None;
+        };
+
+        // The normal always points out of the box.
+        let p = ray.p + t * ray.d;
+        let (axis, at_max) = self.face(p);
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        let (mut dpdu, mut dpdv) = (Vec3::zeroes(), Vec3::zeroes());
+        dpdu[a] = self.max[a] - self.min[a];
+        dpdv[b] = self.max[b] - self.min[b];
+        Some(SurfaceInteraction::new(ray, t, Cuboid::face_normal(axis, at_max), self.uv(p), dpdu,
+                                     dpdv, Vec3::zeroes(), Vec3::zeroes()))
+    }
+
+    fn bounds(&self) -> Bounds3 {
+        Bounds3::new(self.min, self.max)
+    }
+
+    // The coordinates of |p| across its face, along the next two axes in x, y, z order.
+    fn uv(&self, p: Point3) -> (f32, f32) {
+        let (axis, _) = self.face(p);
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        let offset = self.bounds().offset(p);
+        (offset[a], offset[b])
+    }
+
+    fn area(&self) -> f32 {
+        self.bounds().surface_area()
+    }
+
+    fn sample(&self, u: (f32, f32)) -> ShapeSample {
+        // Pick a face with probability proportional to its area, and reuse what's left of u.0
+        // for the position on it.
+        let d = self.max - self.min;
+        let face_areas = [d.y * d.z, d.y * d.z, d.x * d.z, d.x * d.z, d.x * d.y, d.x * d.y];
+        let total = face_areas.iter().sum::<f32>();
+        let mut target = u.0 * total;
+        let mut face = 5;
+        for (i, &area) in face_areas.iter().enumerate() {
+            if target < area {
+                face = i;
+                break;
+            }
+            target -= area;
+        }
+        let u0 = (target / face_areas[face]).clamp(0.0, 1.0);
+
+        let (axis, at_max) = (face / 2, face % 2 == 1);
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut p = self.min;
+        p[axis] = if at_max { self.max[axis] } else { self.min[axis] };
+        p[a] += u0 * d[a];
+        p[b] += u.1 * d[b];
+
+        ShapeSample { p, n: Cuboid::face_normal(axis, at_max), pdf: 1.0 / total }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interaction::tests::check_derivatives;
+
+    #[test]
+    fn intersect() {
+        let cuboid = Cuboid { min: Point3::new(-1.0, 0.0, 2.0), max: Point3::new(1.0, 3.0, 4.0) };
+
+        let ray = Ray { p: Point3::new(0.5, 1.5, 0.0), d: Vec3::new(0.0, 0.0, 1.0) };
+        let SurfaceInteraction { t, p, n, .. } = cuboid.intersect(&ray).unwrap();
+        assert_eq!((t, n), (2.0, Vec3::new(0.0, 0.0, -1.0)));
+        assert_eq!(cuboid.uv(p), (0.75, 0.5));
+
+        // From inside, the ray hits the far face, whose normal still points out.
+        let ray = Ray { p: Point3::new(0.0, 1.0, 3.0), d: Vec3::new(0.0, 1.0, 0.0) };
+        let SurfaceInteraction { t, n, .. } = cuboid.intersect(&ray).unwrap();
+        assert_eq!((t, n), (2.0, Vec3::new(0.0, 1.0, 0.0)));
+
+        let ray = Ray { p: Point3::new(2.0, 1.0, 0.0), d: Vec3::new(0.0, 0.0, 1.0) };
+        assert!(cuboid.intersect(&ray).is_none());
+        let ray = Ray { p: Point3::new(0.0, 1.0, 5.0), d: Vec3::new(0.0, 0.0, 1.0) };
+        assert!(cuboid.intersect(&ray).is_none());
+
+        assert_eq!(cuboid.area(), 2.0 * (6.0 + 4.0 + 6.0));
+
+        // Each face, from a point inside that's off center so that the rays hit them at an
+        // angle.
+        for axis in 0..3 {
+            for &sign in &[-1.0, 1.0] {
+                let mut d = Vec3::new(0.13, 0.21, 0.17);
+                d[axis] = sign;
+                check_derivatives(&cuboid, &Ray { p: Point3::new(0.1, 1.2, 3.1), d });
+            }
+        }
+    }
+
+    #[test]
+    fn sample_on_surface() {
+        let cuboid = Cuboid { min: Point3::new(-1.0, 0.0, 2.0), max: Point3::new(1.0, 3.0, 4.0) };
+        let mut rng = crate::sampling::Rng::new(0);
+        for _ in 0..100 {
+            let s = cuboid.sample(rng.uniform_2d());
+            let (axis, at_max) = cuboid.face(s.p);
+            assert_eq!(s.n, Cuboid::face_normal(axis, at_max));
+            let b = cuboid.bounds();
+            for i in 0..3 {
+                assert!(s.p[i] >= b.min[i] && s.p[i] <= b.max[i]);
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use crate::geometry::{Bounds3, Point3, Vec3};
+use crate::shapes::quadric::{QuadricSurface, phi_max_radians};
+
+// An open cylinder of |radius| around the z axis between the heights |z_min| and |z_max|.
+pub struct Cylinder {
+    radius: f32,
+    z_min: f32,
+    z_max: f32,
+    phi_max: f32,
+}
+
+impl Cylinder {
+    // |z_min| must be less than |z_max|, and |phi_max| is in degrees.
+    pub fn new(radius: f32, z_min: f32, z_max: f32, phi_max: f32) -> Self {
+        Cylinder { radius, z_min, z_max, phi_max: phi_max_radians(phi_max) }
+    }
+}
+
+impl QuadricSurface for Cylinder {
+    fn coefficients(&self, o: Point3, d: Vec3) -> (f32, f32, f32) {
+        (d.x * d.x + d.y * d.y,
+         2.0 * (d.x * o.x + d.y * o.y),
+         o.x * o.x + o.y * o.y - self.radius * self.radius)
+    }
+
+    fn z_range(&self) -> (f32, f32) {
+        (self.z_min, self.z_max)
+    }
+
+    fn phi_max(&self) -> f32 {
+        self.phi_max
+    }
+
+    fn v(&self, p: Point3) -> f32 {
+        (p.z - self.z_min) / (self.z_max - self.z_min)
+    }
+
+    fn v_derivatives(&self, _p: Point3) -> (Vec3, Vec3) {
+        (Vec3::new(0.0, 0.0, self.z_max - self.z_min), Vec3::zeroes())
+    }
+
+    fn normal(&self, p: Point3) -> Vec3 {
+        Vec3::new(p.x, p.y, 0.0)
+    }
+
+    fn bounds(&self) -> Bounds3 {
+        Bounds3::new(Point3::new(-self.radius, -self.radius, self.z_min),
+                     Point3::new(self.radius, self.radius, self.z_max))
+    }
+
+    fn area(&self) -> f32 {
+        (self.z_max - self.z_min) * self.radius * self.phi_max
+    }
+
+    fn sample(&self, u: (f32, f32)) -> Point3 {
+        let z = self.z_min + u.0 * (self.z_max - self.z_min);
+        let phi = u.1 * self.phi_max;
+        Point3::new(self.radius * phi.cos(), self.radius * phi.sin(), z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interaction::SurfaceInteraction;
+    use std::f32::consts::PI;
+    use crate::geometry::{Ray, Transform};
+    use crate::shapes::{Quadric, Shape};
+
+    #[test]
+    fn partial_sweep() {
+        // Half a cylinder on the +y side.
+        let cylinder = Quadric::new(Cylinder::new(2.0, -1.0, 1.0, 180.0), Transform::identity(),
+                                    Transform::identity());
+
+        // The near side is cut away, so the ray hits the inside of the far side.
+        let ray = Ray { p: Point3::new(0.0, -5.0, 0.5), d: Vec3::new(0.0, 1.0, 0.0) };
+        let SurfaceInteraction { t, p, n, .. } = cylinder.intersect(&ray).unwrap();
+        assert!((t - 7.0).abs() < 1e-5);
+        assert!((n - Vec3::new(0.0, 1.0, 0.0)).len() < 1e-5);
+        let (u, v) = cylinder.uv(p);
+        assert!((u - 0.5).abs() < 1e-5 && (v - 0.75).abs() < 1e-5);
+
+        let ray = Ray { p: Point3::new(0.0, 5.0, 0.5), d: Vec3::new(0.0, -1.0, 0.0) };
+        assert!((cylinder.intersect(&ray).unwrap().t - 3.0).abs() < 1e-5);
+        let ray = Ray { p: Point3::new(0.0, 5.0, 1.5), d: Vec3::new(0.0, -1.0, 0.0) };
+        assert!(cylinder.intersect(&ray).is_none());
+
+        assert!((cylinder.area() - 4.0 * PI).abs() < 1e-5);
+        for &u in &[(0.0, 0.0), (0.3, 0.6), (0.99, 0.99)] {
+            let s = cylinder.sample(u);
+            assert!(s.p.y >= -1e-6 && (s.p.x * s.p.x + s.p.y * s.p.y - 4.0).abs() < 1e-4);
+        }
+    }
+}
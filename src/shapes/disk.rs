@@ -0,0 +1,125 @@
+use std::f32::consts::PI;
+
+use crate::geometry::{Bounds3, Frame, Point3, Ray, Vec3};
+use crate::interaction::SurfaceInteraction;
+use crate::shapes::{Shape, ShapeSample};
+
+// A flat disk centered at |c| and perpendicular to |n|, with a hole of |inner_radius| in the
+// middle, which makes it an annulus.
+pub struct Disk {
+    c: Point3,
+    frame: Frame,
+    radius: f32,
+    inner_radius: f32,
+}
+
+impl Disk {
+    // |inner_radius| must be less than |radius|.
+    pub fn new(c: Point3, n: Vec3, radius: f32, inner_radius: f32) -> Self {
+        Disk { c, frame: Frame::from_normal(Vec3::normalize(n)), radius, inner_radius }
+    }
+}
+
+impl Shape for Disk {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction<'_>> {
+        let n = self.frame.n;
+        let denom = Vec3::dot(ray.d, n);
+        if denom == 0.0 {
+            return None;
+        }
+
+        let t = Vec3::dot(self.c - ray.p, n) / denom;
+        if t < 0.0 {
+            return None;
+        }
+
+        let p = ray.p + t * ray.d;
+        let dist_sq = (p - self.c).len_sq();
+        if dist_sq > self.radius * self.radius || dist_sq < self.inner_radius * self.inner_radius {
+            return None;
+        }
+
+        let uv = self.uv(p);
+        let local = self.frame.world_to_local(p - self.c);
+        let (sin_phi, cos_phi) = (2.0 * PI * uv.0).sin_cos();
+        let dpdu = self.frame.local_to_world(Vec3::new(-2.0 * PI * local.y, 2.0 * PI * local.x,
+                                                       0.0));
+        let dpdv = -(self.radius - self.inner_radius)
+            * self.frame.local_to_world(Vec3::new(cos_phi, sin_phi, 0.0));
+
+        // Disks are two-sided and face the ray.
+        let mut si = SurfaceInteraction::new(ray, t, n, uv, dpdu, dpdv, Vec3::zeroes(),
+                                             Vec3::zeroes());
+        si.face_ray();
+        Some(si)
+    }
+
+    fn bounds(&self) -> Bounds3 {
+        // A circle extends r * sqrt(1 - n_i^2) along each axis i.
+        let n = self.frame.n;
+        let extent = |n_i: f32| self.radius * (1.0 - n_i * n_i).max(0.0).sqrt();
+        let e = Vec3::new(extent(n.x), extent(n.y), extent(n.z));
+        Bounds3::new(self.c - e, self.c + e)
+    }
+
+    // u is the angle around the normal and v goes from the outer edge to the inner one.
+    fn uv(&self, p: Point3) -> (f32, f32) {
+        let local = self.frame.world_to_local(p - self.c);
+        let mut phi = local.y.atan2(local.x);
+        if phi < 0.0 {
+            phi += 2.0 * PI;
+        }
+        let r = (local.x * local.x + local.y * local.y).sqrt();
+        (phi / (2.0 * PI), (self.radius - r) / (self.radius - self.inner_radius))
+    }
+
+    fn area(&self) -> f32 {
+        PI * (self.radius * self.radius - self.inner_radius * self.inner_radius)
+    }
+
+    fn sample(&self, u: (f32, f32)) -> ShapeSample {
+        // Inverting the CDF of the radius, which is proportional to r^2 - inner_radius^2.
+        let r_in_sq = self.inner_radius * self.inner_radius;
+        let r = (r_in_sq + u.0 * (self.radius * self.radius - r_in_sq)).sqrt();
+        let phi = 2.0 * PI * u.1;
+        let p = self.c + self.frame.local_to_world(Vec3::new(r * phi.cos(), r * phi.sin(), 0.0));
+        ShapeSample { p, n: self.frame.n, pdf: 1.0 / self.area() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interaction::tests::check_derivatives;
+
+    #[test]
+    fn annulus() {
+        let disk = Disk::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 2.0, 1.0);
+
+        let hit = |x: f32| disk.intersect(&Ray { p: Point3::new(x, 0.0, 0.0),
+                                                 d: Vec3::new(0.0, 0.0, 1.0) });
+        assert!(hit(0.5).is_none());
+        assert!(hit(2.5).is_none());
+        let SurfaceInteraction { t, p, n, .. } = hit(1.5).unwrap();
+        assert_eq!((t, n), (5.0, Vec3::new(0.0, 0.0, -1.0)));
+        assert!((disk.uv(p).1 - 0.5).abs() < 1e-6);
+
+        let b = disk.bounds();
+        assert_eq!((b.min, b.max), (Point3::new(-2.0, -2.0, 5.0), Point3::new(2.0, 2.0, 5.0)));
+
+        for &u in &[(0.0, 0.0), (0.5, 0.3), (0.99, 0.9)] {
+            let s = disk.sample(u);
+            let r = (s.p - disk.c).len();
+            assert!((1.0 - 1e-5..=2.0 + 1e-5).contains(&r));
+            assert!(disk.intersect(&Ray { p: Point3::new(s.p.x, s.p.y, 0.0),
+                                          d: Vec3::new(0.0, 0.0, 1.0) }).is_some());
+        }
+        assert!((disk.area() - 3.0 * PI).abs() < 1e-5);
+
+        // Towards a point away from where u wraps around, at an angle.
+        let target = disk.c + disk.frame.local_to_world(Vec3::new(1.5 * 2.0f32.cos(),
+                                                                  1.5 * 2.0f32.sin(), 0.0));
+        let p = Point3::new(0.5, -1.0, 0.0);
+        check_derivatives(&disk, &Ray { p, d: target - p });
+    }
+}
@@ -0,0 +1,208 @@
+use std::f32::consts::PI;
+
+use crate::geometry::{Bounds3, Point3, Vec3};
+use crate::sampling::distribution::Distribution1D;
+use crate::shapes::quadric::{QuadricSurface, phi_max_radians};
+
+// Number of steps in the tabulated distribution of the area over the height.
+const AREA_STEPS: usize = 256;
+
+// The surface swept by rotating the line segment from |p1| to |p2| around the z axis, which is a
+// hyperboloid of one sheet unless the line meets the axis (a cone) or is parallel to it (a
+// cylinder).
+pub struct Hyperboloid {
+    p1: Point3,
+    p2: Point3,
+    phi_max: f32,
+    // The squared radius at height z is alpha * z^2 + beta * z + gamma.
+    alpha: f32,
+    beta: f32,
+    gamma: f32,
+    // The distribution of the area over the height, which has no closed form inverse.
+    area_distrib: Distribution1D,
+    area: f32,
+}
+
+impl Hyperboloid {
+    // |p1| and |p2| must be at different heights. |phi_max| is in degrees.
+    pub fn new(p1: Point3, p2: Point3, phi_max: f32) -> Self {
+        assert!(p1.z != p2.z);
+
+        // With the point on the line at height z being p1 + s * (z - p1.z) * (p2 - p1), the
+        // squared radius is the quadratic |p1.xy + s * (z - p1.z) * (p2 - p1).xy|^2.
+        let (dx, dy) = (p2.x - p1.x, p2.y - p1.y);
+        let s = 1.0 / (p2.z - p1.z);
+        let w = -p1.z * s;
+        let r0_sq = p1.x * p1.x + p1.y * p1.y;
+        let r1 = 2.0 * (p1.x * dx + p1.y * dy);
+        let r2 = dx * dx + dy * dy;
+        let alpha = r2 * s * s;
+        let beta = r1 * s + 2.0 * r2 * s * w;
+        let gamma = r0_sq + r1 * w + r2 * w * w;
+
+        let mut h = Hyperboloid {
+            p1,
+            p2,
+            phi_max: phi_max_radians(phi_max),
+            alpha,
+            beta,
+            gamma,
+            area_distrib: Distribution1D::new(&[1.0]),
+            area: 0.0,
+        };
+
+        let (z_min, z_max) = h.z_range();
+        let steps = (0..AREA_STEPS)
+            .map(|i| h.ring(z_min + (i as f32 + 0.5) / AREA_STEPS as f32 * (z_max - z_min)))
+            .collect::<Vec<_>>();
+        h.area_distrib = Distribution1D::new(&steps);
+        h.area = h.phi_max * (z_max - z_min) * h.area_distrib.integral();
+        h
+    }
+
+    // Returns the point where the line crosses the height |z|.
+    fn line_at(&self, z: f32) -> Point3 {
+        let v = (z - self.p1.z) / (self.p2.z - self.p1.z);
+        self.p1 + v * (self.p2 - self.p1)
+    }
+
+    // The area per unit height and per radian of the ring at height |z|, r * sqrt(1 + r'^2).
+    fn ring(&self, z: f32) -> f32 {
+        let r_sq = self.alpha * z * z + self.beta * z + self.gamma;
+        let r_dr = self.alpha * z + 0.5 * self.beta;
+        (r_sq + r_dr * r_dr).max(0.0).sqrt()
+    }
+}
+
+impl QuadricSurface for Hyperboloid {
+    fn coefficients(&self, o: Point3, d: Vec3) -> (f32, f32, f32) {
+        (d.x * d.x + d.y * d.y - self.alpha * d.z * d.z,
+         2.0 * (d.x * o.x + d.y * o.y - self.alpha * d.z * o.z) - self.beta * d.z,
+         o.x * o.x + o.y * o.y - (self.alpha * o.z * o.z + self.beta * o.z + self.gamma))
+    }
+
+    fn z_range(&self) -> (f32, f32) {
+        (self.p1.z.min(self.p2.z), self.p1.z.max(self.p2.z))
+    }
+
+    fn phi_max(&self) -> f32 {
+        self.phi_max
+    }
+
+    // The sweep starts at the line, so phi is measured from where the line is at p's height.
+    fn phi(&self, p: Point3) -> f32 {
+        let l = self.line_at(p.z);
+        let phi = (l.x * p.y - l.y * p.x).atan2(l.x * p.x + l.y * p.y);
+        if phi < 0.0 { phi + 2.0 * PI } else { phi }
+    }
+
+    fn v(&self, p: Point3) -> f32 {
+        (p.z - self.p1.z) / (self.p2.z - self.p1.z)
+    }
+
+    fn normal(&self, p: Point3) -> Vec3 {
+        Vec3::new(p.x, p.y, -(self.alpha * p.z + 0.5 * self.beta))
+    }
+
+    // Along the line, rotated to where |p| is in the sweep.
+    fn v_derivatives(&self, p: Point3) -> (Vec3, Vec3) {
+        let (sin_phi, cos_phi) = self.phi(p).sin_cos();
+        let d = self.p2 - self.p1;
+        (Vec3::new(d.x * cos_phi - d.y * sin_phi, d.x * sin_phi + d.y * cos_phi, d.z),
+         Vec3::zeroes())
+    }
+
+    fn bounds(&self) -> Bounds3 {
+        // The radius is convex in z, so it's largest at one of the ends.
+        let r = (self.p1.x * self.p1.x + self.p1.y * self.p1.y)
+            .max(self.p2.x * self.p2.x + self.p2.y * self.p2.y)
+            .sqrt();
+        let (z_min, z_max) = self.z_range();
+        Bounds3::new(Point3::new(-r, -r, z_min), Point3::new(r, r, z_max))
+    }
+
+    fn area(&self) -> f32 {
+        self.area
+    }
+
+    fn sample(&self, u: (f32, f32)) -> Point3 {
+        let (z_min, z_max) = self.z_range();
+        let (x, _, _) = self.area_distrib.sample_continuous(u.0);
+        let l = self.line_at(z_min + x * (z_max - z_min));
+
+        let phi = u.1 * self.phi_max;
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        Point3::new(l.x * cos_phi - l.y * sin_phi, l.x * sin_phi + l.y * cos_phi, l.z)
+    }
+
+    // The height is sampled from the tabulated area, so the density only approximately matches
+    // the uniform 1 / area.
+    fn pdf(&self, p: Point3) -> f32 {
+        let (z_min, z_max) = self.z_range();
+        let ring = self.ring(p.z);
+        if ring == 0.0 {
+            return 0.0;
+        }
+        let pdf_z = self.area_distrib.pdf((p.z - z_min) / (z_max - z_min)) / (z_max - z_min);
+        pdf_z / (self.phi_max * ring)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interaction::SurfaceInteraction;
+    use crate::geometry::{Ray, Transform};
+    use crate::shapes::{Quadric, Shape};
+
+    fn place(h: Hyperboloid) -> Quadric<Hyperboloid> {
+        Quadric::new(h, Transform::identity(), Transform::identity())
+    }
+
+    #[test]
+    fn hyperboloid() {
+        // x^2 + y^2 - z^2 = 1, swept by the line from (1, -1, -1) to (1, 1, 1).
+        let h = place(Hyperboloid::new(Point3::new(1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0),
+                                       360.0));
+
+        // The waist has a radius of 1.
+        let ray = Ray { p: Point3::new(-5.0, 0.0, 0.0), d: Vec3::new(1.0, 0.0, 0.0) };
+        let SurfaceInteraction { t, p, n, .. } = h.intersect(&ray).unwrap();
+        assert!((t - 4.0).abs() < 1e-5);
+        assert!((n - Vec3::new(-1.0, 0.0, 0.0)).len() < 1e-5);
+        let (u, v) = h.uv(p);
+        assert!((u - 0.5).abs() < 1e-5 && (v - 0.5).abs() < 1e-5);
+
+        let ray = Ray { p: Point3::new(-5.0, 0.0, 0.75), d: Vec3::new(1.0, 0.0, 0.0) };
+        let SurfaceInteraction { t, n, .. } = h.intersect(&ray).unwrap();
+        assert!((t - 3.75).abs() < 1e-5);
+        assert!((n - Vec3::normalize(Vec3::new(-1.25, 0.0, -0.75))).len() < 1e-5);
+
+        // Along the axis there's nothing to hit.
+        let ray = Ray { p: Point3::new(0.0, 0.0, -5.0), d: Vec3::new(0.0, 0.0, 1.0) };
+        assert!(h.intersect(&ray).is_none());
+
+        let b = h.bounds();
+        let r = 2f32.sqrt();
+        assert!((b.min - Point3::new(-r, -r, -1.0)).len() < 1e-5);
+
+        // The area of a line parallel to the axis is a cylinder's, and the samples are uniform.
+        let cylinder = place(Hyperboloid::new(Point3::new(2.0, 0.0, 0.0),
+                                              Point3::new(2.0, 0.0, 3.0), 90.0));
+        assert!((cylinder.area() - 3.0 * PI).abs() < 1e-4);
+        let s = cylinder.sample((0.5, 1.0));
+        assert!((s.p - Point3::new(0.0, 2.0, 1.5)).len() < 1e-4);
+        assert!((s.pdf * cylinder.area() - 1.0).abs() < 1e-4);
+        assert!((cylinder.uv(s.p).0 - 1.0).abs() < 1e-4);
+
+        // The area from the analytic formula for x^2 + y^2 - z^2 = 1 over |z| <= 1, which is
+        // 2 pi * (sqrt(3) + asinh(sqrt(2)) / sqrt(2)).
+        let expected = 2.0 * PI * (3f32.sqrt() + 2f32.sqrt().asinh() / 2f32.sqrt());
+        assert!((h.area() / expected - 1.0).abs() < 1e-4);
+        for &u in &[(0.0, 0.0), (0.3, 0.4), (0.7, 0.9), (0.99, 0.1)] {
+            let s = h.sample(u);
+            assert!((s.p.x * s.p.x + s.p.y * s.p.y - s.p.z * s.p.z - 1.0).abs() < 1e-4);
+            assert!((s.pdf * h.area() - 1.0).abs() < 1e-2);
+        }
+    }
+}
@@ -1,7 +1,31 @@
+pub mod cone;
+pub mod cuboid;
+pub mod cylinder;
+pub mod disk;
+pub mod hyperboloid;
+pub mod paraboloid;
+pub mod partial_sphere;
+pub mod plane;
+pub mod quad;
+pub mod quadric;
 pub mod shape;
 pub mod sphere;
+pub mod torus;
 pub mod triangle;
+pub mod triangle_mesh;
 
-pub use self::shape::Shape;
+pub use self::cone::Cone;
+pub use self::cuboid::Cuboid;
+pub use self::cylinder::Cylinder;
+pub use self::disk::Disk;
+pub use self::hyperboloid::Hyperboloid;
+pub use self::paraboloid::Paraboloid;
+pub use self::partial_sphere::PartialSphere;
+pub use self::plane::Plane;
+pub use self::quad::Quad;
+pub use self::quadric::{Quadric, QuadricSurface};
+pub use self::shape::{Shape, ShapeSample};
 pub use self::sphere::Sphere; 
+pub use self::torus::Torus;
 pub use self::triangle::Triangle;
+pub use self::triangle_mesh::TriangleMesh;
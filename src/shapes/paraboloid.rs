@@ -0,0 +1,125 @@
+use crate::geometry::{Bounds3, Point3, Vec3};
+use crate::shapes::quadric::{QuadricSurface, phi_max_radians};
+
+// A paraboloid around the z axis with its vertex at the origin, opening towards +z and reaching
+// |radius| at the height |z_max|. It's cut to the heights above |z_min|.
+pub struct Paraboloid {
+    radius: f32,
+    z_min: f32,
+    z_max: f32,
+    phi_max: f32,
+}
+
+impl Paraboloid {
+    // Requires 0 <= |z_min| < |z_max|. |phi_max| is in degrees.
+    pub fn new(radius: f32, z_min: f32, z_max: f32, phi_max: f32) -> Self {
+        Paraboloid { radius, z_min, z_max, phi_max: phi_max_radians(phi_max) }
+    }
+
+    // The paraboloid is x^2 + y^2 = c * z.
+    fn c(&self) -> f32 {
+        self.radius * self.radius / self.z_max
+    }
+
+    // The antiderivative of the area of the ring at height z with respect to z and phi. The
+    // ring's circumference per radian is r(z) * sqrt(1 + r'(z)^2) = sqrt(c * z + c^2 / 4).
+    fn area_integral(&self, z: f32) -> f32 {
+        let c = self.c();
+        2.0 / (3.0 * c) * (c * z + 0.25 * c * c).powf(1.5)
+    }
+}
+
+impl QuadricSurface for Paraboloid {
+    fn coefficients(&self, o: Point3, d: Vec3) -> (f32, f32, f32) {
+        let k = 1.0 / self.c();
+        (k * (d.x * d.x + d.y * d.y),
+         2.0 * k * (d.x * o.x + d.y * o.y) - d.z,
+         k * (o.x * o.x + o.y * o.y) - o.z)
+    }
+
+    fn z_range(&self) -> (f32, f32) {
+        (self.z_min, self.z_max)
+    }
+
+    fn phi_max(&self) -> f32 {
+        self.phi_max
+    }
+
+    fn v(&self, p: Point3) -> f32 {
+        (p.z - self.z_min) / (self.z_max - self.z_min)
+    }
+
+    fn normal(&self, p: Point3) -> Vec3 {
+        Vec3::new(2.0 * p.x, 2.0 * p.y, -self.c())
+    }
+
+    // The radius grows with sqrt(z), so the derivatives are infinite at the tip.
+    fn v_derivatives(&self, p: Point3) -> (Vec3, Vec3) {
+        let dz = self.z_max - self.z_min;
+        (dz * Vec3::new(p.x / (2.0 * p.z), p.y / (2.0 * p.z), 1.0),
+         -(dz * dz / (4.0 * p.z * p.z)) * Vec3::new(p.x, p.y, 0.0))
+    }
+
+    fn bounds(&self) -> Bounds3 {
+        Bounds3::new(Point3::new(-self.radius, -self.radius, self.z_min),
+                     Point3::new(self.radius, self.radius, self.z_max))
+    }
+
+    fn area(&self) -> f32 {
+        self.phi_max * (self.area_integral(self.z_max) - self.area_integral(self.z_min))
+    }
+
+    fn sample(&self, u: (f32, f32)) -> Point3 {
+        // Inverts the area integral for z.
+        let c = self.c();
+        let a0 = self.area_integral(self.z_min);
+        let a = a0 + u.0 * (self.area_integral(self.z_max) - a0);
+        let z = ((1.5 * c * a).powf(2.0 / 3.0) - 0.25 * c * c) / c;
+        let z = z.clamp(self.z_min, self.z_max);
+
+        let r = (c * z).sqrt();
+        let phi = u.1 * self.phi_max;
+        Point3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interaction::SurfaceInteraction;
+    use std::f32::consts::PI;
+    use crate::geometry::{Ray, Transform};
+    use crate::shapes::{Quadric, Shape};
+
+    #[test]
+    fn paraboloid() {
+        // z = x^2 + y^2.
+        let paraboloid = Quadric::new(Paraboloid::new(2.0, 1.0, 4.0, 360.0),
+                                      Transform::identity(), Transform::identity());
+
+        let ray = Ray { p: Point3::new(1.5, 0.0, 10.0), d: Vec3::new(0.0, 0.0, -1.0) };
+        let SurfaceInteraction { t, p, n, .. } = paraboloid.intersect(&ray).unwrap();
+        assert!((t - 7.75).abs() < 1e-5);
+        assert!((n - Vec3::normalize(Vec3::new(3.0, 0.0, -1.0))).len() < 1e-5);
+        let (u, v) = paraboloid.uv(p);
+        assert!(u.abs() < 1e-5 && (v - 1.25 / 3.0).abs() < 1e-5);
+
+        // Below z_min the bottom is open.
+        let ray = Ray { p: Point3::new(0.5, 0.0, 10.0), d: Vec3::new(0.0, 0.0, -1.0) };
+        assert!(paraboloid.intersect(&ray).is_none());
+
+        // Compare the area with a sum over thin rings.
+        let n = 10000;
+        let ring = |z: f32| 2.0 * PI * z.sqrt() * (1.0 + 1.0 / (4.0 * z)).sqrt();
+        let area = (0..n).map(|i| ring(1.0 + (i as f32 + 0.5) * 3.0 / n as f32) * 3.0 / n as f32)
+            .sum::<f32>();
+        assert!((paraboloid.area() / area - 1.0).abs() < 1e-4);
+
+        // The samples at the quartiles of the area split it into equal parts.
+        let quartile = paraboloid.sample((0.25, 0.0)).p.z;
+        let below = (0..n).map(|i| 1.0 + (i as f32 + 0.5) * 3.0 / n as f32)
+            .filter(|&z| z < quartile).map(|z| ring(z) * 3.0 / n as f32).sum::<f32>();
+        assert!((below / area - 0.25).abs() < 1e-3);
+        assert!((paraboloid.sample((1.0, 0.0)).p.z - 4.0).abs() < 1e-4);
+    }
+}
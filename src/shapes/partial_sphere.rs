@@ -0,0 +1,121 @@
+use crate::geometry::{Bounds3, Point3, Vec3};
+use crate::shapes::quadric::{QuadricSurface, phi_max_radians};
+
+// A sphere of |radius| around the origin that's cut to the heights between |z_min| and |z_max|
+// and swept |phi_max| around the z axis. Whole spheres should use Sphere, which samples them
+// more efficiently.
+pub struct PartialSphere {
+    radius: f32,
+    z_min: f32,
+    z_max: f32,
+    phi_max: f32,
+}
+
+impl PartialSphere {
+    // The heights are clamped to the sphere and |phi_max| is in degrees.
+    pub fn new(radius: f32, z_min: f32, z_max: f32, phi_max: f32) -> Self {
+        PartialSphere {
+            radius,
+            z_min: z_min.min(z_max).clamp(-radius, radius),
+            z_max: z_min.max(z_max).clamp(-radius, radius),
+            phi_max: phi_max_radians(phi_max),
+        }
+    }
+
+    fn theta(&self, z: f32) -> f32 {
+        (z / self.radius).clamp(-1.0, 1.0).acos()
+    }
+}
+
+impl QuadricSurface for PartialSphere {
+    fn coefficients(&self, o: Point3, d: Vec3) -> (f32, f32, f32) {
+        let o = o - Point3::zeroes();
+        (Vec3::dot(d, d), 2.0 * Vec3::dot(o, d), Vec3::dot(o, o) - self.radius * self.radius)
+    }
+
+    fn z_range(&self) -> (f32, f32) {
+        (self.z_min, self.z_max)
+    }
+
+    fn phi_max(&self) -> f32 {
+        self.phi_max
+    }
+
+    // v goes with the polar angle from the bottom cut to the top one.
+    fn v(&self, p: Point3) -> f32 {
+        let theta_min = self.theta(self.z_min);
+        (self.theta(p.z) - theta_min) / (self.theta(self.z_max) - theta_min)
+    }
+
+    fn normal(&self, p: Point3) -> Vec3 {
+        p - Point3::zeroes()
+    }
+
+    fn v_derivatives(&self, p: Point3) -> (Vec3, Vec3) {
+        // v is linear in the polar angle, and the point's derivative with respect to the angle
+        // is its rotation towards the bottom of the sphere.
+        let theta_range = self.theta(self.z_max) - self.theta(self.z_min);
+        let phi = p.y.atan2(p.x);
+        let r = (p.x * p.x + p.y * p.y).sqrt();
+        let dpdtheta = Vec3::new(p.z * phi.cos(), p.z * phi.sin(), -r);
+        (theta_range * dpdtheta, -theta_range * theta_range * (p - Point3::zeroes()))
+    }
+
+    fn bounds(&self) -> Bounds3 {
+        Bounds3::new(Point3::new(-self.radius, -self.radius, self.z_min),
+                     Point3::new(self.radius, self.radius, self.z_max))
+    }
+
+    // Like a cylinder around it, every slice of the sphere has the same area per unit height.
+    fn area(&self) -> f32 {
+        self.phi_max * self.radius * (self.z_max - self.z_min)
+    }
+
+    fn sample(&self, u: (f32, f32)) -> Point3 {
+        let z = self.z_min + u.0 * (self.z_max - self.z_min);
+        let r = (self.radius * self.radius - z * z).max(0.0).sqrt();
+        let phi = u.1 * self.phi_max;
+        Point3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interaction::SurfaceInteraction;
+    use std::f32::consts::PI;
+    use crate::geometry::{Ray, Transform};
+    use crate::shapes::{Quadric, Shape};
+
+    #[test]
+    fn cut_sphere() {
+        // The upper half of a sphere, cut open on the -y side.
+        let sphere = Quadric::new(PartialSphere::new(1.0, 0.0, 2.0, 180.0),
+                                  Transform::identity(), Transform::identity());
+
+        // Looking down at the top.
+        let ray = Ray { p: Point3::new(0.0, 0.6, 5.0), d: Vec3::new(0.0, 0.0, -1.0) };
+        let SurfaceInteraction { t, p, n, .. } = sphere.intersect(&ray).unwrap();
+        assert!((t - 4.2).abs() < 1e-5);
+        assert!((n - Vec3::new(0.0, 0.6, 0.8)).len() < 1e-5);
+        let (u, v) = sphere.uv(p);
+        assert!((u - 0.5).abs() < 1e-5);
+        assert!((v - (1.0 - 0.8f32.acos() / (0.5 * PI))).abs() < 1e-5);
+
+        // The ray enters through the open side and hits the inside of the far one.
+        let ray = Ray { p: Point3::new(0.0, -5.0, 0.5), d: Vec3::new(0.0, 1.0, 0.0) };
+        let SurfaceInteraction { t, .. } = sphere.intersect(&ray).unwrap();
+        assert!((t - (5.0 + 0.75f32.sqrt())).abs() < 1e-5);
+        let ray = Ray { p: Point3::new(0.0, -5.0, -0.5), d: Vec3::new(0.0, 1.0, 0.0) };
+        assert!(sphere.intersect(&ray).is_none());
+
+        assert!((sphere.area() - PI).abs() < 1e-5);
+        let b = sphere.bounds();
+        assert_eq!((b.min.z, b.max.z), (0.0, 1.0));
+        for &u in &[(0.0, 0.0), (0.5, 0.5), (0.99, 0.99)] {
+            let s = sphere.sample(u);
+            assert!(((s.p - Point3::zeroes()).len() - 1.0).abs() < 1e-5);
+            assert!(s.p.z >= 0.0 && s.p.y >= -1e-6);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::geometry::{Bounds3, Frame, Point3, Ray, Vec3};
+use crate::interaction::SurfaceInteraction;
+use crate::shapes::{Shape, ShapeSample};
+
+// The infinite plane through |p| that's perpendicular to |n|.
+pub struct Plane {
+    p: Point3,
+    frame: Frame,
+}
+
+impl Plane {
+    pub fn new(p: Point3, n: Vec3) -> Self {
+        Plane { p, frame: Frame::from_normal(Vec3::normalize(n)) }
+    }
+}
+
+impl Shape for Plane {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction<'_>> {
+        let n = self.frame.n;
+        let denom = Vec3::dot(ray.d, n);
+        if denom == 0.0 {
+            return None;
+        }
+
+        let t = Vec3::dot(self.p - ray.p, n) / denom;
+        if t < 0.0 {
+            return None;
+        }
+
+        // Like triangles, planes are two-sided and face the ray.
+        let uv = self.uv(ray.p + t * ray.d);
+        let mut si = SurfaceInteraction::new(ray, t, n, uv, self.frame.s, self.frame.t,
+                                             Vec3::zeroes(), Vec3::zeroes());
+        si.face_ray();
+        Some(si)
+    }
+
+    fn bounds(&self) -> Bounds3 {
+        Bounds3::infinite()
+    }
+
+    // The coordinates of |p| along two perpendicular directions in the plane, measured from the
+    // plane's point.
+    fn uv(&self, p: Point3) -> (f32, f32) {
+        let d = p - self.p;
+        (Vec3::dot(d, self.frame.s), Vec3::dot(d, self.frame.t))
+    }
+
+    fn area(&self) -> f32 {
+        f32::INFINITY
+    }
+
+    // An infinite plane can't be sampled uniformly, so this returns a sample with a density of
+    // zero, which lights skip.
+    fn sample(&self, _u: (f32, f32)) -> ShapeSample {
+        ShapeSample { p: self.p, n: self.frame.n, pdf: 0.0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interaction::tests::check_derivatives;
+
+    #[test]
+    fn intersect() {
+        let plane = Plane::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 2.0, 0.0));
+
+        let ray = Ray { p: Point3::new(3.0, 5.0, 0.0), d: Vec3::new(0.0, -2.0, 0.0) };
+        let SurfaceInteraction { t, p, n, .. } = plane.intersect(&ray).unwrap();
+        assert_eq!((t, p, n), (2.0, Point3::new(3.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)));
+        let (u, v) = plane.uv(p);
+        assert!((u * u + v * v - 9.0).abs() < 1e-5);
+
+        // From below, the normal faces down.
+        let ray = Ray { p: Point3::zeroes(), d: Vec3::new(1.0, 1.0, 0.0) };
+        let SurfaceInteraction { t, n, .. } = plane.intersect(&ray).unwrap();
+        assert_eq!((t, n), (1.0, Vec3::new(0.0, -1.0, 0.0)));
+
+        let ray = Ray { p: Point3::zeroes(), d: Vec3::new(1.0, 0.0, 0.0) };
+        assert!(plane.intersect(&ray).is_none());
+        let ray = Ray { p: Point3::zeroes(), d: Vec3::new(0.0, -1.0, 0.0) };
+        assert!(plane.intersect(&ray).is_none());
+
+        check_derivatives(&plane, &Ray { p: Point3::new(1.0, 4.0, 2.0),
+                                         d: Vec3::new(0.5, -1.0, -0.3) });
+    }
+}
@@ -0,0 +1,91 @@
+use crate::geometry::{Bounds3, Point3, Ray, Vec3};
+use crate::interaction::SurfaceInteraction;
+use crate::shapes::{Shape, ShapeSample};
+
+// The parallelogram with corner |p| and edges |e1| and |e2|, which is a rectangle when the edges
+// are perpendicular.
+pub struct Quad {
+    pub p: Point3,
+    pub e1: Vec3,
+    pub e2: Vec3,
+}
+
+impl Shape for Quad {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction<'_>> {
+        let n = Vec3::cross(self.e1, self.e2);
+        let denom = Vec3::dot(ray.d, n);
+        if denom == 0.0 {
+            return None;
+        }
+
+        let t = Vec3::dot(self.p - ray.p, n) / denom;
+        if t < 0.0 {
+            return None;
+        }
+
+        let hit = ray.p + t * ray.d;
+        let (u, v) = self.uv(hit);
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return None;
+        }
+
+        // Quads are two-sided and face the ray.
+        let mut si = SurfaceInteraction::new(ray, t, Vec3::normalize(n), (u, v), self.e1, self.e2,
+                                             Vec3::zeroes(), Vec3::zeroes());
+        si.face_ray();
+        Some(si)
+    }
+
+    fn bounds(&self) -> Bounds3 {
+        let b = Bounds3::new(self.p, self.p + self.e1);
+        Bounds3::union(b, Bounds3::new(self.p + self.e2, self.p + self.e1 + self.e2))
+    }
+
+    // The coordinates of |p| along the edges, from 0 to 1.
+    fn uv(&self, p: Point3) -> (f32, f32) {
+        let n = Vec3::cross(self.e1, self.e2);
+        let w = n / Vec3::dot(n, n);
+        let d = p - self.p;
+        (Vec3::dot(w, Vec3::cross(d, self.e2)), Vec3::dot(w, Vec3::cross(self.e1, d)))
+    }
+
+    fn area(&self) -> f32 {
+        Vec3::cross(self.e1, self.e2).len()
+    }
+
+    fn sample(&self, u: (f32, f32)) -> ShapeSample {
+        let p = self.p + u.0 * self.e1 + u.1 * self.e2;
+        let n = Vec3::normalize(Vec3::cross(self.e1, self.e2));
+        ShapeSample { p, n, pdf: 1.0 / self.area() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interaction::tests::check_derivatives;
+
+    #[test]
+    fn parallelogram() {
+        let quad = Quad {
+            p: Point3::new(0.0, 0.0, 2.0),
+            e1: Vec3::new(2.0, 0.0, 0.0),
+            e2: Vec3::new(1.0, 1.0, 0.0),
+        };
+
+        let ray = |x: f32, y: f32| Ray { p: Point3::new(x, y, 0.0), d: Vec3::new(0.0, 0.0, 1.0) };
+        let SurfaceInteraction { t, p, n, .. } = quad.intersect(&ray(2.0, 0.5)).unwrap();
+        assert_eq!((t, n), (2.0, Vec3::new(0.0, 0.0, -1.0)));
+        assert_eq!(quad.uv(p), (0.75, 0.5));
+        assert!(quad.intersect(&ray(0.2, 0.5)).is_none());
+        assert!(quad.intersect(&ray(1.5, 1.5)).is_none());
+
+        assert_eq!(quad.area(), 2.0);
+        let b = quad.bounds();
+        assert_eq!((b.min, b.max), (Point3::new(0.0, 0.0, 2.0), Point3::new(3.0, 1.0, 2.0)));
+        assert_eq!(quad.sample((0.5, 1.0)).p, Point3::new(2.0, 1.0, 2.0));
+
+        check_derivatives(&quad, &Ray { p: Point3::new(1.0, -0.5, 0.0),
+                                        d: Vec3::new(0.4, 0.6, 1.0) });
+    }
+}
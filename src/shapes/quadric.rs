@@ -0,0 +1,221 @@
+use std::f32::consts::PI;
+
+use crate::geometry::{Bounds3, Point3, Ray, Transform, Vec3};
+use crate::interaction::{SurfaceInteraction, normal_derivatives};
+use crate::math::solve_quadratic;
+use crate::shapes::{Shape, ShapeSample};
+
+// A surface of revolution around the z axis of its own object space, where some quadratic
+// function of the position is zero. The surface is cut to a range of heights and only sweeps
+// phi_max radians around the axis.
+pub trait QuadricSurface: Send + Sync {
+    // Returns the coefficients (a, b, c) of a * t^2 + b * t + c, which is zero where the ray
+    // o + t * d hits the whole, uncut surface.
+    fn coefficients(&self, o: Point3, d: Vec3) -> (f32, f32, f32);
+
+    // The heights along z that the surface is cut to.
+    fn z_range(&self) -> (f32, f32);
+
+    fn phi_max(&self) -> f32;
+
+    // Returns the angle of |p| around the z axis from where the sweep starts, in [0, 2 pi).
+    fn phi(&self, p: Point3) -> f32 {
+        let phi = p.y.atan2(p.x);
+        if phi < 0.0 { phi + 2.0 * PI } else { phi }
+    }
+
+    // Returns the v parameter of |p|, which goes from 0 to 1 along the axis. u is always the
+    // fraction of phi_max that |p| is swept by.
+    fn v(&self, p: Point3) -> f32;
+
+    // Returns the normal at |p| pointing away from the axis. It doesn't need to be normalized.
+    fn normal(&self, p: Point3) -> Vec3;
+
+    // Returns the first and second derivatives of the position at |p| with respect to v. The
+    // derivatives with respect to u follow from the sweep around the axis.
+    fn v_derivatives(&self, p: Point3) -> (Vec3, Vec3);
+
+    // Returns the object space bounding box.
+    fn bounds(&self) -> Bounds3;
+
+    fn area(&self) -> f32;
+
+    // Samples a point on the surface with the density pdf().
+    fn sample(&self, u: (f32, f32)) -> Point3;
+
+    // The density with respect to area with which sample() picks |p|, which is uniform unless
+    // the surface overrides it.
+    fn pdf(&self, _p: Point3) -> f32 {
+        1.0 / self.area()
+    }
+
+    // Whether the point |p| on the whole surface is part of the cut one.
+    fn contains(&self, p: Point3) -> bool {
+        let (z_min, z_max) = self.z_range();
+        p.z >= z_min && p.z <= z_max && self.phi(p) <= self.phi_max()
+    }
+}
+
+// A quadric surface placed in the world by a rigid transform.
+pub struct Quadric<S: QuadricSurface> {
+    surface: S,
+    object_to_world: Transform,
+    world_to_object: Transform,
+}
+
+impl<S: QuadricSurface> Quadric<S> {
+    // |object_to_world| may only rotate and translate, since normals are transformed like any
+    // other direction, and |world_to_object| must be its inverse.
+    pub fn new(surface: S, object_to_world: Transform, world_to_object: Transform) -> Self {
+        Quadric { surface, object_to_world, world_to_object }
+    }
+
+    fn world_normal(&self, p: Point3) -> Vec3 {
+        Vec3::normalize(self.object_to_world.apply_vec(&self.surface.normal(p)))
+    }
+
+    // Returns the local geometry at the object space point |p|, which is at |t| along |ray|.
+    fn interaction(&self, ray: &Ray, t: f32, p: Point3) -> SurfaceInteraction<'_> {
+        let phi_max = self.surface.phi_max();
+        let uv = (self.surface.phi(p) / phi_max, self.surface.v(p));
+
+        // Sweeping by phi rotates the point, and its derivative with respect to v, around z.
+        let z = Vec3::new(0.0, 0.0, 1.0);
+        let radial = Vec3::new(p.x, p.y, 0.0);
+        let dpdu = phi_max * Vec3::cross(z, radial);
+        let (dpdv, d2pdvv) = self.surface.v_derivatives(p);
+        let d2pduu = -phi_max * phi_max * radial;
+        let d2pduv = phi_max * Vec3::cross(z, dpdv);
+
+        let n = Vec3::normalize(self.surface.normal(p));
+        let (dndu, dndv) = normal_derivatives(n, dpdu, dpdv, d2pduu, d2pduv, d2pdvv);
+
+        let to_world = |v: Vec3| self.object_to_world.apply_vec(&v);
+        SurfaceInteraction::new(ray, t, to_world(n), uv, to_world(dpdu), to_world(dpdv),
+                                to_world(dndu), to_world(dndv))
+    }
+}
+
+impl<S: QuadricSurface> Shape for Quadric<S> {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction<'_>> {
+        // Rigid transforms don't change the length of the direction, so t is the same in both
+        // spaces.
+        let o = self.world_to_object.apply_pt(&ray.p);
+        let d = self.world_to_object.apply_vec(&ray.d);
+
+        let (a, b, c) = self.surface.coefficients(o, d);
+        let (t0, t1) = solve_quadratic(a as f64, b as f64, c as f64)?;
+
+        // The near hit may be cut away, in which case the ray can see the far side through the
+        // gap.
+        for &t in &[t0 as f32, t1 as f32] {
+            if t < 0.0 {
+                continue;
+            }
+            let p = o + t * d;
+            if self.surface.contains(p) {
+                return Some(self.interaction(ray, t, p));
+            }
+        }
+
+        None
+    }
+
+    fn bounds(&self) -> Bounds3 {
+        self.object_to_world.apply_bounds(&self.surface.bounds())
+    }
+
+    fn uv(&self, p: Point3) -> (f32, f32) {
+        let p = self.world_to_object.apply_pt(&p);
+        (self.surface.phi(p) / self.surface.phi_max(), self.surface.v(p))
+    }
+
+    fn area(&self) -> f32 {
+        self.surface.area()
+    }
+
+    fn sample(&self, u: (f32, f32)) -> ShapeSample {
+        let p = self.surface.sample(u);
+        ShapeSample {
+            p: self.object_to_world.apply_pt(&p),
+            n: self.world_normal(p),
+            pdf: self.surface.pdf(p),
+        }
+    }
+
+    // Same as area_pdf_from(), but with the surface's own density in case it isn't uniform.
+    fn pdf_from(&self, p: Point3, wi: Vec3) -> f32 {
+        let si = match self.intersect(&Ray { p, d: wi }) {
+            Some(si) => si,
+            None => return 0.0,
+        };
+
+        let cos_theta = Vec3::dot(si.n, Vec3::normalize(wi)).abs();
+        if cos_theta == 0.0 {
+            return 0.0;
+        }
+        let pdf = self.surface.pdf(self.world_to_object.apply_pt(&si.p));
+        pdf * (si.p - p).len_sq() / cos_theta
+    }
+}
+
+// Converts an angle in degrees to radians in [0, 2 pi].
+pub fn phi_max_radians(degrees: f32) -> f32 {
+    degrees.clamp(0.0, 360.0).to_radians()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interaction::tests::check_derivatives;
+    use crate::shapes::{Cone, Cylinder, Hyperboloid, Paraboloid, PartialSphere};
+
+    #[test]
+    fn placement() {
+        // A cylinder standing up along +y at x = 5.
+        let to_world = Transform::translate(Vec3::new(5.0, 0.0, 0.0))
+            .compose(&Transform::rotate(-90.0, Vec3::new(1.0, 0.0, 0.0)));
+        let to_object = Transform::rotate(90.0, Vec3::new(1.0, 0.0, 0.0))
+            .compose(&Transform::translate(Vec3::new(-5.0, 0.0, 0.0)));
+        let cylinder = Quadric::new(Cylinder::new(1.0, 0.0, 2.0, 360.0), to_world, to_object);
+
+        let ray = Ray { p: Point3::new(0.0, 1.5, 0.0), d: Vec3::new(1.0, 0.0, 0.0) };
+        let SurfaceInteraction { t, p, n, .. } = cylinder.intersect(&ray).unwrap();
+        assert!((t - 4.0).abs() < 1e-5);
+        assert!((n - Vec3::new(-1.0, 0.0, 0.0)).len() < 1e-5);
+        let (u, v) = cylinder.uv(p);
+        assert!((u - 0.5).abs() < 1e-5 && (v - 0.75).abs() < 1e-5);
+        let ray = Ray { p: Point3::new(0.0, 2.5, 0.0), d: Vec3::new(1.0, 0.0, 0.0) };
+        assert!(cylinder.intersect(&ray).is_none());
+
+        let b = cylinder.bounds();
+        assert!((b.min - Point3::new(4.0, 0.0, -1.0)).len() < 1e-5);
+        assert!((b.max - Point3::new(6.0, 2.0, 1.0)).len() < 1e-5);
+
+        let s = cylinder.sample((0.25, 0.5));
+        assert!((s.p - Point3::new(4.0, 0.5, 0.0)).len() < 1e-5);
+        assert!((s.n - Vec3::new(-1.0, 0.0, 0.0)).len() < 1e-5);
+    }
+
+    #[test]
+    fn derivatives() {
+        fn check<S: QuadricSurface>(surface: S) {
+            let to_world = Transform::translate(Vec3::new(1.0, 2.0, 3.0))
+                .compose(&Transform::rotate(30.0, Vec3::new(1.0, 1.0, 0.0)));
+            let to_object = Transform::rotate(-30.0, Vec3::new(1.0, 1.0, 0.0))
+                .compose(&Transform::translate(Vec3::new(-1.0, -2.0, -3.0)));
+
+            // From outside towards a point on the side facing +x and +y, away from the seam.
+            let target = to_world.apply_pt(&Point3::new(0.3, 0.4, 0.6));
+            let p = to_world.apply_pt(&Point3::new(4.0, 5.0, 1.0));
+            let quadric = Quadric::new(surface, to_world, to_object);
+            check_derivatives(&quadric, &Ray { p, d: target - p });
+        }
+
+        check(Cylinder::new(1.0, 0.0, 2.0, 300.0));
+        check(Cone::new(1.5, 2.0, 300.0));
+        check(Paraboloid::new(1.5, 0.2, 2.0, 300.0));
+        check(Hyperboloid::new(Point3::new(1.0, 0.0, 0.0), Point3::new(0.5, 1.0, 2.0), 300.0));
+        check(PartialSphere::new(1.0, -0.5, 0.9, 300.0));
+    }
+}
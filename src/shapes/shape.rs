@@ -1,6 +1,92 @@
-use crate::geometry::{Point3, Vec3, Ray};
+use crate::geometry::{Bounds3, Point3, Vec3, Ray};
+use crate::interaction::SurfaceInteraction;
 
-pub trait Shape {
-    // If there's an intersection, returns the point of intersection and the normal.
-    fn intersect(&self, ray: &Ray) -> Option<(f32, Point3, Vec3)>;
+// A point sampled on the surface of a shape.
+pub struct ShapeSample {
+    pub p: Point3,
+    // The normalized surface normal at |p|.
+    pub n: Vec3,
+    // The density of the sample, either with respect to area or to solid angle depending on how
+    // it was sampled.
+    pub pdf: f32,
+}
+
+// Shapes are shared between the render threads.
+pub trait Shape: Send + Sync {
+    // If there's an intersection, returns the local geometry at the closest one in front of the
+    // ray's origin.
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction<'_>>;
+
+    // Returns the world space bounding box of the shape.
+    fn bounds(&self) -> Bounds3;
+
+    // Shapes such as triangle meshes are made of many parts, which the Bvh builds its hierarchy
+    // over and refers to by index. Other shapes are a single part, 0.
+    fn num_parts(&self) -> u32 {
+        1
+    }
+
+    fn part_bounds(&self, _part: u32) -> Bounds3 {
+        self.bounds()
+    }
+
+    // Same as intersect(), but only with the part |part|.
+    fn intersect_part(&self, _part: u32, ray: &Ray) -> Option<SurfaceInteraction<'_>> {
+        self.intersect(ray)
+    }
+
+    // Returns the surface parameterization of the point |p| on the shape, which texture lookups
+    // use.
+    fn uv(&self, p: Point3) -> (f32, f32);
+
+    fn area(&self) -> f32;
+
+    // Samples a point uniformly over the surface using |u|. The pdf is with respect to area.
+    fn sample(&self, u: (f32, f32)) -> ShapeSample;
+
+    // Samples a point on the shape as seen from |p|, with the pdf with respect to solid angle at
+    // |p|. Shapes can override this to only sample the part of the surface that's visible from
+    // |p|; by default the whole surface is sampled uniformly.
+    fn sample_from(&self, p: Point3, u: (f32, f32)) -> Option<ShapeSample> {
+        sample_area_from(self, p, u)
+    }
+
+    // Returns the density with which sample_from() samples the point that the ray leaving |p| in
+    // direction |wi| hits, or 0 if it misses the shape.
+    fn pdf_from(&self, p: Point3, wi: Vec3) -> f32 {
+        area_pdf_from(self, p, wi)
+    }
+}
+
+// Samples |shape| uniformly by area and converts the pdf to solid angle at |p|.
+pub fn sample_area_from<S: Shape + ?Sized>(shape: &S, p: Point3, u: (f32, f32))
+                                           -> Option<ShapeSample> {
+    let mut s = shape.sample(u);
+    let wi = s.p - p;
+    let dist_sq = wi.len_sq();
+    if dist_sq == 0.0 {
+        return None;
+    }
+
+    // dA = r^2 / cos(theta) dw, where theta is the angle at the sampled point.
+    let cos_theta = Vec3::dot(s.n, Vec3::normalize(wi)).abs();
+    if cos_theta == 0.0 {
+        return None;
+    }
+    s.pdf *= dist_sq / cos_theta;
+    Some(s)
+}
+
+// The density of sample_area_from() for the direction |wi| from |p|.
+pub fn area_pdf_from<S: Shape + ?Sized>(shape: &S, p: Point3, wi: Vec3) -> f32 {
+    let si = match shape.intersect(&Ray { p, d: wi }) {
+        Some(si) => si,
+        None => return 0.0,
+    };
+
+    let cos_theta = Vec3::dot(si.n, Vec3::normalize(wi)).abs();
+    if cos_theta == 0.0 {
+        return 0.0;
+    }
+    (si.p - p).len_sq() / (cos_theta * shape.area())
 }
@@ -1,5 +1,10 @@
-use crate::geometry::{Point3, Vec3, Ray};
-use crate::shapes::Shape;
+use std::f32::consts::PI;
+
+use crate::geometry::{Bounds3, Frame, Point3, Vec3, Ray};
+use crate::interaction::SurfaceInteraction;
+use crate::sampling::warp::{uniform_cone_pdf, uniform_sample_sphere, uniform_sphere_pdf};
+use crate::shapes::{Shape, ShapeSample};
+use crate::shapes::shape::{area_pdf_from, sample_area_from};
 
 pub struct Sphere {
    pub c: Point3, // center
@@ -7,8 +12,9 @@ pub struct Sphere {
 }
 
 impl Shape for Sphere {
-    fn intersect(&self, ray: &Ray) -> Option<(f32, Point3, Vec3)> {
-        let d = Vec3::normalize(ray.d);
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction<'_>> {
+        // The direction isn't normalized so that t is in the same units as for the other shapes.
+        let d = ray.d;
         let v = ray.p - self.c;
 
         let a = Vec3::dot(d, d);
@@ -21,21 +27,130 @@ impl Shape for Sphere {
             return None;
         }
 
-        let t = if discrm > 0.0 {
-            let t1 = (-b + discrm.sqrt()) / (2.0 * a);
-            let t2 = (-b - discrm.sqrt()) / (2.0 * a);
-            t1.min(t2)
+        // Use the nearest intersection in front of the ray origin, which is the far one when the
+        // ray starts inside the sphere.
+        let sqrt_discrm = discrm.sqrt();
+        let t_near = (-b - sqrt_discrm) / (2.0 * a);
+        let t_far = (-b + sqrt_discrm) / (2.0 * a);
+        let t = if t_near >= 0.0 {
+            t_near
+        } else if t_far >= 0.0 {
+            t_far
         } else {
-            -b / (2.0 * a)
+            return None;
         };
 
+        // The normal always points out of the sphere.
         let pi = ray.p + t * d;
         let n = Vec3::normalize(pi - self.c);
 
-        if t < 0.0 {
-            None
-        } else {
-            Some((t, pi, n))
+        let uv = self.uv(pi);
+        let (sin_phi, cos_phi) = (2.0 * PI * uv.0).sin_cos();
+        let (sin_theta, cos_theta) = (PI * uv.1).sin_cos();
+        let dpdu = 2.0 * PI * self.r * Vec3::new(-sin_theta * sin_phi, 0.0, sin_theta * cos_phi);
+        let dpdv = PI * self.r * Vec3::new(cos_theta * cos_phi, -sin_theta, cos_theta * sin_phi);
+        // The normal is the offset from the center over the radius, and changes the same way.
+        Some(SurfaceInteraction::new(ray, t, n, uv, dpdu, dpdv, dpdu / self.r, dpdv / self.r))
+    }
+
+    fn bounds(&self) -> Bounds3 {
+        let r = Vec3::new(self.r, self.r, self.r);
+        Bounds3::new(self.c - r, self.c + r)
+    }
+
+    // u goes around the +y axis starting at +x, and v from the top to the bottom, as in an
+    // equirectangular map.
+    fn uv(&self, p: Point3) -> (f32, f32) {
+        let d = Vec3::normalize(p - self.c);
+        let mut phi = d.z.atan2(d.x);
+        if phi < 0.0 {
+            phi += 2.0 * PI;
         }
+        (phi / (2.0 * PI), d.y.clamp(-1.0, 1.0).acos() / PI)
+    }
+
+    fn area(&self) -> f32 {
+        4.0 * PI * self.r * self.r
+    }
+
+    fn sample(&self, u: (f32, f32)) -> ShapeSample {
+        let n = uniform_sample_sphere(u);
+        ShapeSample { p: self.c + self.r * n, n, pdf: uniform_sphere_pdf() / (self.r * self.r) }
+    }
+
+    // Samples the cone of directions subtended by the sphere when |p| is outside it, which only
+    // ever picks visible points.
+    fn sample_from(&self, p: Point3, u: (f32, f32)) -> Option<ShapeSample> {
+        let dc_sq = (self.c - p).len_sq();
+        if dc_sq <= self.r * self.r {
+            return sample_area_from(self, p, u);
+        }
+
+        let dc = dc_sq.sqrt();
+        let sin_theta_max = self.r / dc;
+        let cos_theta_max = (1.0 - sin_theta_max * sin_theta_max).max(0.0).sqrt();
+
+        let cos_theta = (1.0 - u.0) + u.0 * cos_theta_max;
+        let sin_theta_sq = (1.0 - cos_theta * cos_theta).max(0.0);
+        let phi = 2.0 * PI * u.1;
+
+        // Rather than intersecting the sampled direction with the sphere, find the angle alpha
+        // at the center between the direction to |p| and the sampled point.
+        let ds = dc * cos_theta - (self.r * self.r - dc_sq * sin_theta_sq).max(0.0).sqrt();
+        let cos_alpha = (dc_sq + self.r * self.r - ds * ds) / (2.0 * dc * self.r);
+        let sin_alpha = (1.0 - cos_alpha * cos_alpha).max(0.0).sqrt();
+
+        let frame = Frame::from_normal((p - self.c) / dc);
+        let n = frame.local_to_world(Vec3::new(sin_alpha * phi.cos(),
+                                               sin_alpha * phi.sin(),
+                                               cos_alpha));
+
+        Some(ShapeSample { p: self.c + self.r * n, n, pdf: uniform_cone_pdf(cos_theta_max) })
+    }
+
+    fn pdf_from(&self, p: Point3, wi: Vec3) -> f32 {
+        let dc_sq = (self.c - p).len_sq();
+        if dc_sq <= self.r * self.r {
+            return area_pdf_from(self, p, wi);
+        }
+
+        if self.intersect(&Ray { p, d: wi }).is_none() {
+            return 0.0;
+        }
+        let sin_theta_max_sq = self.r * self.r / dc_sq;
+        uniform_cone_pdf((1.0 - sin_theta_max_sq).max(0.0).sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interaction::tests::check_derivatives;
+
+    #[test]
+    fn intersect_from_inside() {
+        let sphere = Sphere { c: Point3::new(0.0, 0.0, 0.0), r: 2.0 };
+
+        let ray = Ray { p: Point3::new(0.0, 0.0, -5.0), d: Vec3::new(0.0, 0.0, 1.0) };
+        let SurfaceInteraction { t, n, .. } = sphere.intersect(&ray).unwrap();
+        assert_eq!(t, 3.0);
+        assert_eq!(n, Vec3::new(0.0, 0.0, -1.0));
+
+        let ray = Ray { p: Point3::new(0.0, 0.0, 1.0), d: Vec3::new(0.0, 0.0, 1.0) };
+        let SurfaceInteraction { t, n, .. } = sphere.intersect(&ray).unwrap();
+        assert_eq!(t, 1.0);
+        assert_eq!(n, Vec3::new(0.0, 0.0, 1.0));
+
+        let ray = Ray { p: Point3::new(0.0, 0.0, 3.0), d: Vec3::new(0.0, 0.0, 1.0) };
+        assert!(sphere.intersect(&ray).is_none());
+    }
+
+    #[test]
+    fn derivatives() {
+        let sphere = Sphere { c: Point3::new(1.0, -1.0, 2.0), r: 2.0 };
+        check_derivatives(&sphere, &Ray { p: Point3::new(4.0, 3.0, 5.0),
+                                          d: Vec3::new(-1.0, -1.5, -1.0) });
+        check_derivatives(&sphere, &Ray { p: Point3::new(1.0, -1.0, 2.0),
+                                          d: Vec3::new(-0.5, 0.7, -0.2) });
     }
 }
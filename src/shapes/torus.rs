@@ -0,0 +1,255 @@
+use std::f32::consts::PI;
+
+use crate::geometry::{Bounds3, Point3, Ray, Transform, Vec3};
+use crate::interaction::SurfaceInteraction;
+use crate::math::solve_quartic;
+use crate::shapes::{Shape, ShapeSample};
+
+// A torus around the z axis of its own object space. The center of the tube is a circle of
+// |major_radius| in the xy plane, and the tube has |minor_radius|.
+pub struct Torus {
+    major_radius: f32,
+    minor_radius: f32,
+    object_to_world: Transform,
+    world_to_object: Transform,
+}
+
+impl Torus {
+    // |minor_radius| must be less than |major_radius|. |object_to_world| may only rotate and
+    // translate, and |world_to_object| must be its inverse.
+    pub fn new(major_radius: f32, minor_radius: f32, object_to_world: Transform,
+               world_to_object: Transform) -> Self {
+        Torus { major_radius, minor_radius, object_to_world, world_to_object }
+    }
+
+    // The object space normal at |p|, which points away from the nearest point on the circle
+    // at the center of the tube.
+    fn normal(&self, p: Point3) -> Vec3 {
+        let r = (p.x * p.x + p.y * p.y).sqrt();
+        let scale = if r == 0.0 { 0.0 } else { self.major_radius / r };
+        Vec3::normalize(Vec3::new(p.x - scale * p.x, p.y - scale * p.y, p.z))
+    }
+
+    // u goes around the z axis starting at +x, and v around the tube starting at the outer
+    // equator and going up first.
+    fn object_uv(&self, p: Point3) -> (f32, f32) {
+        let wrap = |angle: f32| if angle < 0.0 { angle + 2.0 * PI } else { angle };
+        let phi = wrap(p.y.atan2(p.x));
+        let theta = wrap(p.z.atan2((p.x * p.x + p.y * p.y).sqrt() - self.major_radius));
+        (phi / (2.0 * PI), theta / (2.0 * PI))
+    }
+
+    // Inverts the CDF of the angle around the tube, (theta + k * sin(theta)) / (2 pi) with
+    // k = minor_radius / major_radius. It's monotonic since k < 1, which keeps Newton's method
+    // within the bracket that bisection maintains.
+    fn sample_theta(&self, u: f32) -> f32 {
+        let k = self.minor_radius / self.major_radius;
+        let target = 2.0 * PI * u;
+        let (mut lo, mut hi) = (0.0, 2.0 * PI);
+        let mut theta = target;
+        for _ in 0..20 {
+            let f = theta + k * theta.sin() - target;
+            if f.abs() < 1e-6 {
+                break;
+            }
+            if f > 0.0 {
+                hi = theta;
+            } else {
+                lo = theta;
+            }
+            let next = theta - f / (1.0 + k * theta.cos());
+            theta = if next > lo && next < hi { next } else { 0.5 * (lo + hi) };
+        }
+        theta
+    }
+}
+
+impl Shape for Torus {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction<'_>> {
+        let o = self.world_to_object.apply_pt(&ray.p);
+        let d = self.world_to_object.apply_vec(&ray.d);
+        let (ox, oy, oz) = (o.x as f64, o.y as f64, o.z as f64);
+        let (dx, dy, dz) = (d.x as f64, d.y as f64, d.z as f64);
+        let (major, minor) = (self.major_radius as f64, self.minor_radius as f64);
+
+        let dd = dx * dx + dy * dy + dz * dz;
+        if dd == 0.0 {
+            return None;
+        }
+
+        // Solving from the point on the ray closest to the center keeps the coefficients small,
+        // and the roots accurate, when the ray starts far away.
+        let t_shift = -(ox * dx + oy * dy + oz * dz) / dd;
+        let (ox, oy, oz) = (ox + t_shift * dx, oy + t_shift * dy, oz + t_shift * dz);
+        let oo = ox * ox + oy * oy + oz * oz;
+        if oo > (major + minor) * (major + minor) {
+            return None;
+        }
+
+        // Substituting the ray into (x^2 + y^2 + z^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + y^2).
+        let od = ox * dx + oy * dy + oz * dz;
+        let k = oo + major * major - minor * minor;
+        let four_r_sq = 4.0 * major * major;
+        let roots = solve_quartic(dd * dd,
+                                  4.0 * dd * od,
+                                  2.0 * dd * k + 4.0 * od * od - four_r_sq * (dx * dx + dy * dy),
+                                  4.0 * od * k - 2.0 * four_r_sq * (ox * dx + oy * dy),
+                                  k * k - four_r_sq * (ox * ox + oy * oy));
+
+        let t = roots.as_slice().iter().map(|t| t + t_shift).find(|&t| t >= 0.0)? as f32;
+        let p = o + t * d;
+        let n = self.normal(p);
+        let uv = self.object_uv(p);
+
+        // Sweeping around the z axis rotates the normal along with the point, and going around
+        // the tube moves the point by the change in the normal times the minor radius.
+        let (sin_theta, cos_theta) = (2.0 * PI * uv.1).sin_cos();
+        let (sin_phi, cos_phi) = (2.0 * PI * uv.0).sin_cos();
+        let z = Vec3::new(0.0, 0.0, 1.0);
+        let dpdu = 2.0 * PI * Vec3::cross(z, Vec3::new(p.x, p.y, 0.0));
+        let dndv = 2.0 * PI * Vec3::new(-sin_theta * cos_phi, -sin_theta * sin_phi, cos_theta);
+        let dndu = 2.0 * PI * Vec3::cross(z, n);
+
+        let to_world = |v: Vec3| self.object_to_world.apply_vec(&v);
+        Some(SurfaceInteraction::new(ray, t, to_world(n), uv, to_world(dpdu),
+                                     to_world(self.minor_radius * dndv), to_world(dndu),
+                                     to_world(dndv)))
+    }
+
+    fn bounds(&self) -> Bounds3 {
+        let r = self.major_radius + self.minor_radius;
+        let b = Bounds3::new(Point3::new(-r, -r, -self.minor_radius),
+                             Point3::new(r, r, self.minor_radius));
+        self.object_to_world.apply_bounds(&b)
+    }
+
+    fn uv(&self, p: Point3) -> (f32, f32) {
+        self.object_uv(self.world_to_object.apply_pt(&p))
+    }
+
+    fn area(&self) -> f32 {
+        4.0 * PI * PI * self.major_radius * self.minor_radius
+    }
+
+    // The outside of the tube has more area than the inside, in proportion to the distance from
+    // the axis.
+    fn sample(&self, u: (f32, f32)) -> ShapeSample {
+        let theta = self.sample_theta(u.0);
+        let phi = 2.0 * PI * u.1;
+        let r = self.major_radius + self.minor_radius * theta.cos();
+        let p = Point3::new(r * phi.cos(), r * phi.sin(), self.minor_radius * theta.sin());
+        ShapeSample {
+            p: self.object_to_world.apply_pt(&p),
+            n: self.object_to_world.apply_vec(&self.normal(p)),
+            pdf: 1.0 / self.area(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interaction::tests::check_derivatives;
+
+    fn torus() -> Torus {
+        Torus::new(2.0, 0.5, Transform::identity(), Transform::identity())
+    }
+
+    fn ray(p: (f32, f32, f32), d: (f32, f32, f32)) -> Ray {
+        Ray { p: Point3::new(p.0, p.1, p.2), d: Vec3::new(d.0, d.1, d.2) }
+    }
+
+    #[test]
+    fn intersect() {
+        let torus = torus();
+
+        // Through the middle of the tube on both sides.
+        let SurfaceInteraction { t, p, n, .. } =
+            torus.intersect(&ray((-5.0, 0.0, 0.0), (1.0, 0.0, 0.0))).unwrap();
+        assert!((t - 2.5).abs() < 1e-5);
+        assert!((n - Vec3::new(-1.0, 0.0, 0.0)).len() < 1e-5);
+        let (u, v) = torus.uv(p);
+        assert!((u - 0.5).abs() < 1e-5 && v.abs() < 1e-5);
+
+        // From inside the hole, the inner equator faces the ray.
+        let SurfaceInteraction { t, n, .. } =
+            torus.intersect(&ray((0.0, 0.0, 0.0), (0.0, 1.0, 0.0))).unwrap();
+        assert!((t - 1.5).abs() < 1e-5);
+        assert!((n - Vec3::new(0.0, -1.0, 0.0)).len() < 1e-5);
+
+        // Down through the tube and through the hole.
+        let SurfaceInteraction { t, p, n, .. } =
+            torus.intersect(&ray((2.0, 0.0, 3.0), (0.0, 0.0, -1.0))).unwrap();
+        assert!((t - 2.5).abs() < 1e-5);
+        assert!((n - Vec3::new(0.0, 0.0, 1.0)).len() < 1e-5);
+        assert!((torus.uv(p).1 - 0.25).abs() < 1e-5);
+        assert!(torus.intersect(&ray((0.0, 0.0, 3.0), (0.0, 0.0, -1.0))).is_none());
+        assert!(torus.intersect(&ray((-5.0, 0.0, 0.6), (1.0, 0.0, 0.0))).is_none());
+    }
+
+    #[test]
+    fn grazing_and_distant_rays() {
+        let torus = torus();
+
+        // Rays just below and above the top of the tube, where the roots are nearly double.
+        let SurfaceInteraction { t, n, .. } =
+            torus.intersect(&ray((-5.0, 0.0, 0.4999), (1.0, 0.0, 0.0))).unwrap();
+        assert!((t - 3.0).abs() < 0.02);
+        assert!(n.z > 0.99);
+        assert!(torus.intersect(&ray((-5.0, 0.0, 0.5001), (1.0, 0.0, 0.0))).is_none());
+
+        // Tangent to the outer equator.
+        let SurfaceInteraction { t, .. } =
+            torus.intersect(&ray((-5.0, 2.4999, 0.0), (1.0, 0.0, 0.0))).unwrap();
+        assert!((t - 5.0).abs() < 0.05);
+
+        // From far away, with a direction that isn't normalized.
+        let SurfaceInteraction { t, p, .. } =
+            torus.intersect(&ray((-1e4, 0.0, 0.0), (2.0, 0.0, 0.0))).unwrap();
+        assert!((t - (1e4 - 2.5) / 2.0).abs() < 1e-2);
+        assert!((p.x + 2.5).abs() < 1e-2);
+        let SurfaceInteraction { p, .. } =
+            torus.intersect(&ray((2.0, 0.0, 1e4), (0.0, 0.0, -1.0))).unwrap();
+        assert!((p.z - 0.5).abs() < 1e-2);
+    }
+
+    #[test]
+    fn placement_and_sampling() {
+        let to_world = Transform::translate(Vec3::new(0.0, 1.0, 0.0))
+            .compose(&Transform::rotate(-90.0, Vec3::new(1.0, 0.0, 0.0)));
+        let to_object = Transform::rotate(90.0, Vec3::new(1.0, 0.0, 0.0))
+            .compose(&Transform::translate(Vec3::new(0.0, -1.0, 0.0)));
+        let torus = Torus::new(2.0, 0.5, to_world, to_object);
+
+        // Lying flat at y = 1.
+        let SurfaceInteraction { t, n, .. } =
+            torus.intersect(&ray((2.0, 5.0, 0.0), (0.0, -1.0, 0.0))).unwrap();
+        assert!((t - 3.5).abs() < 1e-4);
+        assert!((n - Vec3::new(0.0, 1.0, 0.0)).len() < 1e-4);
+        let b = torus.bounds();
+        assert!((b.min - Point3::new(-2.5, 0.5, -2.5)).len() < 1e-5);
+        assert!((b.max - Point3::new(2.5, 1.5, 2.5)).len() < 1e-5);
+
+        // A quarter of the area is below the angle that 0.25 maps to, which is less than a
+        // quarter turn since the outside of the tube is larger.
+        let theta = torus.sample_theta(0.25);
+        assert!(theta < 0.5 * PI && (theta + 0.25 * theta.sin() - 0.5 * PI).abs() < 1e-5);
+        assert!((torus.sample_theta(0.5) - PI).abs() < 1e-5);
+
+        for &u in &[(0.0, 0.0), (0.2, 0.7), (0.6, 0.1), (0.999, 0.5)] {
+            let s = torus.sample(u);
+            let p = torus.world_to_object.apply_pt(&s.p);
+            let r = (p.x * p.x + p.y * p.y).sqrt();
+            assert!(((r - 2.0).powi(2) + p.z * p.z - 0.25).abs() < 1e-4);
+            let back = Ray { p: s.p + 0.1 * s.n, d: -s.n };
+            assert!((torus.intersect(&back).unwrap().t - 0.1).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn derivatives() {
+        let torus = torus();
+        check_derivatives(&torus, &ray((4.0, 3.0, 2.0), (-2.5, -1.5, -1.8)));
+        check_derivatives(&torus, &ray((0.0, 0.0, 0.1), (0.7, 1.0, 0.05)));
+    }
+}
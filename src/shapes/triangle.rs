@@ -1,5 +1,7 @@
-use crate::geometry::{Point3, Ray, Vec3};
-use crate::shapes::Shape;
+use crate::geometry::{Bounds3, Point3, Ray, Vec3};
+use crate::interaction::SurfaceInteraction;
+use crate::sampling::warp::uniform_sample_triangle;
+use crate::shapes::{Shape, ShapeSample};
 
 pub struct Triangle {
     pub p0: Point3,
@@ -8,50 +10,138 @@ pub struct Triangle {
 }
 
 impl Shape for Triangle {
-    fn intersect(&self, ray: &Ray) -> Option<(f32, Point3, Vec3)> {
-        let vp = ray.p - self.p0;
-        let v1 = self.p1 - self.p0;
-        let v2 = self.p2 - self.p0;
-    
-        let v_tmpd = Vec3::cross(ray.d, v2); 
-        let v_tmpp = Vec3::cross(vp, v1); 
-
-        let s = 1.0 / Vec3::dot(v_tmpd, v1);
-
-        let u = s * Vec3::dot(v_tmpd, vp);
-        if u < 0.0 || u > 1.0 {
-            return None;
-        }
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction<'_>> {
+        let (t, b1, b2) = intersect_triangle(ray, self.p0, self.p1, self.p2)?;
 
-        let v = s * Vec3::dot(v_tmpp, ray.d);
-        if v < 0.0 || v > 1.0 {
-            return None;
-        }
+        // Triangles are two-sided and face the ray. The winding order decides which side is the
+        // front.
+        let (dpdu, dpdv) = (self.p1 - self.p0, self.p2 - self.p0);
+        let n = Vec3::normalize(Vec3::cross(dpdu, dpdv));
+        let mut si = SurfaceInteraction::new(ray, t, n, (b1, b2), dpdu, dpdv, Vec3::zeroes(),
+                                             Vec3::zeroes());
+        si.face_ray();
+        Some(si)
+    }
 
-        if u + v < 0.0 || u + v > 1.0 {
-            return None;
-        }
-    
-        let t = s * Vec3::dot(v_tmpp, v2); 
-        
-        if t >= 0.0 { 
-            let pi = ray.p + t * ray.d;
-
-            let mut n = Vec3::normalize(Vec3::cross(v1, v2));
-            if Vec3::dot(-ray.d, n) < 0.0 {
-                n = -n;
-            }
-            // TODO: Test that the normal returned is correct.
-            Some((t, pi, n))
-        } else {
-            None
-        }
+    fn bounds(&self) -> Bounds3 {
+        Bounds3::union_pt(Bounds3::new(self.p0, self.p1), self.p2)
+    }
+
+    // The barycentric coordinates of |p| for |p1| and |p2|.
+    fn uv(&self, p: Point3) -> (f32, f32) {
+        barycentrics(p, self.p0, self.p1, self.p2)
+    }
+
+    fn area(&self) -> f32 {
+        0.5 * Vec3::cross(self.p1 - self.p0, self.p2 - self.p0).len()
+    }
+
+    fn sample(&self, u: (f32, f32)) -> ShapeSample {
+        let (b0, b1) = uniform_sample_triangle(u);
+        let p = self.p0 + b1 * (self.p1 - self.p0) + (1.0 - b0 - b1) * (self.p2 - self.p0);
+        let n = Vec3::normalize(Vec3::cross(self.p1 - self.p0, self.p2 - self.p0));
+        ShapeSample { p, n, pdf: 1.0 / self.area() }
     }
 }
 
+// Intersects |ray| with the triangle |p0|, |p1|, |p2| from either side. Returns t and the
+// barycentric coordinates of the hit for |p1| and |p2|.
+//
+// This is the watertight algorithm of Woop, Benthin and Wald. The vertices are moved into a
+// space where the ray starts at the origin and points along +z, so whether the ray passes
+// inside an edge only depends on the sign of that edge's 2D edge function. Neighbouring
+// triangles evaluate the function for a shared edge from the same two vertices, so a ray can't
+// slip between them.
+pub fn intersect_triangle(ray: &Ray, p0: Point3, p1: Point3,
+                          p2: Point3) -> Option<(f32, f32, f32)> {
+    // Permute the axes so that z is the largest dimension of the direction, which keeps the
+    // shear below well conditioned.
+    let abs_d = Vec3::new(ray.d.x.abs(), ray.d.y.abs(), ray.d.z.abs());
+    let kz = if abs_d.x > abs_d.y {
+        if abs_d.x > abs_d.z { 0 } else { 2 }
+    } else if abs_d.y > abs_d.z {
+        1
+    } else {
+        2
+    };
+    if ray.d[kz] == 0.0 {
+        return None;
+    }
+    let kx = (kz + 1) % 3;
+    let ky = (kx + 1) % 3;
+
+    let shear_x = -ray.d[kx] / ray.d[kz];
+    let shear_y = -ray.d[ky] / ray.d[kz];
+    let shear_z = 1.0 / ray.d[kz];
+
+    // The vertices relative to the ray origin, permuted and sheared in x and y.
+    let transform = |p: Point3| {
+        let v = p - ray.p;
+        (v[kx] + shear_x * v[kz], v[ky] + shear_y * v[kz], v[kz])
+    };
+    let (x0, y0, z0) = transform(p0);
+    let (x1, y1, z1) = transform(p1);
+    let (x2, y2, z2) = transform(p2);
+
+    let mut e0 = x1 * y2 - y1 * x2;
+    let mut e1 = x2 * y0 - y2 * x0;
+    let mut e2 = x0 * y1 - y0 * x1;
+
+    // A zero edge function means the ray passes exactly through an edge or a vertex, where the
+    // sign decides which triangle gets the hit, so it's recomputed without the rounding error.
+    if e0 == 0.0 || e1 == 0.0 || e2 == 0.0 {
+        let edge = |xa: f32, ya: f32, xb: f32, yb: f32| {
+            (xa as f64 * yb as f64 - ya as f64 * xb as f64) as f32
+        };
+        e0 = edge(x1, y1, x2, y2);
+        e1 = edge(x2, y2, x0, y0);
+        e2 = edge(x0, y0, x1, y1);
+    }
+
+    if (e0 < 0.0 || e1 < 0.0 || e2 < 0.0) && (e0 > 0.0 || e1 > 0.0 || e2 > 0.0) {
+        return None;
+    }
+    // Zero for degenerate triangles and rays in the plane of the triangle.
+    let det = e0 + e1 + e2;
+    if det == 0.0 || !det.is_finite() {
+        return None;
+    }
+
+    // t is interpolated from the z of the vertices, scaled so that the direction has length 1
+    // along z. Its sign is compared with the determinant's before dividing.
+    let t_scaled = shear_z * (e0 * z0 + e1 * z1 + e2 * z2);
+    if (det < 0.0 && t_scaled > 0.0) || (det > 0.0 && t_scaled < 0.0) {
+        return None;
+    }
+
+    // Dividing each of them, rather than multiplying by 1 / det, keeps tiny triangles from
+    // overflowing.
+    let t = t_scaled / det;
+    if !t.is_finite() {
+        return None;
+    }
+    Some((t, e1 / det, e2 / det))
+}
+
+// Returns the barycentric coordinates for |p1| and |p2| of the point |p| projected onto the
+// plane of the triangle |p0|, |p1|, |p2|.
+pub fn barycentrics(p: Point3, p0: Point3, p1: Point3, p2: Point3) -> (f32, f32) {
+    let v1 = p1 - p0;
+    let v2 = p2 - p0;
+    let vp = p - p0;
+    let (d11, d12, d22) = (Vec3::dot(v1, v1), Vec3::dot(v1, v2), Vec3::dot(v2, v2));
+    let (dp1, dp2) = (Vec3::dot(vp, v1), Vec3::dot(vp, v2));
+    let denom = d11 * d22 - d12 * d12;
+    if denom == 0.0 {
+        return (0.0, 0.0);
+    }
+    ((d22 * dp1 - d12 * dp2) / denom, (d11 * dp2 - d12 * dp1) / denom)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::interaction::tests::check_derivatives;
 
     #[test]
     fn intersect() {
@@ -67,11 +157,11 @@ mod tests {
         };
 
         match triangle1.intersect(&ray1) {
-            Some((_, p, n)) => {
-                assert_eq!(p, Point3::new(0.0, 0.0, 2.0));
-                assert_eq!(n, Vec3::new(0.0, 0.0, -1.0));
+            Some(si) => {
+                assert_eq!(si.p, Point3::new(0.0, 0.0, 2.0));
+                assert_eq!(si.n, Vec3::new(0.0, 0.0, -1.0));
             },
-            None => assert!(false),
+            None => panic!("expected an intersection"),
         }
 
         let triangle2 = Triangle {
@@ -80,10 +170,10 @@ mod tests {
             p2: Point3::new(0.0, 1.0, -2.0),
         };
 
-        match triangle2.intersect(&ray1) {
-            Some(_) => assert!(false),
-            None => assert!(true),
-        }
+        assert!(triangle2.intersect(&ray1).is_none());
+
+        check_derivatives(&triangle1, &Ray { p: Point3::new(0.5, -0.5, 0.0),
+                                             d: Vec3::new(-0.2, 0.3, 1.0) });
     }
 
     #[test]
@@ -99,29 +189,180 @@ mod tests {
             d: Vec3::new(0.0, 0.0, 1.0),
         };
 
-        match triangle.intersect(&ray1) {
-            Some(_) => assert!(false),
-            None => assert!(true),
-        }
+        assert!(triangle.intersect(&ray1).is_none());
 
         let ray2 = Ray {
             p: Point3::new(2.0, 2.0, 0.0),
             d: Vec3::new(0.0, 0.0, 1.0),
         };
 
-        match triangle.intersect(&ray2) {
-            Some(_) => assert!(false),
-            None => assert!(true),
-        }
+        assert!(triangle.intersect(&ray2).is_none());
 
         let ray3 = Ray {
             p: Point3::new(0.0, -2.0, 0.0),
             d: Vec3::new(0.0, 0.0, 1.0),
         };
 
-        match triangle.intersect(&ray3) {
-            Some(_) => assert!(false),
-            None => assert!(true),
+        assert!(triangle.intersect(&ray3).is_none());
+    }
+
+    #[test]
+    fn degenerate() {
+        let p = Point3::new(0.0, 0.0, 2.0);
+        let ray = Ray { p: Point3::zeroes(), d: Vec3::new(0.0, 0.0, 1.0) };
+
+        // Collinear and coincident vertices, including the point the ray goes through.
+        let line = [p, Point3::new(1.0, 1.0, 2.0), Point3::new(-1.0, -1.0, 2.0)];
+        assert!(intersect_triangle(&ray, line[0], line[1], line[2]).is_none());
+        assert!(intersect_triangle(&ray, p, p, p).is_none());
+
+        // A ray in the plane of the triangle and a ray without a direction.
+        let (p0, p1, p2) = (Point3::new(-1.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0),
+                            Point3::new(0.0, 0.0, 1.0));
+        let in_plane = Ray { p: Point3::new(0.0, 0.0, -1.0), d: Vec3::new(0.0, 0.0, 1.0) };
+        assert!(intersect_triangle(&in_plane, p0, p1, p2).is_none());
+        let still = Ray { p: Point3::new(0.0, -1.0, 0.5), d: Vec3::zeroes() };
+        assert!(intersect_triangle(&still, p0, p1, p2).is_none());
+
+        // Through a vertex and along the edges of a tiny triangle.
+        let (t, b1, b2) = intersect_triangle(&ray, p, Point3::new(1e-20, 0.0, 2.0),
+                                             Point3::new(0.0, 1e-20, 2.0)).unwrap();
+        assert_eq!((t, b1, b2), (2.0, 0.0, 0.0));
+    }
+
+    // Closed meshes built from separate triangles that share their vertices exactly.
+    fn octahedron() -> Vec<Triangle> {
+        let axes = [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)];
+        let mut tris = Vec::new();
+        for &sx in &[-1.0, 1.0] {
+            for &sy in &[-1.0, 1.0] {
+                for &sz in &[-1.0, 1.0] {
+                    tris.push(Triangle {
+                        p0: Point3::zeroes() + sx * axes[0],
+                        p1: Point3::zeroes() + sy * axes[1],
+                        p2: Point3::zeroes() + sz * axes[2],
+                    });
+                }
+            }
+        }
+        tris
+    }
+
+    // A sphere tessellated into rings, with vertices that aren't exactly representable.
+    fn sphere(rings: usize, segments: usize) -> Vec<Triangle> {
+        let vertex = |i: usize, j: usize| {
+            let theta = std::f32::consts::PI * i as f32 / rings as f32;
+            let phi = 2.0 * std::f32::consts::PI * (j % segments) as f32 / segments as f32 + 0.1;
+            Point3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin())
+        };
+        let mut tris = Vec::new();
+        for i in 0..rings {
+            for j in 0..segments {
+                let (a, b) = (vertex(i, j), vertex(i, j + 1));
+                let (c, d) = (vertex(i + 1, j + 1), vertex(i + 1, j));
+                if i > 0 {
+                    tris.push(Triangle { p0: a, p1: b, p2: c });
+                }
+                if i + 1 < rings {
+                    tris.push(Triangle { p0: a, p1: c, p2: d });
+                }
+            }
+        }
+        tris
+    }
+
+    fn hits(tris: &[Triangle], ray: &Ray) -> bool {
+        tris.iter().any(|tri| tri.intersect(ray).is_some())
+    }
+
+    // Rays from inside closed meshes aimed exactly at the vertices and along the edges, where
+    // floating point errors can let them pass between neighbouring triangles.
+    #[test]
+    fn watertight_from_inside() {
+        let origins = [Point3::zeroes(), Point3::new(0.1, -0.2, 0.05),
+                       Point3::new(-0.3, 0.01, 0.2)];
+
+        for tris in &[octahedron(), sphere(7, 11), sphere(12, 17)] {
+            let mut targets = Vec::new();
+            for tri in tris {
+                for &(a, b) in &[(tri.p0, tri.p1), (tri.p1, tri.p2), (tri.p2, tri.p0)] {
+                    for k in 0..=8 {
+                        let s = k as f32 / 8.0;
+                        targets.push(a + (b - a) * s);
+                    }
+                }
+            }
+
+            let mut leaks = 0;
+            for &o in &origins {
+                for &target in &targets {
+                    if !hits(tris, &Ray { p: o, d: target - o }) {
+                        leaks += 1;
+                    }
+                }
+            }
+            assert_eq!(leaks, 0);
+        }
+
+        // A dense grid of directions, many of which lie in the planes of the octahedron's edges.
+        let tris = octahedron();
+        let n = 12;
+        let mut leaks = 0;
+        for i in -n..=n {
+            for j in -n..=n {
+                for k in -n..=n {
+                    if (i, j, k) == (0, 0, 0) {
+                        continue;
+                    }
+                    let d = Vec3::new(i as f32, j as f32, k as f32) / n as f32;
+                    if !hits(&tris, &Ray { p: Point3::zeroes(), d }) {
+                        leaks += 1;
+                    }
+                }
+            }
+        }
+        assert_eq!(leaks, 0);
+    }
+
+    // Parallel grids of rays through a cube made of two triangles per face. The grid lines
+    // fall exactly on the diagonals that split the faces.
+    #[test]
+    fn watertight_from_outside() {
+        let corner = |x: f32, y: f32, z: f32| Point3::new(x, y, z);
+        let mut tris = Vec::new();
+        for axis in 0..3 {
+            for &side in &[-1.0, 1.0] {
+                let mut quad = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].iter()
+                    .map(|&(a, b)| {
+                        let mut p = corner(0.0, 0.0, 0.0);
+                        p[axis] = side;
+                        p[(axis + 1) % 3] = a;
+                        p[(axis + 2) % 3] = b;
+                        p
+                    });
+                let (a, b, c, d) = (quad.next().unwrap(), quad.next().unwrap(),
+                                    quad.next().unwrap(), quad.next().unwrap());
+                tris.push(Triangle { p0: a, p1: b, p2: c });
+                tris.push(Triangle { p0: a, p1: c, p2: d });
+            }
+        }
+
+        let n = 32;
+        let mut leaks = 0;
+        for &d in &[Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.3, -0.2, -1.0),
+                    Vec3::new(0.7, 0.7, -1.0)] {
+            for i in 1..2 * n {
+                for j in 1..2 * n {
+                    let (x, y) = (i as f32 / n as f32 - 1.0, j as f32 / n as f32 - 1.0);
+                    // Start above the top face, shifted back along the ray so that it passes
+                    // through (x, y, 0) inside the cube.
+                    let p = corner(x, y, 0.0) - 3.0 * d;
+                    if !hits(&tris, &Ray { p, d }) {
+                        leaks += 1;
+                    }
+                }
+            }
         }
+        assert_eq!(leaks, 0);
     }
 }
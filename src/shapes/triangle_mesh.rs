@@ -0,0 +1,376 @@
+use std::sync::OnceLock;
+
+use crate::geometry::{Bounds3, Frame, Point3, Ray, Vec3};
+use crate::interaction::SurfaceInteraction;
+use crate::sampling::distribution::Distribution1D;
+use crate::sampling::warp::uniform_sample_triangle;
+use crate::shapes::triangle::{barycentrics, intersect_triangle};
+use crate::shapes::{Shape, ShapeSample};
+
+// A mesh of triangles that share their vertices. Each triangle is three consecutive entries of
+// |indices|. The per-vertex arrays other than |positions| are optional, and are either empty or
+// have an entry for every position. The whole mesh is a single shape whose parts are its
+// triangles, so a triangle only costs its three indices and the Bvh's reference to it.
+pub struct TriangleMesh {
+    pub indices: Vec<u32>,
+    pub positions: Vec<Point3>,
+    // Shading normals, which don't need to be normalized.
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f32, f32)>,
+    pub tangents: Vec<Vec3>,
+    // The distribution of the triangles' areas. It's only built once the mesh is sampled or its
+    // area is needed, which only happens for emissive meshes.
+    areas: OnceLock<Distribution1D>,
+}
+
+impl TriangleMesh {
+    pub fn new(indices: Vec<u32>, positions: Vec<Point3>, normals: Vec<Vec3>,
+               uvs: Vec<(f32, f32)>, tangents: Vec<Vec3>) -> Self {
+        assert!(indices.len().is_multiple_of(3));
+        assert!(indices.len() / 3 <= u32::MAX as usize);
+        assert!(indices.iter().all(|&i| (i as usize) < positions.len()));
+        for &len in &[normals.len(), uvs.len(), tangents.len()] {
+            assert!(len == 0 || len == positions.len());
+        }
+        TriangleMesh { indices, positions, normals, uvs, tangents, areas: OnceLock::new() }
+    }
+
+    pub fn num_triangles(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn vertices(&self, tri: u32) -> [usize; 3] {
+        let first = 3 * tri as usize;
+        let i = &self.indices[first..first + 3];
+        [i[0] as usize, i[1] as usize, i[2] as usize]
+    }
+
+    fn positions(&self, tri: u32) -> (Point3, Point3, Point3) {
+        let [i0, i1, i2] = self.vertices(tri);
+        let p = &self.positions;
+        (p[i0], p[i1], p[i2])
+    }
+
+    fn geometric_normal(&self, tri: u32) -> Vec3 {
+        let (p0, p1, p2) = self.positions(tri);
+        Vec3::normalize(Vec3::cross(p1 - p0, p2 - p0))
+    }
+
+    fn triangle_area(&self, tri: u32) -> f32 {
+        let (p0, p1, p2) = self.positions(tri);
+        0.5 * Vec3::cross(p1 - p0, p2 - p0).len()
+    }
+
+    // Interpolates the per-vertex |values| over triangle |tri| with the barycentric coordinates
+    // |b1| and |b2|, or returns None if the mesh doesn't have them.
+    fn interpolate(&self, values: &[Vec3], tri: u32, b1: f32, b2: f32) -> Option<Vec3> {
+        if values.is_empty() {
+            return None;
+        }
+        let [i0, i1, i2] = self.vertices(tri);
+        Some((1.0 - b1 - b2) * values[i0] + b1 * values[i1] + b2 * values[i2])
+    }
+
+    fn area_distribution(&self) -> &Distribution1D {
+        self.areas.get_or_init(|| {
+            let areas = (0..self.num_triangles() as u32)
+                .map(|tri| self.triangle_area(tri))
+                .collect::<Vec<_>>();
+            Distribution1D::new(&areas)
+        })
+    }
+
+    // Returns the triangle that |ray| hits first and the t of the hit, without computing the
+    // rest of the interaction.
+    fn closest_hit(&self, ray: &Ray) -> Option<(u32, f32)> {
+        (0..self.num_triangles() as u32)
+            .filter_map(|tri| {
+                let (p0, p1, p2) = self.positions(tri);
+                intersect_triangle(ray, p0, p1, p2).map(|(t, _, _)| (tri, t))
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+    }
+}
+
+impl Shape for TriangleMesh {
+    // Finds the closest triangle by testing all of them, which is what the Bvh avoids by
+    // intersecting the triangles as separate parts.
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction<'_>> {
+        let (tri, _) = self.closest_hit(ray)?;
+        self.intersect_part(tri, ray)
+    }
+
+    fn bounds(&self) -> Bounds3 {
+        self.positions.iter().fold(Bounds3::empty(), |b, &p| Bounds3::union_pt(b, p))
+    }
+
+    fn num_parts(&self) -> u32 {
+        self.num_triangles() as u32
+    }
+
+    fn part_bounds(&self, tri: u32) -> Bounds3 {
+        let (p0, p1, p2) = self.positions(tri);
+        Bounds3::union_pt(Bounds3::new(p0, p1), p2)
+    }
+
+    // If the mesh has normals, the interpolated one is the shading normal and keeps the
+    // orientation it was authored with. Otherwise the face normal faces the ray, like for
+    // Triangle, and the winding order decides which side is the front.
+    fn intersect_part(&self, tri: u32, ray: &Ray) -> Option<SurfaceInteraction<'_>> {
+        let (p0, p1, p2) = self.positions(tri);
+        let (t, b1, b2) = intersect_triangle(ray, p0, p1, p2)?;
+        let b0 = 1.0 - b1 - b2;
+
+        let [i0, i1, i2] = self.vertices(tri);
+        let (uv0, uv1, uv2) = if self.uvs.is_empty() {
+            ((0.0, 0.0), (1.0, 0.0), (0.0, 1.0))
+        } else {
+            (self.uvs[i0], self.uvs[i1], self.uvs[i2])
+        };
+        let uv = (b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0, b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1);
+
+        // Anything that's interpolated across the triangle changes linearly with u and v. Its
+        // derivatives follow from its differences |d02| and |d12| between the vertices, unless
+        // the texture coordinates are degenerate.
+        let duv02 = (uv0.0 - uv2.0, uv0.1 - uv2.1);
+        let duv12 = (uv1.0 - uv2.0, uv1.1 - uv2.1);
+        let det = duv02.0 * duv12.1 - duv02.1 * duv12.0;
+        let derivatives = |d02: Vec3, d12: Vec3| {
+            if det.abs() < 1e-9 {
+                return None;
+            }
+            Some(((duv12.1 * d02 - duv02.1 * d12) / det, (duv02.0 * d12 - duv12.0 * d02) / det))
+        };
+
+        let ng = self.geometric_normal(tri);
+        let (dpdu, dpdv) = derivatives(p0 - p2, p1 - p2).unwrap_or_else(|| {
+            let frame = Frame::from_normal(ng);
+            (frame.s, frame.t)
+        });
+        let mut si = SurfaceInteraction::new(ray, t, ng, uv, dpdu, dpdv, Vec3::zeroes(),
+                                             Vec3::zeroes());
+        si.face_ray();
+
+        let normal = match self.interpolate(&self.normals, tri, b1, b2) {
+            Some(normal) if normal.len_sq() > 0.0 => normal,
+            _ => return Some(si),
+        };
+        let len = normal.len();
+        let ns = normal / len;
+
+        // The derivatives of the interpolated normal, less the part that normalizing it removes.
+        let normals = &self.normals;
+        let (dndu, dndv) = derivatives(normals[i0] - normals[i2], normals[i1] - normals[i2])
+            .map(|(du, dv)| {
+                let project = |d: Vec3| (d - Vec3::dot(d, ns) * ns) / len;
+                (project(du), project(dv))
+            })
+            .unwrap_or((Vec3::zeroes(), Vec3::zeroes()));
+
+        // The shading derivatives of the position are the true ones projected onto the plane
+        // perpendicular to the shading normal, which keeps any skew of the texture mapping. The
+        // mesh's own tangents replace the direction of dpdu if it has any, except where they're
+        // zero.
+        let project = |d: Vec3| d - Vec3::dot(d, ns) * ns;
+        let dpdu_shading = match self.interpolate(&self.tangents, tri, b1, b2) {
+            Some(tangent) if tangent.len_sq() > 0.0 => {
+                dpdu.len() * Frame::from_normal_tangent(ns, tangent).s
+            },
+            _ => project(dpdu),
+        };
+        si.set_shading_geometry(ns, dpdu_shading, project(dpdv), dndu, dndv);
+        Some(si)
+    }
+
+    // Interpolates the texture coordinates of the triangle that |p| lies on, or falls back to
+    // its barycentric coordinates like Triangle. The triangle is the closest one whose edges
+    // |p| lies within, found by testing all of them.
+    fn uv(&self, p: Point3) -> (f32, f32) {
+        let on_triangle = (0..self.num_triangles() as u32)
+            .filter_map(|tri| {
+                let (p0, p1, p2) = self.positions(tri);
+                let (b1, b2) = barycentrics(p, p0, p1, p2);
+                let eps = 1e-4;
+                if b1 < -eps || b2 < -eps || b1 + b2 > 1.0 + eps {
+                    return None;
+                }
+                let dist = Vec3::dot(p - p0, self.geometric_normal(tri)).abs();
+                Some((dist, tri, b1, b2))
+            })
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        let (tri, b1, b2) = match on_triangle {
+            Some((_, tri, b1, b2)) => (tri, b1, b2),
+            None => return (0.0, 0.0),
+        };
+        if self.uvs.is_empty() {
+            return (b1, b2);
+        }
+
+        let [i0, i1, i2] = self.vertices(tri);
+        let uvs = &self.uvs;
+        let b0 = 1.0 - b1 - b2;
+        (b0 * uvs[i0].0 + b1 * uvs[i1].0 + b2 * uvs[i2].0,
+         b0 * uvs[i0].1 + b1 * uvs[i1].1 + b2 * uvs[i2].1)
+    }
+
+    fn area(&self) -> f32 {
+        let areas = self.area_distribution();
+        areas.integral() * self.num_triangles() as f32
+    }
+
+    // Picks a triangle in proportion to its area and samples it uniformly, with |u.0| reused
+    // for the point within the triangle.
+    fn sample(&self, u: (f32, f32)) -> ShapeSample {
+        let (x, _, tri) = self.area_distribution().sample_continuous(u.0);
+        let u0 = (x * self.num_triangles() as f32 - tri as f32).clamp(0.0, 1.0);
+
+        let tri = tri as u32;
+        let (p0, p1, p2) = self.positions(tri);
+        let (b0, b1) = uniform_sample_triangle((u0, u.1));
+        let p = p0 * b0 + p1 * b1 + p2 * (1.0 - b0 - b1);
+        ShapeSample { p, n: self.geometric_normal(tri), pdf: 1.0 / self.area() }
+    }
+
+    // Same as area_pdf_from(), but with the face normal that sample() uses rather than the
+    // shading normal.
+    fn pdf_from(&self, p: Point3, wi: Vec3) -> f32 {
+        let (tri, t) = match self.closest_hit(&Ray { p, d: wi }) {
+            Some(hit) => hit,
+            None => return 0.0,
+        };
+
+        let cos_theta = Vec3::dot(self.geometric_normal(tri), Vec3::normalize(wi)).abs();
+        if cos_theta == 0.0 {
+            return 0.0;
+        }
+        (t * wi).len_sq() / (cos_theta * self.area())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interaction::tests::check_derivatives;
+    use crate::material::{Material, MaterialKind};
+
+    // A unit square in the z = 0 plane split along its diagonal, with normals that tilt
+    // outwards along x like a cylinder would.
+    fn square(normals: bool, uvs: bool) -> TriangleMesh {
+        let positions = vec![Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0),
+                             Point3::new(1.0, 1.0, 0.0), Point3::new(0.0, 1.0, 0.0)];
+        let normals = if normals {
+            vec![Vec3::new(-1.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 1.0),
+                 Vec3::new(1.0, 0.0, 1.0), Vec3::new(-1.0, 0.0, 1.0)]
+        } else {
+            Vec::new()
+        };
+        let uvs = if uvs {
+            vec![(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)]
+        } else {
+            Vec::new()
+        };
+        TriangleMesh::new(vec![0, 1, 2, 0, 2, 3], positions, normals, uvs, Vec::new())
+    }
+
+    fn down(x: f32, y: f32) -> Ray {
+        Ray { p: Point3::new(x, y, 1.0), d: Vec3::new(0.0, 0.0, -1.0) }
+    }
+
+    #[test]
+    fn smooth_shading() {
+        let mesh = square(true, true);
+        assert_eq!(mesh.num_parts(), 2);
+
+        // In the middle the tilts cancel out, and they blend towards the edges.
+        let si = mesh.intersect(&down(0.5, 0.25)).unwrap();
+        assert!((si.t - 1.0).abs() < 1e-6);
+        assert!((si.shading.n - Vec3::new(0.0, 0.0, 1.0)).len() < 1e-6);
+        assert!((si.uv.0 - 1.0).abs() < 1e-6 && (si.uv.1 - 0.5).abs() < 1e-6);
+        assert_eq!(mesh.uv(si.p), si.uv);
+        let si = mesh.intersect(&down(0.75, 0.25)).unwrap();
+        assert!((si.shading.n - Vec3::normalize(Vec3::new(0.5, 0.0, 1.0))).len() < 1e-6);
+        assert_eq!(si.n, Vec3::new(0.0, 0.0, 1.0));
+
+        // The texture coordinates are twice the positions.
+        assert!((si.dpdu - Vec3::new(0.5, 0.0, 0.0)).len() < 1e-6);
+        assert!((si.dpdv - Vec3::new(0.0, 0.5, 0.0)).len() < 1e-6);
+        check_derivatives(&mesh, &down(0.6, 0.3));
+
+        // The shading normal keeps its orientation when the triangle is hit from behind, and
+        // the true normal follows it.
+        let up = Ray { p: Point3::new(0.25, 0.75, -1.0), d: Vec3::new(0.0, 0.0, 1.0) };
+        assert!(mesh.intersect_part(0, &up).is_none());
+        let si = mesh.intersect_part(1, &up).unwrap();
+        assert!((si.shading.n - Vec3::normalize(Vec3::new(-0.5, 0.0, 1.0))).len() < 1e-6);
+        assert_eq!(si.n, Vec3::new(0.0, 0.0, 1.0));
+        assert!((si.uv.0 - 0.5).abs() < 1e-6 && (si.uv.1 - 1.5).abs() < 1e-6);
+        assert_eq!(mesh.intersect(&up).unwrap().t, si.t);
+
+        // Without texture coordinates, v runs towards the third vertex at an angle to u. The
+        // shading derivatives keep that skew, projected onto the shading plane.
+        let mesh = square(true, false);
+        let si = mesh.intersect(&down(0.75, 0.25)).unwrap();
+        let ns = si.shading.n;
+        let (dpdu, dpdv) = (Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!((si.dpdu, si.dpdv), (dpdu, dpdv));
+        assert!((si.shading.dpdu - (dpdu - Vec3::dot(dpdu, ns) * ns)).len() < 1e-6);
+        assert!((si.shading.dpdv - (dpdv - Vec3::dot(dpdv, ns) * ns)).len() < 1e-6);
+        check_derivatives(&mesh, &down(0.6, 0.3));
+
+        // Tangents turn the shading dpdu without changing its length, unless they're zero.
+        let mut mesh = square(true, true);
+        mesh.tangents = vec![Vec3::new(0.0, 1.0, 0.0); 4];
+        let si = mesh.intersect(&down(0.5, 0.25)).unwrap();
+        assert!((si.shading.dpdu - Vec3::new(0.0, 0.5, 0.0)).len() < 1e-6);
+        mesh.tangents = vec![Vec3::zeroes(); 4];
+        let si = mesh.intersect(&down(0.5, 0.25)).unwrap();
+        assert!((si.shading.dpdu - Vec3::new(0.5, 0.0, 0.0)).len() < 1e-6);
+    }
+
+    #[test]
+    fn flat_shading() {
+        let mesh = square(false, false);
+
+        // The face normal faces the ray, and the winding order makes +z the front.
+        let up = Ray { p: Point3::new(0.75, 0.25, -1.0), d: Vec3::new(0.0, 0.0, 1.0) };
+        let SurfaceInteraction { p, n, front_face, .. } = mesh.intersect(&up).unwrap();
+        assert_eq!((n, front_face), (Vec3::new(0.0, 0.0, -1.0), false));
+        let si = mesh.intersect(&down(0.75, 0.25)).unwrap();
+        assert_eq!((si.n, si.front_face), (Vec3::new(0.0, 0.0, 1.0), true));
+        let (b1, b2) = mesh.uv(p);
+        assert!((b1 - 0.5).abs() < 1e-6 && (b2 - 0.25).abs() < 1e-6);
+        let si = mesh.intersect(&up).unwrap();
+        assert_eq!((si.dpdu, si.dpdv), (Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0)));
+        assert_eq!(si.shading.n, si.n);
+
+        // Light leaving glass through the square at 60 degrees is totally reflected back in,
+        // while light coming in at the same angle can pass through.
+        let glass = Material {
+            kind: MaterialKind::Dielectric { ior: 1.5, roughness: 0.0, anisotropy: 0.0 },
+            ..Material::default()
+        };
+        let d = Vec3::new(60.0f32.to_radians().sin(), 0.0, 60.0f32.to_radians().cos());
+        let out = Ray { p: Point3::new(0.25, 0.1, -0.25), d };
+        let into = Ray { p: Point3::new(0.25, 0.1, 0.25), d: Vec3::new(d.x, d.y, -d.z) };
+        for (ray, passes) in &[(out, false), (into, true)] {
+            let si = mesh.intersect(ray).unwrap();
+            let bsdf = glass.bsdf(si.shading_frame());
+            let wi = bsdf.sample_f(si.wo, (0.99, 0.5)).unwrap().wi;
+            assert_eq!(wi.z * ray.d.z > 0.0, *passes);
+        }
+
+        // Each triangle is half of the square, so the first half of u.0 samples the one below
+        // the diagonal.
+        assert_eq!(mesh.area(), 1.0);
+        let b = mesh.part_bounds(1);
+        assert_eq!((b.min, b.max), (Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 0.0)));
+        assert_eq!(mesh.bounds().max, Point3::new(1.0, 1.0, 0.0));
+        for &(u, below) in &[((0.1, 0.2), true), ((0.45, 0.5), true), ((0.9, 0.3), false)] {
+            let s = mesh.sample(u);
+            assert!(s.p.z == 0.0 && s.n.z.abs() == 1.0 && s.pdf == 1.0);
+            assert_eq!(s.p.x >= s.p.y, below);
+            let pdf = mesh.pdf_from(Point3::new(s.p.x, s.p.y, 2.0), Vec3::new(0.0, 0.0, -1.0));
+            assert!((pdf - 4.0).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use crate::accel::Bvh;
+use crate::lights::{AreaLight, Light};
+use crate::object::Object;
+
+// Everything that rays interact with during rendering.
+pub struct World {
+    pub bvh: Bvh,
+    // The lights from the scene, followed by an area light for each object with an emissive
+    // material. Unbounded objects, such as planes, can't be sampled and only glow when they're
+    // hit.
+    pub lights: Vec<Box<dyn Light>>,
+}
+
+impl World {
+    pub fn new(objs: Vec<Object>, mut lights: Vec<Box<dyn Light>>) -> Self {
+        for obj in &objs {
+            if !obj.material.emission.is_black() && obj.shape.area().is_finite() {
+                lights.push(Box::new(AreaLight {
+                    shape: obj.shape.clone(),
+                    radiance: obj.material.emission,
+                }));
+            }
+        }
+
+        let bvh = Bvh::new(objs);
+        for light in &mut lights {
+            light.preprocess(bvh.bounds());
+        }
+
+        World { bvh, lights }
+    }
+}